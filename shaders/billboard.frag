@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 tex_coords;
+
+out vec4 color;
+
+uniform sampler2D sprite;
+uniform vec4 tint;
+
+void main() {
+    color = texture(sprite, tex_coords) * tint;
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+in vec2 corner;
+
+out vec2 tex_coords;
+
+uniform mat4 view;
+uniform mat4 projection;
+uniform vec3 centre;
+uniform float size;
+
+void main() {
+    // The camera's right and up, from the rows of the view matrix.
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    vec3 position = centre + (right * corner.x + up * corner.y) * size;
+    gl_Position = projection * view * vec4(position, 1.0);
+
+    tex_coords = corner * 0.5 + 0.5;
+}
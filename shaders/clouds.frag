@@ -0,0 +1,18 @@
+#version 330 core
+
+in vec2 uv;
+in vec2 across;
+
+out vec4 color;
+
+uniform sampler2D clouds;
+uniform vec3 tint;
+uniform float alpha;
+uniform float reach;
+
+void main() {
+    vec4 texel = texture(clouds, uv);
+    // Thin out towards the edge of the layer, which would otherwise be a hard line.
+    float fade = 1.0 - smoothstep(reach * 0.5, reach, length(across));
+    color = vec4(vec3(texel) * tint, texel.a * alpha * fade);
+}
@@ -0,0 +1,25 @@
+#version 330 core
+
+in vec2 corner;
+
+out vec2 uv;
+// From the eye across the layer.
+out vec2 across;
+
+uniform mat4 view;
+uniform mat4 projection;
+uniform vec3 eye;
+uniform float height;
+uniform float reach;
+uniform float tile;
+uniform vec2 offset;
+
+void main() {
+    // A square of sky centred over the eye, so the clouds never run out.
+    vec2 xz = eye.xz + corner * reach;
+    gl_Position = projection * view * vec4(xz.x, height, xz.y, 1.0);
+
+    // Tied to the world rather than the eye, so the clouds stay put as it moves.
+    uv = (xz - offset) / tile;
+    across = corner * reach;
+}
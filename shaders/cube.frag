@@ -4,8 +4,11 @@ in VERTEX_OUTPUT
 {
     vec3 frag_pos;
     vec3 normal;
-    vec3 light_pos;
+    vec3 light_dir;
     vec2 tex_coords;
+    vec4 tile;
+    float shine;
+    float alpha;
 } vtx_in;
 
 out vec4 color;
@@ -15,24 +18,40 @@ uniform vec3 light_ambient;
 uniform vec3 light_diffuse;
 uniform vec3 light_specular;
 uniform float shine;
+uniform float cutoff;
 uniform sampler2D diffuse_map;
 uniform sampler2D specular_map;
 
 void main() {
+    // Texture coordinates count repeats of the tile, so merged faces show it
+    // once per block. Gradients are taken before wrapping so mipmaps don't
+    // jump at the seams.
+    vec2 span = vtx_in.tile.zw - vtx_in.tile.xy;
+    vec2 uv = vtx_in.tile.xy + fract(vtx_in.tex_coords) * span;
+    vec2 dx = dFdx(vtx_in.tex_coords) * span;
+    vec2 dy = dFdy(vtx_in.tex_coords) * span;
+
+    vec4 texel = textureGrad(diffuse_map, uv, dx, dy);
+    if (texel.a < cutoff) {
+        discard;
+    }
+
     // ambient
-    vec3 ambient = light_ambient * vec3(texture(diffuse_map, vtx_in.tex_coords));
+    vec3 ambient = light_ambient * vec3(texel);
 
     // diffuse
     vec3 norm = normalize(vtx_in.normal);
-    vec3 light_dir = normalize(vtx_in.light_pos - vtx_in.frag_pos);
+    vec3 light_dir = normalize(vtx_in.light_dir);
     float diff = max(dot(norm, light_dir), 0.0);
-    vec3 diffuse = light_diffuse * diff * vec3(texture(diffuse_map, vtx_in.tex_coords));
+    vec3 diffuse = light_diffuse * diff * vec3(texel);
 
     // specular
     vec3 view_dir = normalize(-vtx_in.frag_pos);
     vec3 reflect_dir = reflect(-light_dir, norm);
-    float spec = pow(max(dot(view_dir, reflect_dir), 0.0), shine);
-    vec3 specular = light_specular * spec * vec3(texture(specular_map, vtx_in.tex_coords));
+    // Surfaces without a shine of their own use the uniform.
+    float surface_shine = vtx_in.shine > 0.0 ? vtx_in.shine : shine;
+    float spec = pow(max(dot(view_dir, reflect_dir), 0.0), surface_shine);
+    vec3 specular = light_specular * spec * vec3(textureGrad(specular_map, uv, dx, dy));
 
-    color = vec4(ambient + diffuse + specular, 1.0);
+    color = vec4(ambient + diffuse + specular, texel.a * vtx_in.alpha);
 }
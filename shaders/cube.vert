@@ -3,27 +3,37 @@
 in vec3 position;
 in vec3 normal;
 in vec2 tex_coords;
+in vec4 tile;
+in float surface_shine;
+in float surface_alpha;
 
 out VERTEX_OUTPUT
 {
     vec3 frag_pos;
     vec3 normal;
-    vec3 light_pos;
+    vec3 light_dir;
     vec2 tex_coords;
+    vec4 tile;
+    float shine;
+    float alpha;
 } vtx_out;
 
 
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
-uniform vec3 light_pos;
+// Towards the light, which is far enough away to shine the same way everywhere.
+uniform vec3 light_dir;
 
 void main() {
     gl_Position = projection * view * model * vec4(position, 1.0);
 
     vtx_out.tex_coords = tex_coords;
+    vtx_out.tile = tile;
+    vtx_out.shine = surface_shine;
+    vtx_out.alpha = surface_alpha;
 
     vtx_out.frag_pos = vec3(view * model * vec4(position, 1.0));
     vtx_out.normal = mat3(transpose(inverse(view * model))) * normal;
-    vtx_out.light_pos = vec3(view * vec4(light_pos, 1.0));
+    vtx_out.light_dir = mat3(view) * light_dir;
 }
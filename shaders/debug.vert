@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec3 position;
+in vec3 color;
+
+out vec3 Color;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    gl_Position = projection * view * vec4(position, 1.0);
+    Color = color;
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec2 tex_coords;
+in vec4 particle_tint;
+
+out vec4 color;
+
+uniform sampler2D sprite;
+
+void main() {
+    color = texture(sprite, tex_coords) * particle_tint;
+}
@@ -0,0 +1,24 @@
+#version 330 core
+
+in vec2 corner;
+// Per particle.
+in vec3 centre;
+in float size;
+in vec4 tint;
+
+out vec2 tex_coords;
+out vec4 particle_tint;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    // The camera's right and up, from the rows of the view matrix.
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    vec3 position = centre + (right * corner.x + up * corner.y) * size;
+    gl_Position = projection * view * vec4(position, 1.0);
+
+    tex_coords = corner * 0.5 + 0.5;
+    particle_tint = tint;
+}
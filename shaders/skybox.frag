@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec3 direction;
+
+out vec4 color;
+
+uniform samplerCube sky;
+// The clear colour, which the sky fades into as the daylight goes.
+uniform vec3 tint;
+uniform float daylight;
+
+void main() {
+    color = vec4(mix(tint, vec3(texture(sky, direction)), daylight), 1.0);
+}
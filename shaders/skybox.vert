@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec3 position;
+
+out vec3 direction;
+
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+    // Only the turn of the view, so the box stays centred on the eye.
+    vec4 pos = projection * mat4(mat3(view)) * vec4(position, 1.0);
+    // As far away as can be drawn, so everything else is in front.
+    gl_Position = pos.xyww;
+    direction = position;
+}
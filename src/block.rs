@@ -0,0 +1,516 @@
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use resource::atlas::Atlas;
+use world::{
+    BlockId,
+    Face,
+    AIR,
+};
+use world::mesh::{
+    BlockTypes,
+    Shape,
+    TileId,
+};
+use world::shape::Connected;
+use inventory::item::{
+    self,
+    Tool,
+    TIERS,
+};
+use errors::{
+    Result,
+    Error,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Opacity {
+    // Hides neighbouring faces.
+    Opaque,
+    // Fully opaque or fully transparent per texel, like leaves.
+    Cutout,
+    // Blended, like glass and water.
+    Translucent,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockType {
+    name: String,
+    // Tile names indexed like `Face::all()`.
+    textures: [String; 6],
+    tiles: [TileId; 6],
+    specular: Option<String>,
+    shine: Option<f32>,
+    opacity: Opacity,
+    alpha: f32,
+    hardness: f32,
+    tools: Vec<Tool>,
+    tier: u8,
+    drop: Option<String>,
+    group: Option<String>,
+    shape: Shape,
+    connected: Option<Connected>,
+    rotation: u8,
+}
+
+impl BlockType {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn texture(&self, face: Face) -> &str {
+        &self.textures[face_index(face)]
+    }
+
+    pub fn tile(&self, face: Face) -> TileId {
+        self.tiles[face_index(face)]
+    }
+
+    // Tile used for this block's specular map, if it has one.
+    pub fn specular(&self) -> Option<&str> {
+        self.specular.as_ref().map(|s| &s[..])
+    }
+
+    // Specular exponent. Blocks without their own use the world's.
+    pub fn shine(&self) -> Option<f32> {
+        self.shine
+    }
+
+    pub fn opacity(&self) -> Opacity {
+        self.opacity
+    }
+
+    // Opacity of translucent blocks on top of their texture's, so each can be
+    // blended by its own amount.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub fn hardness(&self) -> f32 {
+        self.hardness
+    }
+
+    // Tools that break this block faster than a bare hand.
+    pub fn tools(&self) -> &[Tool] {
+        &self.tools
+    }
+
+    // Lowest tier of one of `tools` the block drops anything for, 0 if it
+    // always drops.
+    pub fn tier(&self) -> u8 {
+        self.tier
+    }
+
+    // Name of the item dropped when broken. `None` drops nothing.
+    pub fn drop(&self) -> Option<&str> {
+        self.drop.as_ref().map(|s| &s[..])
+    }
+
+    // Name of the block this one is a variant of, such as a fluid's source.
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_ref().map(|s| &s[..])
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    // Geometry of connected blocks.
+    pub fn connected(&self) -> Option<&Connected> {
+        self.connected.as_ref()
+    }
+
+    // Quarter turns applied to the textures.
+    pub fn rotation(&self) -> u8 {
+        self.rotation
+    }
+}
+
+#[cfg(test)]
+impl BlockType {
+    // An opaque cube of hardness 1 that any tool breaks and drops itself, to
+    // build on with the `with_` methods. Its faces are all tile 0, so it is
+    // only fit for tests; real blocks come from `Registry::load`.
+    pub fn new(name: &str) -> BlockType {
+        let texture = String::from(name);
+        BlockType {
+            name: String::from(name),
+            textures: [texture.clone(), texture.clone(), texture.clone(),
+                       texture.clone(), texture.clone(), texture],
+            tiles: [0; 6],
+            specular: None,
+            shine: None,
+            opacity: Opacity::Opaque,
+            alpha: 1.0,
+            hardness: 1.0,
+            tools: Vec::new(),
+            tier: 0,
+            drop: Some(String::from(name)),
+            group: None,
+            shape: Shape::Cube,
+            connected: None,
+            rotation: 0,
+        }
+    }
+
+    pub fn with_hardness(self, hardness: f32) -> BlockType {
+        BlockType {
+            hardness: hardness,
+            ..self
+        }
+    }
+
+    // Breaks faster with `tools`, and drops nothing without one at least
+    // `tier`.
+    pub fn with_tools(self, tools: Vec<Tool>, tier: u8) -> BlockType {
+        BlockType {
+            tools: tools,
+            tier: tier,
+            ..self
+        }
+    }
+}
+
+// Block types in the order they are declared, which fixes their ids. Air is
+// always id 0 and is not declared in the file, so entries must only ever be
+// appended to keep saved worlds valid.
+pub struct Registry {
+    blocks: Vec<Option<BlockType>>,
+    names: HashMap<String, BlockId>,
+    groups: Vec<BlockId>,
+}
+
+impl Registry {
+    // Tile names are checked against `atlas`, and specular maps against the
+    // images in `tiles`.
+    pub fn load<P, Q>(path: P, atlas: &Atlas, tiles: Q) -> Result<Registry>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let entries = match table.remove("blocks") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => {
+                let desc = "`blocks` must be an array of tables";
+                return Err(config::error_at(path, &src, 0, desc));
+            },
+            None => Vec::new(),
+        };
+
+        let mut registry = Registry {
+            blocks: vec![None],
+            names: HashMap::new(),
+            groups: vec![AIR],
+        };
+        registry.names.insert(String::from("air"), AIR);
+
+        for (i, entry) in entries.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "blocks", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_block = match TomlBlockType::decode(&mut decoder) {
+                Ok(b) => b,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let block = match toml_block.into_block_type(atlas, tiles.as_ref()) {
+                Ok(b) => b,
+                Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+            };
+
+            if registry.names.contains_key(&block.name) {
+                let desc = format!("duplicate block `{}`", block.name);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            if registry.blocks.len() > BlockId::max_value() as usize {
+                return Err(config::error_at(path, &src, offset, "too many block types"));
+            }
+            let id = registry.blocks.len() as BlockId;
+            let group = match block.group() {
+                Some(group) => match registry.id(group) {
+                    Some(g) => g,
+                    None => {
+                        let desc = format!("block `{}` is in group `{}`, which must be \
+                                            declared before it", block.name, group);
+                        return Err(config::error_at(path, &src, offset, desc));
+                    },
+                },
+                None => id,
+            };
+            registry.groups.push(group);
+            registry.names.insert(block.name.clone(), id);
+            registry.blocks.push(Some(block));
+        }
+
+        Ok(registry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.names.get(name).cloned()
+    }
+
+    // Like `id`, for blocks the game cannot run without.
+    pub fn require(&self, name: &str) -> Result<BlockId> {
+        self.id(name).ok_or(Error::with_detail("block error",
+                                               format!("no block named `{}`", name)))
+    }
+
+    // `None` for air and unknown ids.
+    pub fn get(&self, id: BlockId) -> Option<&BlockType> {
+        match self.blocks.get(id as usize) {
+            Some(&Some(ref block)) => Some(block),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self, id: BlockId) -> &str {
+        match self.get(id) {
+            Some(block) => block.name(),
+            None => "air",
+        }
+    }
+
+    pub fn opacity(&self, id: BlockId) -> Option<Opacity> {
+        self.get(id).map(|b| b.opacity())
+    }
+
+    // The specular tile to use for each diffuse tile, taken from the first block
+    // that uses the tile and has a specular map.
+    pub fn specular_tiles(&self) -> HashMap<String, String> {
+        let mut tiles = HashMap::new();
+        for block in self.blocks.iter().filter_map(|b| b.as_ref()) {
+            if let Some(specular) = block.specular() {
+                for texture in block.textures.iter() {
+                    if !tiles.contains_key(texture) {
+                        tiles.insert(texture.clone(), String::from(specular));
+                    }
+                }
+            }
+        }
+        tiles
+    }
+}
+
+impl BlockTypes for Registry {
+    fn is_opaque(&self, id: BlockId) -> bool {
+        self.opacity(id) == Some(Opacity::Opaque)
+    }
+
+    fn tile(&self, id: BlockId, face: Face) -> TileId {
+        match self.get(id) {
+            Some(block) => block.tile(face),
+            None => 0,
+        }
+    }
+
+    fn is_translucent(&self, id: BlockId) -> bool {
+        self.opacity(id) == Some(Opacity::Translucent)
+    }
+
+    fn is_cutout(&self, id: BlockId) -> bool {
+        self.opacity(id) == Some(Opacity::Cutout)
+    }
+
+    fn group(&self, id: BlockId) -> BlockId {
+        self.groups.get(id as usize).cloned().unwrap_or(id)
+    }
+
+    fn shape(&self, id: BlockId) -> Shape {
+        self.get(id).map_or(Shape::Cube, |b| b.shape())
+    }
+
+    fn rotation(&self, id: BlockId) -> u8 {
+        self.get(id).map_or(0, |b| b.rotation())
+    }
+
+    fn shine(&self, id: BlockId) -> Option<f32> {
+        self.get(id).and_then(|b| b.shine())
+    }
+
+    fn alpha(&self, id: BlockId) -> f32 {
+        self.get(id).map_or(1.0, |b| b.alpha())
+    }
+
+    fn connected(&self, id: BlockId) -> Option<&Connected> {
+        self.get(id).and_then(|b| b.connected())
+    }
+}
+
+fn face_index(face: Face) -> usize {
+    match face {
+        Face::Left => 0,
+        Face::Right => 1,
+        Face::Bottom => 2,
+        Face::Top => 3,
+        Face::Back => 4,
+        Face::Front => 5,
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlBlockType {
+    name: String,
+    texture: Option<String>,
+    side: Option<String>,
+    top: Option<String>,
+    bottom: Option<String>,
+    specular: Option<String>,
+    shine: Option<f32>,
+    opacity: Option<String>,
+    alpha: Option<f32>,
+    hardness: Option<f32>,
+    tools: Option<Vec<String>>,
+    tier: Option<String>,
+    drop: Option<String>,
+    group: Option<String>,
+    shape: Option<String>,
+    rotation: Option<u8>,
+    post: Option<i32>,
+    arm: Option<i32>,
+    bars: Option<Vec<Vec<i32>>>,
+    collision: Option<i32>,
+}
+
+impl TomlBlockType {
+    fn into_block_type(self, atlas: &Atlas, tiles_dir: &Path)
+                       -> ::std::result::Result<BlockType, String>
+    {
+        let name = self.name;
+        let side = try!(self.side.clone().or(self.texture.clone())
+                        .ok_or(format!("block `{}` needs `texture` or `side`", name)));
+        let top = self.top.clone().unwrap_or(side.clone());
+        let bottom = self.bottom.clone().unwrap_or(side.clone());
+
+        let textures = [side.clone(), side.clone(), bottom, top, side.clone(), side];
+        let mut tiles = [0; 6];
+        for (i, texture) in textures.iter().enumerate() {
+            tiles[i] = try!(atlas.tile(texture)
+                            .ok_or(format!("block `{}` uses unknown tile `{}`", name, texture)));
+        }
+
+        if let Some(ref specular) = self.specular {
+            if !tiles_dir.join(format!("{}.png", specular)).exists() {
+                return Err(format!("block `{}` uses missing specular map `{}`", name, specular));
+            }
+        }
+
+        let opacity = match self.opacity.as_ref().map(|s| &s[..]) {
+            None | Some("opaque") => Opacity::Opaque,
+            Some("cutout") => Opacity::Cutout,
+            Some("translucent") => Opacity::Translucent,
+            Some(other) => return Err(format!("block `{}` has unknown opacity `{}`, expected \
+                                               `opaque`, `cutout` or `translucent`",
+                                              name, other)),
+        };
+
+        let alpha = self.alpha.unwrap_or(1.0);
+        if alpha <= 0.0 || alpha > 1.0 {
+            return Err(format!("block `{}` has alpha outside 0 to 1", name));
+        }
+        if alpha < 1.0 && opacity != Opacity::Translucent {
+            return Err(format!("block `{}` needs to be translucent to have an alpha", name));
+        }
+
+        let shape = match self.shape.as_ref().map(|s| &s[..]) {
+            None | Some("cube") => Shape::Cube,
+            Some("flat") => Shape::Flat,
+            Some("connected") => Shape::Connected,
+            Some(other) => return Err(format!("block `{}` has unknown shape `{}`, expected \
+                                               `cube`, `flat` or `connected`", name, other)),
+        };
+
+        let connected = if shape == Shape::Connected {
+            let post = self.post.unwrap_or(2);
+            let arm = self.arm.unwrap_or(1);
+            if post < 1 || post > 8 || arm < 1 || arm > 8 {
+                return Err(format!("block `{}` needs `post` and `arm` from 1 to 8", name));
+            }
+            let mut bars = Vec::new();
+            for bar in self.bars.clone().unwrap_or(vec![vec![0, 16]]) {
+                if bar.len() != 2 || bar[0] < 0 || bar[0] >= bar[1] || bar[1] > 16 {
+                    return Err(format!("block `{}` has a bar that is not `[bottom, top]` \
+                                        within 0 to 16", name));
+                }
+                bars.push((bar[0], bar[1]));
+            }
+            Some(Connected::new(post, arm, bars, self.collision.unwrap_or(16)))
+        } else {
+            None
+        };
+
+        let rotation = self.rotation.unwrap_or(0);
+        if rotation > 3 {
+            return Err(format!("block `{}` has rotation outside 0 to 3", name));
+        }
+
+        let hardness = self.hardness.unwrap_or(1.0);
+        if hardness < 0.0 {
+            return Err(format!("block `{}` has negative hardness", name));
+        }
+        if self.shine.map_or(false, |s| s <= 0.0) {
+            return Err(format!("block `{}` has a shine that is not positive", name));
+        }
+
+        let mut tools = Vec::new();
+        for tool in self.tools.clone().unwrap_or(Vec::new()) {
+            match Tool::from_name(&tool) {
+                Some(t) => tools.push(t),
+                None => return Err(format!("block `{}` has unknown tool `{}`", name, tool)),
+            }
+        }
+        let tier = match self.tier {
+            Some(ref t) => match item::tier(t) {
+                Some(t) => t,
+                None => return Err(format!("block `{}` has unknown tier `{}`, expected one of \
+                                            {}", name, t, TIERS.join(", "))),
+            },
+            None => 0,
+        };
+        if tier > 0 && tools.is_empty() {
+            return Err(format!("block `{}` needs `tools` to go with its `tier`", name));
+        }
+
+        // Blocks drop themselves unless told otherwise; an empty string drops nothing.
+        let drop = match self.drop {
+            Some(ref d) if d.is_empty() => None,
+            Some(d) => Some(d),
+            None => Some(name.clone()),
+        };
+
+        Ok(BlockType {
+            name: name,
+            textures: textures,
+            tiles: tiles,
+            specular: self.specular,
+            shine: self.shine,
+            opacity: opacity,
+            alpha: alpha,
+            hardness: hardness,
+            tools: tools,
+            tier: tier,
+            drop: drop,
+            group: self.group,
+            shape: shape,
+            connected: connected,
+            rotation: rotation,
+        })
+    }
+}
@@ -45,14 +45,29 @@ impl Camera {
         self.elev += -deltay;
     }
 
-    pub fn look_at(&self, pos: Vec3) -> Mat4 {
+    // Where the camera sits when looking at `pos`.
+    pub fn eye(&self, pos: Vec3) -> Vec3 {
         let elevation = gel::radians(self.elev);
         let azimuth = gel::radians(self.azimuth);
         let mut position = pos;
         position.x += self.radius * elevation.cos() * azimuth.cos();
         position.y += self.radius * elevation.sin();
         position.z += self.radius * elevation.cos() * azimuth.sin();
-        Mat4::look_at(position, pos, self.up)
+        position
+    }
+
+    // The unit direction the camera looks in, leaving out its tilt.
+    pub fn heading(&self) -> Vec3 {
+        let azimuth = gel::radians(self.azimuth);
+        Vec3::new(-azimuth.cos(), 0.0, -azimuth.sin())
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn look_at(&self, pos: Vec3) -> Mat4 {
+        Mat4::look_at(self.eye(pos), pos, self.up)
     }
 }
 
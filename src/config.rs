@@ -4,6 +4,7 @@ use std::path::{
 };
 use std::io::prelude::*;
 use std::fs::File;
+use std::fmt;
 
 use toml::{
     self,
@@ -36,6 +37,18 @@ pub struct Config {
     camera: CameraParams,
     player: PlayerParams,
     light: LightParams,
+    atlas: AtlasParams,
+    world: WorldParams,
+    terrain: TerrainParams,
+    underground: UndergroundParams,
+    ticks: TickParams,
+    fluids: FluidParams,
+    minecart: MinecartParams,
+    mining: MiningParams,
+    mobs: MobsParams,
+    day: DayParams,
+    skybox: SkyboxParams,
+    sky: SkyParams,
 }
 
 impl Config {
@@ -50,6 +63,7 @@ impl Config {
         let mut decoder = Decoder::new(Value::Table(table));
 
         let toml_config = try!(TomlConfig::decode(&mut decoder));
+        try!(toml_config.validate(path.as_ref(), &buf));
         let config: Config = toml_config.into();
         Ok(config)
     }
@@ -77,6 +91,54 @@ impl Config {
     pub fn light(&self) -> &LightParams {
         &self.light
     }
+
+    pub fn atlas(&self) -> &AtlasParams {
+        &self.atlas
+    }
+
+    pub fn world(&self) -> &WorldParams {
+        &self.world
+    }
+
+    pub fn terrain(&self) -> &TerrainParams {
+        &self.terrain
+    }
+
+    pub fn underground(&self) -> &UndergroundParams {
+        &self.underground
+    }
+
+    pub fn ticks(&self) -> &TickParams {
+        &self.ticks
+    }
+
+    pub fn fluids(&self) -> &FluidParams {
+        &self.fluids
+    }
+
+    pub fn minecart(&self) -> &MinecartParams {
+        &self.minecart
+    }
+
+    pub fn mining(&self) -> &MiningParams {
+        &self.mining
+    }
+
+    pub fn mobs(&self) -> &MobsParams {
+        &self.mobs
+    }
+
+    pub fn day(&self) -> &DayParams {
+        &self.day
+    }
+
+    pub fn skybox(&self) -> &SkyboxParams {
+        &self.skybox
+    }
+
+    pub fn sky(&self) -> &SkyParams {
+        &self.sky
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -138,20 +200,21 @@ impl WindowParams {
 
 #[derive(Debug, Clone)]
 pub struct PlayerParams {
-    diffuse_map: PathBuf,
-    specular_map: PathBuf,
+    model: String,
     shine: f32,
     scale: f32,
     speed: f32,
+    reach: f32,
+    health: u32,
+    damage: u32,
+    slots: usize,
+    items: Vec<String>,
 }
 
 impl PlayerParams {
-    pub fn diffuse_map(&self) -> &Path {
-        &self.diffuse_map
-    }
-
-    pub fn specular_map(&self) -> &Path {
-        &self.specular_map
+    // Name of the model the player is drawn with.
+    pub fn model(&self) -> &str {
+        &self.model
     }
 
     pub fn shine(&self) -> f32 {
@@ -165,37 +228,243 @@ impl PlayerParams {
     pub fn speed(&self) -> f32 {
         self.speed
     }
+
+    // How far from the player blocks can be broken and placed.
+    pub fn reach(&self) -> f32 {
+        self.reach
+    }
+
+    // Health of a player who has not been hurt.
+    pub fn health(&self) -> u32 {
+        self.health
+    }
+
+    // Health taken off a mob by hitting it bare handed. Swords multiply it by
+    // their speed.
+    pub fn damage(&self) -> u32 {
+        self.damage
+    }
+
+    // Size of the inventory, hotbar included.
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    // Items a new player starts with a full stack of each of, in hotbar order.
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
 }
 
 #[derive(Debug, Clone)]
-pub struct LightParams {
+pub struct AtlasParams {
+    tiles: PathBuf,
+    padding: u32,
+    dump: Option<PathBuf>,
+}
+
+impl AtlasParams {
+    pub fn tiles(&self) -> &Path {
+        &self.tiles
+    }
+
+    pub fn padding(&self) -> u32 {
+        self.padding
+    }
+
+    pub fn dump(&self) -> Option<&Path> {
+        self.dump.as_ref().map(|p| p.as_path())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorldParams {
+    shine: f32,
+    radius: i32,
+    cutoff: f32,
+    greedy: bool,
+    save: Option<PathBuf>,
+}
+
+impl WorldParams {
+    pub fn shine(&self) -> f32 {
+        self.shine
+    }
+
+    pub fn radius(&self) -> i32 {
+        self.radius
+    }
+
+    // Texels of cutout blocks less opaque than this are not drawn.
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    // Whether chunk meshes merge coplanar faces into larger quads.
+    pub fn greedy(&self) -> bool {
+        self.greedy
+    }
+
+    // Directory region files are kept in. Nothing is saved when unset.
+    pub fn save(&self) -> Option<&Path> {
+        self.save.as_ref().map(|p| p.as_path())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TerrainParams {
+    seed: u32,
+    base: i32,
+    amplitude: f32,
     scale: f32,
-    position: Vec3,
-    color: Vec3,
-    ambient: Vec3,
-    diffuse: Vec3,
-    specular: Vec3,
+    octaves: u32,
+    biome_scale: f32,
+    snow_line: i32,
+    sea_level: i32,
 }
 
-impl LightParams {
-    pub fn color(&self) -> Vec3 {
-        self.color
+impl TerrainParams {
+    // The default terrain for `seed`.
+    pub fn new(seed: u32) -> TerrainParams {
+        TerrainParams {
+            seed: seed,
+            base: 64,
+            amplitude: 24.0,
+            scale: 1.0 / 128.0,
+            octaves: 4,
+            biome_scale: 1.0 / 512.0,
+            snow_line: 84,
+            sea_level: 56,
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn base(&self) -> i32 {
+        self.base
+    }
+
+    pub fn amplitude(&self) -> f32 {
+        self.amplitude
     }
 
     pub fn scale(&self) -> f32 {
         self.scale
     }
 
-    pub fn position(&self) -> Vec3 {
-        self.position
+    pub fn octaves(&self) -> u32 {
+        self.octaves
     }
 
-    pub fn ambient(&self) -> Vec3 {
-        self.ambient
+    pub fn biome_scale(&self) -> f32 {
+        self.biome_scale
     }
 
-    pub fn diffuse(&self) -> Vec3 {
-        self.diffuse
+    pub fn snow_line(&self) -> i32 {
+        self.snow_line
+    }
+
+    // Ground lower than this is covered in water up to it.
+    pub fn sea_level(&self) -> i32 {
+        self.sea_level
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UndergroundParams {
+    cave_scale: f32,
+    cave_threshold: f32,
+    cave_margin: i32,
+    lava_level: i32,
+    ores: Vec<OreParams>,
+}
+
+impl UndergroundParams {
+    // The default caves, without any ores.
+    pub fn new() -> UndergroundParams {
+        UndergroundParams {
+            cave_scale: 0.03,
+            cave_threshold: 0.06,
+            cave_margin: 4,
+            lava_level: 10,
+            ores: Vec::new(),
+        }
+    }
+
+    pub fn cave_scale(&self) -> f32 {
+        self.cave_scale
+    }
+
+    pub fn cave_threshold(&self) -> f32 {
+        self.cave_threshold
+    }
+
+    pub fn cave_margin(&self) -> i32 {
+        self.cave_margin
+    }
+
+    // Caves are flooded with lava up to this height.
+    pub fn lava_level(&self) -> i32 {
+        self.lava_level
+    }
+
+    pub fn ores(&self) -> &[OreParams] {
+        &self.ores
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OreParams {
+    block: String,
+    alt: Option<String>,
+    host: String,
+    min_y: i32,
+    max_y: i32,
+    veins: f32,
+    size: u32,
+}
+
+impl OreParams {
+    pub fn block(&self) -> &str {
+        &self.block
+    }
+
+    pub fn alt(&self) -> Option<&str> {
+        self.alt.as_ref().map(|s| &s[..])
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn min_y(&self) -> i32 {
+        self.min_y
+    }
+
+    pub fn max_y(&self) -> i32 {
+        self.max_y
+    }
+
+    pub fn veins(&self) -> f32 {
+        self.veins
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LightParams {
+    color: Vec3,
+    specular: Vec3,
+}
+
+impl LightParams {
+    pub fn color(&self) -> Vec3 {
+        self.color
     }
 
     pub fn specular(&self) -> Vec3 {
@@ -240,6 +509,357 @@ impl ProjectionParams {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct TickParams {
+    rate: f32,
+    random: u32,
+    growth: f32,
+}
+
+impl TickParams {
+    // Block update ticks per second.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    // Random ticks given to each 16-high section of a loaded chunk per block
+    // update tick.
+    pub fn random(&self) -> u32 {
+        self.random
+    }
+
+    // Chance that a random tick grows a crop by one stage.
+    pub fn growth(&self) -> f32 {
+        self.growth
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FluidParams {
+    water_rate: f32,
+    lava_rate: f32,
+}
+
+impl FluidParams {
+    // Flow updates per second.
+    pub fn water_rate(&self) -> f32 {
+        self.water_rate
+    }
+
+    pub fn lava_rate(&self) -> f32 {
+        self.lava_rate
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MinecartParams {
+    texture: PathBuf,
+    scale: f32,
+    friction: f32,
+    max_speed: f32,
+    push: f32,
+}
+
+impl MinecartParams {
+    pub fn texture(&self) -> &Path {
+        &self.texture
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    // Speed lost per second, in blocks per second.
+    pub fn friction(&self) -> f32 {
+        self.friction
+    }
+
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    // Speed gained per second while pushing.
+    pub fn push(&self) -> f32 {
+        self.push
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MiningParams {
+    seconds: f32,
+    penalty: f32,
+}
+
+impl MiningParams {
+    // Seconds to break a block of hardness 1 by hand.
+    pub fn seconds(&self) -> f32 {
+        self.seconds
+    }
+
+    // How many times longer blocks take without a good enough tool.
+    pub fn penalty(&self) -> f32 {
+        self.penalty
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MobsParams {
+    rate: f32,
+    interval: f32,
+    attempts: u32,
+    radius: i32,
+    near: f32,
+    despawn: f32,
+    search: usize,
+}
+
+impl MobsParams {
+    pub fn new() -> MobsParams {
+        MobsParams {
+            rate: 20.0,
+            interval: 2.0,
+            attempts: 4,
+            radius: 32,
+            near: 12.0,
+            despawn: 72.0,
+            search: 256,
+        }
+    }
+
+    // Simulation steps per second.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    // Seconds between rounds of spawning.
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    // Columns tried for a spawn each round.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // Furthest from the player, in blocks along each axis, that mobs spawn.
+    pub fn radius(&self) -> i32 {
+        self.radius
+    }
+
+    // Mobs never spawn closer to the player than this.
+    pub fn near(&self) -> f32 {
+        self.near
+    }
+
+    // Mobs further from the player than this are removed.
+    pub fn despawn(&self) -> f32 {
+        self.despawn
+    }
+
+    // Most cells a mob looks at when finding a path.
+    pub fn search(&self) -> usize {
+        self.search
+    }
+}
+
+// The lighting and sky at one time of day.
+#[derive(Debug, Clone)]
+pub struct SkyColors {
+    ambient: Vec3,
+    diffuse: Vec3,
+    clear: Vec3,
+}
+
+impl SkyColors {
+    pub fn ambient(&self) -> Vec3 {
+        self.ambient
+    }
+
+    pub fn diffuse(&self) -> Vec3 {
+        self.diffuse
+    }
+
+    // The colour of the sky behind everything.
+    pub fn clear(&self) -> Vec3 {
+        self.clear
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DayParams {
+    length: f32,
+    start: f32,
+    tilt: f32,
+    distance: f32,
+    size: f32,
+    sun: String,
+    moon: String,
+    dawn: SkyColors,
+    noon: SkyColors,
+    dusk: SkyColors,
+    night: SkyColors,
+}
+
+impl DayParams {
+    pub fn new() -> DayParams {
+        DayParams {
+            length: 1200.0,
+            start: 0.3,
+            tilt: 20.0,
+            distance: 150.0,
+            size: 12.0,
+            sun: "other/sun.png".to_owned(),
+            moon: "other/moon.png".to_owned(),
+            dawn: sky_colors(0.2, 0.4, (0.8, 0.5, 0.4)),
+            noon: sky_colors(0.3, 0.7, (0.5, 0.7, 1.0)),
+            dusk: sky_colors(0.2, 0.4, (0.7, 0.4, 0.4)),
+            night: sky_colors(0.05, 0.1, (0.01, 0.01, 0.05)),
+        }
+    }
+
+    // Seconds from one midnight to the next.
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    // Time of day the game starts at, as a fraction of a day from midnight.
+    pub fn start(&self) -> f32 {
+        self.start
+    }
+
+    // Degrees the sun's path leans from passing straight overhead.
+    pub fn tilt(&self) -> f32 {
+        self.tilt
+    }
+
+    // How far away the sun and moon are drawn, which must be within `zfar`.
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    // Half the width of the sun and moon, in blocks at `distance`.
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    // Images in the assets directory.
+    pub fn sun(&self) -> &str {
+        &self.sun
+    }
+
+    pub fn moon(&self) -> &str {
+        &self.moon
+    }
+
+    pub fn dawn(&self) -> &SkyColors {
+        &self.dawn
+    }
+
+    pub fn noon(&self) -> &SkyColors {
+        &self.noon
+    }
+
+    pub fn dusk(&self) -> &SkyColors {
+        &self.dusk
+    }
+
+    pub fn night(&self) -> &SkyColors {
+        &self.night
+    }
+}
+
+// Images in the assets directory for each face of the skybox, named by the
+// way they face from the middle: right is +x, top +y and front +z.
+#[derive(Debug, Clone)]
+pub struct SkyboxParams {
+    right: String,
+    left: String,
+    top: String,
+    bottom: String,
+    front: String,
+    back: String,
+}
+
+impl SkyboxParams {
+    pub fn right(&self) -> &str {
+        &self.right
+    }
+
+    pub fn left(&self) -> &str {
+        &self.left
+    }
+
+    pub fn top(&self) -> &str {
+        &self.top
+    }
+
+    pub fn bottom(&self) -> &str {
+        &self.bottom
+    }
+
+    pub fn front(&self) -> &str {
+        &self.front
+    }
+
+    pub fn back(&self) -> &str {
+        &self.back
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SkyParams {
+    clouds: bool,
+    cloud_texture: String,
+    cloud_height: f32,
+    cloud_tile: f32,
+    cloud_speed: f32,
+    cloud_wind: f32,
+    cloud_alpha: f32,
+    cloud_distance: f32,
+}
+
+impl SkyParams {
+    // Whether clouds are drawn at all.
+    pub fn clouds(&self) -> bool {
+        self.clouds
+    }
+
+    // An image in the assets directory, tiled across the sky.
+    pub fn cloud_texture(&self) -> &str {
+        &self.cloud_texture
+    }
+
+    // Height of the cloud layer above the bottom of the world.
+    pub fn cloud_height(&self) -> f32 {
+        self.cloud_height
+    }
+
+    // Blocks across one tile of the cloud texture.
+    pub fn cloud_tile(&self) -> f32 {
+        self.cloud_tile
+    }
+
+    // Blocks per second the clouds drift.
+    pub fn cloud_speed(&self) -> f32 {
+        self.cloud_speed
+    }
+
+    // Degrees from +z towards +x of the way the clouds drift.
+    pub fn cloud_wind(&self) -> f32 {
+        self.cloud_wind
+    }
+
+    // Opacity of the thickest cloud.
+    pub fn cloud_alpha(&self) -> f32 {
+        self.cloud_alpha
+    }
+
+    // How far from the eye the clouds reach, fading out towards the edge.
+    pub fn cloud_distance(&self) -> f32 {
+        self.cloud_distance
+    }
+}
+
 #[derive(Debug, Clone, RustcDecodable)]
 struct TomlConfig {
     paths: TomlPaths,
@@ -248,6 +868,49 @@ struct TomlConfig {
     camera: TomlCameraParams,
     player: TomlPlayerParams,
     light: TomlLightParams,
+    atlas: TomlAtlasParams,
+    world: TomlWorldParams,
+    terrain: TomlTerrainParams,
+    underground: TomlUndergroundParams,
+    ticks: TomlTickParams,
+    fluids: TomlFluidParams,
+    minecart: TomlMinecartParams,
+    mining: TomlMiningParams,
+    mobs: TomlMobsParams,
+    day: TomlDayParams,
+    skybox: TomlSkyboxParams,
+    sky: TomlSkyParams,
+}
+
+impl TomlConfig {
+    // Catches values that would decode fine but break the game later, pointing
+    // at where they were written.
+    fn validate(&self, path: &Path, src: &str) -> Result<()> {
+        if let Some(ref ores) = self.underground.ores {
+            for (i, ore) in ores.iter().enumerate() {
+                if let Err(desc) = ore.check() {
+                    let offset = entry_offset(src, "underground.ores", i);
+                    return Err(error_at(path, src, offset, desc));
+                }
+            }
+        }
+        if let Err(desc) = self.ticks.check() {
+            return Err(error_at(path, src, table_offset(src, "ticks"), desc));
+        }
+        if let Err(desc) = self.fluids.check() {
+            return Err(error_at(path, src, table_offset(src, "fluids"), desc));
+        }
+        if let Err(desc) = self.mobs.check() {
+            return Err(error_at(path, src, table_offset(src, "mobs"), desc));
+        }
+        if let Err(desc) = self.day.check() {
+            return Err(error_at(path, src, table_offset(src, "day"), desc));
+        }
+        if let Err(desc) = self.sky.check() {
+            return Err(error_at(path, src, table_offset(src, "sky"), desc));
+        }
+        Ok(())
+    }
 }
 
 impl Into<Config> for TomlConfig {
@@ -259,6 +922,18 @@ impl Into<Config> for TomlConfig {
             camera: self.camera.into(),
             player: self.player.into(),
             light: self.light.into(),
+            atlas: self.atlas.into(),
+            world: self.world.into(),
+            terrain: self.terrain.into(),
+            underground: self.underground.into(),
+            ticks: self.ticks.into(),
+            fluids: self.fluids.into(),
+            minecart: self.minecart.into(),
+            mining: self.mining.into(),
+            mobs: self.mobs.into(),
+            day: self.day.into(),
+            skybox: self.skybox.into(),
+            sky: self.sky.into(),
         }
     }
 }
@@ -309,43 +984,168 @@ impl Into<WindowParams> for TomlWindowParams {
 
 #[derive(Debug, Clone, RustcDecodable)]
 struct TomlPlayerParams {
-    diffuse_map: String,
-    specular_map: String,
+    model: Option<String>,
     shine: f32,
     scale: f32,
     speed: f32,
+    reach: Option<f32>,
+    health: Option<u32>,
+    damage: Option<u32>,
+    slots: Option<usize>,
+    items: Option<Vec<String>>,
 }
 
 impl Into<PlayerParams> for TomlPlayerParams {
     fn into(self) -> PlayerParams {
         PlayerParams {
-            diffuse_map: self.diffuse_map.into(),
-            specular_map: self.specular_map.into(),
+            model: self.model.unwrap_or("player_male".to_string()),
             shine: self.shine,
             scale: self.scale,
             speed: self.speed,
+            reach: self.reach.unwrap_or(5.0),
+            health: self.health.unwrap_or(20),
+            damage: self.damage.unwrap_or(2),
+            slots: self.slots.unwrap_or(36),
+            items: self.items.unwrap_or(vec!["stone".to_string()]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlAtlasParams {
+    tiles: String,
+    padding: Option<u32>,
+    dump: Option<String>,
+}
+
+impl Into<AtlasParams> for TomlAtlasParams {
+    fn into(self) -> AtlasParams {
+        AtlasParams {
+            tiles: self.tiles.into(),
+            padding: self.padding.unwrap_or(8),
+            dump: self.dump.map(|d| d.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlWorldParams {
+    shine: f32,
+    radius: Option<i32>,
+    cutoff: Option<f32>,
+    greedy: Option<bool>,
+    save: Option<String>,
+}
+
+impl Into<WorldParams> for TomlWorldParams {
+    fn into(self) -> WorldParams {
+        WorldParams {
+            shine: self.shine,
+            radius: self.radius.unwrap_or(2),
+            cutoff: self.cutoff.unwrap_or(0.5),
+            greedy: self.greedy.unwrap_or(false),
+            save: self.save.map(|s| s.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlTerrainParams {
+    seed: u32,
+    base: Option<i32>,
+    amplitude: Option<f32>,
+    scale: Option<f32>,
+    octaves: Option<u32>,
+    biome_scale: Option<f32>,
+    snow_line: Option<i32>,
+    sea_level: Option<i32>,
+}
+
+impl Into<TerrainParams> for TomlTerrainParams {
+    fn into(self) -> TerrainParams {
+        let defaults = TerrainParams::new(self.seed);
+        TerrainParams {
+            seed: self.seed,
+            base: self.base.unwrap_or(defaults.base),
+            amplitude: self.amplitude.unwrap_or(defaults.amplitude),
+            scale: self.scale.unwrap_or(defaults.scale),
+            octaves: self.octaves.unwrap_or(defaults.octaves),
+            biome_scale: self.biome_scale.unwrap_or(defaults.biome_scale),
+            snow_line: self.snow_line.unwrap_or(defaults.snow_line),
+            sea_level: self.sea_level.unwrap_or(defaults.sea_level),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlUndergroundParams {
+    cave_scale: Option<f32>,
+    cave_threshold: Option<f32>,
+    cave_margin: Option<i32>,
+    lava_level: Option<i32>,
+    ores: Option<Vec<TomlOreParams>>,
+}
+
+impl Into<UndergroundParams> for TomlUndergroundParams {
+    fn into(self) -> UndergroundParams {
+        let defaults = UndergroundParams::new();
+        UndergroundParams {
+            cave_scale: self.cave_scale.unwrap_or(defaults.cave_scale),
+            cave_threshold: self.cave_threshold.unwrap_or(defaults.cave_threshold),
+            cave_margin: self.cave_margin.unwrap_or(defaults.cave_margin),
+            lava_level: self.lava_level.unwrap_or(defaults.lava_level),
+            ores: self.ores.unwrap_or(Vec::new()).into_iter().map(|o| o.into()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlOreParams {
+    block: String,
+    alt: Option<String>,
+    host: Option<String>,
+    min_y: i32,
+    max_y: i32,
+    veins: f32,
+    size: Option<u32>,
+}
+
+impl TomlOreParams {
+    fn check(&self) -> ::std::result::Result<(), String> {
+        if self.min_y < 0 || self.min_y > self.max_y {
+            Err(format!("ore `{}` needs 0 <= `min_y` <= `max_y`", self.block))
+        } else if self.veins < 0.0 {
+            Err(format!("ore `{}` has a negative number of veins", self.block))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Into<OreParams> for TomlOreParams {
+    fn into(self) -> OreParams {
+        OreParams {
+            block: self.block,
+            alt: self.alt,
+            host: self.host.unwrap_or(String::from("stone")),
+            min_y: self.min_y,
+            max_y: self.max_y,
+            veins: self.veins,
+            size: self.size.unwrap_or(8),
         }
     }
 }
 
 #[derive(Debug, Clone, RustcDecodable)]
 struct TomlLightParams {
-    scale: f32,
-    position: Vec3,
     color: Vec3,
-    ambient: Vec3,
-    diffuse: Vec3,
     specular: Vec3,
 }
 
 impl Into<LightParams> for TomlLightParams {
     fn into(self) -> LightParams {
         LightParams {
-            scale: self.scale,
-            position: self.position,
             color: self.color,
-            ambient: self.ambient,
-            diffuse: self.diffuse,
             specular: self.specular,
         }
     }
@@ -383,7 +1183,286 @@ impl Into<ProjectionParams> for TomlProjectionParams {
     }
 }
 
-fn parse(toml: &str, path: &Path) -> Result<toml::Table> {
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlTickParams {
+    rate: Option<f32>,
+    random: Option<u32>,
+    growth: Option<f32>,
+}
+
+impl TomlTickParams {
+    fn check(&self) -> ::std::result::Result<(), String> {
+        if self.rate.map_or(false, |r| r <= 0.0) {
+            Err("ticks has a `rate` that is not positive".to_string())
+        } else if self.growth.map_or(false, |g| g <= 0.0 || g > 1.0) {
+            Err("ticks needs `growth` to be above 0 and at most 1".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Into<TickParams> for TomlTickParams {
+    fn into(self) -> TickParams {
+        TickParams {
+            rate: self.rate.unwrap_or(20.0),
+            random: self.random.unwrap_or(3),
+            growth: self.growth.unwrap_or(0.05),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlFluidParams {
+    water_rate: Option<f32>,
+    lava_rate: Option<f32>,
+}
+
+impl TomlFluidParams {
+    fn check(&self) -> ::std::result::Result<(), String> {
+        if self.water_rate.map_or(false, |r| r <= 0.0) {
+            Err("fluids has a `water_rate` that is not positive".to_string())
+        } else if self.lava_rate.map_or(false, |r| r <= 0.0) {
+            Err("fluids has a `lava_rate` that is not positive".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Into<FluidParams> for TomlFluidParams {
+    fn into(self) -> FluidParams {
+        FluidParams {
+            water_rate: self.water_rate.unwrap_or(5.0),
+            lava_rate: self.lava_rate.unwrap_or(1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlMinecartParams {
+    texture: String,
+    scale: Option<f32>,
+    friction: Option<f32>,
+    max_speed: Option<f32>,
+    push: Option<f32>,
+}
+
+impl Into<MinecartParams> for TomlMinecartParams {
+    fn into(self) -> MinecartParams {
+        MinecartParams {
+            texture: self.texture.into(),
+            scale: self.scale.unwrap_or(0.4),
+            friction: self.friction.unwrap_or(0.5),
+            max_speed: self.max_speed.unwrap_or(8.0),
+            push: self.push.unwrap_or(4.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlMiningParams {
+    seconds: Option<f32>,
+    penalty: Option<f32>,
+}
+
+impl Into<MiningParams> for TomlMiningParams {
+    fn into(self) -> MiningParams {
+        MiningParams {
+            seconds: self.seconds.unwrap_or(1.5),
+            penalty: self.penalty.unwrap_or(3.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlMobsParams {
+    rate: Option<f32>,
+    interval: Option<f32>,
+    attempts: Option<u32>,
+    radius: Option<i32>,
+    near: Option<f32>,
+    despawn: Option<f32>,
+    search: Option<usize>,
+}
+
+impl TomlMobsParams {
+    fn check(&self) -> ::std::result::Result<(), String> {
+        let defaults = MobsParams::new();
+        let radius = self.radius.unwrap_or(defaults.radius);
+        if self.rate.map_or(false, |r| r <= 0.0) {
+            Err("mobs has a `rate` that is not positive".to_string())
+        } else if self.interval.map_or(false, |i| i <= 0.0) {
+            Err("mobs has an `interval` that is not positive".to_string())
+        } else if self.despawn.map_or(false, |d| d <= 0.0) {
+            Err("mobs has a `despawn` distance that is not positive".to_string())
+        } else if radius < 0 {
+            Err("mobs has a negative `radius`".to_string())
+        } else if self.near.unwrap_or(defaults.near) > radius as f32 {
+            Err("mobs needs `near` to be at most `radius`".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Into<MobsParams> for TomlMobsParams {
+    fn into(self) -> MobsParams {
+        let defaults = MobsParams::new();
+        MobsParams {
+            rate: self.rate.unwrap_or(defaults.rate),
+            interval: self.interval.unwrap_or(defaults.interval),
+            attempts: self.attempts.unwrap_or(defaults.attempts),
+            radius: self.radius.unwrap_or(defaults.radius),
+            near: self.near.unwrap_or(defaults.near),
+            despawn: self.despawn.unwrap_or(defaults.despawn),
+            search: self.search.unwrap_or(defaults.search),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlSkyColors {
+    ambient: Vec3,
+    diffuse: Vec3,
+    clear: Vec3,
+}
+
+impl Into<SkyColors> for TomlSkyColors {
+    fn into(self) -> SkyColors {
+        SkyColors {
+            ambient: self.ambient,
+            diffuse: self.diffuse,
+            clear: self.clear,
+        }
+    }
+}
+
+fn sky_colors(ambient: f32, diffuse: f32, clear: (f32, f32, f32)) -> SkyColors {
+    SkyColors {
+        ambient: Vec3::repeat(ambient),
+        diffuse: Vec3::repeat(diffuse),
+        clear: Vec3::new(clear.0, clear.1, clear.2),
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlDayParams {
+    length: Option<f32>,
+    start: Option<f32>,
+    tilt: Option<f32>,
+    distance: Option<f32>,
+    size: Option<f32>,
+    sun: Option<String>,
+    moon: Option<String>,
+    dawn: Option<TomlSkyColors>,
+    noon: Option<TomlSkyColors>,
+    dusk: Option<TomlSkyColors>,
+    night: Option<TomlSkyColors>,
+}
+
+impl TomlDayParams {
+    fn check(&self) -> ::std::result::Result<(), String> {
+        if self.length.map_or(false, |l| l <= 0.0) {
+            Err("day has a length that is not positive".to_string())
+        } else if self.start.map_or(false, |s| s < 0.0 || s >= 1.0) {
+            Err("day needs `start` to be at least 0 and below 1".to_string())
+        } else if self.distance.map_or(false, |d| d <= 0.0) {
+            Err("day has a `distance` that is not positive".to_string())
+        } else if self.size.map_or(false, |s| s <= 0.0) {
+            Err("day has a `size` that is not positive".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Into<DayParams> for TomlDayParams {
+    fn into(self) -> DayParams {
+        let defaults = DayParams::new();
+        DayParams {
+            length: self.length.unwrap_or(defaults.length),
+            start: self.start.unwrap_or(defaults.start),
+            tilt: self.tilt.unwrap_or(defaults.tilt),
+            distance: self.distance.unwrap_or(defaults.distance),
+            size: self.size.unwrap_or(defaults.size),
+            sun: self.sun.unwrap_or(defaults.sun),
+            moon: self.moon.unwrap_or(defaults.moon),
+            dawn: self.dawn.map_or(defaults.dawn, Into::into),
+            noon: self.noon.map_or(defaults.noon, Into::into),
+            dusk: self.dusk.map_or(defaults.dusk, Into::into),
+            night: self.night.map_or(defaults.night, Into::into),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlSkyboxParams {
+    right: Option<String>,
+    left: Option<String>,
+    top: Option<String>,
+    bottom: Option<String>,
+    front: Option<String>,
+    back: Option<String>,
+}
+
+impl Into<SkyboxParams> for TomlSkyboxParams {
+    fn into(self) -> SkyboxParams {
+        SkyboxParams {
+            right: self.right.unwrap_or("other/skybox_sideClouds.png".to_owned()),
+            left: self.left.unwrap_or("other/skybox_side.png".to_owned()),
+            top: self.top.unwrap_or("other/skybox_top.png".to_owned()),
+            bottom: self.bottom.unwrap_or("other/skybox_bottom.png".to_owned()),
+            front: self.front.unwrap_or("other/skybox_sideHills.png".to_owned()),
+            back: self.back.unwrap_or("other/skybox_sideHills.png".to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlSkyParams {
+    clouds: Option<bool>,
+    cloud_texture: Option<String>,
+    cloud_height: Option<f32>,
+    cloud_tile: Option<f32>,
+    cloud_speed: Option<f32>,
+    cloud_wind: Option<f32>,
+    cloud_alpha: Option<f32>,
+    cloud_distance: Option<f32>,
+}
+
+impl TomlSkyParams {
+    fn check(&self) -> ::std::result::Result<(), String> {
+        if self.cloud_tile.map_or(false, |t| t <= 0.0) {
+            Err("sky has a `cloud_tile` that is not positive".to_string())
+        } else if self.cloud_speed.map_or(false, |s| s < 0.0) {
+            Err("sky has a negative `cloud_speed`".to_string())
+        } else if self.cloud_alpha.map_or(false, |a| a < 0.0 || a > 1.0) {
+            Err("sky needs `cloud_alpha` to be from 0 to 1".to_string())
+        } else if self.cloud_distance.map_or(false, |d| d <= 0.0) {
+            Err("sky has a `cloud_distance` that is not positive".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Into<SkyParams> for TomlSkyParams {
+    fn into(self) -> SkyParams {
+        SkyParams {
+            clouds: self.clouds.unwrap_or(true),
+            cloud_texture: self.cloud_texture.unwrap_or("other/clouds.png".to_owned()),
+            cloud_height: self.cloud_height.unwrap_or(140.0),
+            cloud_tile: self.cloud_tile.unwrap_or(96.0),
+            cloud_speed: self.cloud_speed.unwrap_or(1.5),
+            cloud_wind: self.cloud_wind.unwrap_or(60.0),
+            cloud_alpha: self.cloud_alpha.unwrap_or(0.8),
+            cloud_distance: self.cloud_distance.unwrap_or(180.0),
+        }
+    }
+}
+
+pub fn parse(toml: &str, path: &Path) -> Result<toml::Table> {
     let mut parser = Parser::new(&toml);
     match parser.parse() {
         Some(toml) => return Ok(toml),
@@ -406,3 +1485,63 @@ fn parse(toml: &str, path: &Path) -> Result<toml::Table> {
     }
     Err(Error::with_detail("parse error", error_str))
 }
+
+// Zero-based line and column of a byte offset into `src`.
+pub fn to_linecol(src: &str, offset: usize) -> (usize, usize) {
+    let mut line = 0;
+    let mut col = 0;
+    for (i, c) in src.char_indices() {
+        if i >= offset {
+            break;
+        }
+        if c == '\n' {
+            line += 1;
+            col = 0;
+        } else {
+            col += 1;
+        }
+    }
+    (line, col)
+}
+
+// An error for something found at `offset` of the file at `path`, formatted
+// the same way as parse errors.
+pub fn error_at<D>(path: &Path, src: &str, offset: usize, desc: D) -> Error
+    where D: fmt::Display
+{
+    let (line, col) = to_linecol(src, offset);
+    Error::with_detail("validation error",
+                       format!("{}:{}:{} {}", path.display(), line + 1, col + 1, desc))
+}
+
+// Byte offset of the `[name]` header in `src`, for pointing errors at a
+// table. Zero if there is no such header.
+pub fn table_offset(src: &str, name: &str) -> usize {
+    let header = format!("[{}]", name);
+    let mut offset = 0;
+    for line in src.split('\n') {
+        if line.trim() == header {
+            return offset;
+        }
+        offset += line.len() + 1;
+    }
+    0
+}
+
+// Byte offset of the `n`th `[[name]]` header in `src`, for pointing errors at
+// entries of an array of tables. Zero if there is no such header.
+pub fn entry_offset(src: &str, name: &str, n: usize) -> usize {
+    let header = format!("[[{}]]", name);
+    let mut count = 0;
+    let mut offset = 0;
+    for line in src.split('\n') {
+        if line.trim() == header {
+            if count == n {
+                return offset;
+            }
+            count += 1;
+        }
+        offset += line.len() + 1;
+    }
+    0
+}
@@ -0,0 +1,441 @@
+pub mod smelting;
+
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use inventory::{
+    Items,
+    ItemId,
+    Stack,
+};
+use errors::Result;
+
+pub use self::smelting::{
+    Smelting,
+    Oven,
+};
+
+// Side of the largest crafting grid, the one at a crafting table.
+pub const GRID: usize = 3;
+
+// A recipe whose items must be laid out in a pattern. The pattern may be
+// placed anywhere in the grid, and mirrored left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shaped {
+    width: usize,
+    height: usize,
+    // Rows of the pattern, trimmed to the cells that hold items.
+    cells: Vec<Option<ItemId>>,
+    output: Stack,
+}
+
+impl Shaped {
+    pub fn new(width: usize, height: usize, cells: Vec<Option<ItemId>>, output: Stack) -> Shaped {
+        let (width, height, cells) = trim(width, height, &cells);
+        Shaped {
+            width: width,
+            height: height,
+            cells: cells,
+            output: output,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn output(&self) -> Stack {
+        self.output
+    }
+
+    // Whether a trimmed grid holds this pattern.
+    fn matches(&self, width: usize, height: usize, cells: &[Option<ItemId>]) -> bool {
+        width == self.width && height == self.height &&
+            (&cells[..] == &self.cells[..] || mirror(width, height, cells) == self.cells)
+    }
+
+    fn inputs(&self) -> Vec<ItemId> {
+        let mut inputs: Vec<ItemId> = self.cells.iter().filter_map(|&c| c).collect();
+        inputs.sort();
+        inputs
+    }
+}
+
+// A recipe whose items may be put anywhere in the grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shapeless {
+    // Sorted, so grids can be compared without regard to order.
+    inputs: Vec<ItemId>,
+    output: Stack,
+}
+
+impl Shapeless {
+    pub fn new(inputs: Vec<ItemId>, output: Stack) -> Shapeless {
+        let mut inputs = inputs;
+        inputs.sort();
+        Shapeless {
+            inputs: inputs,
+            output: output,
+        }
+    }
+
+    pub fn output(&self) -> Stack {
+        self.output
+    }
+}
+
+// Crafting recipes. No two recipes may be made from the same grid, so the
+// result of a grid never depends on the order recipes are declared in.
+pub struct Recipes {
+    shaped: Vec<Shaped>,
+    shapeless: Vec<Shapeless>,
+}
+
+impl Recipes {
+    pub fn new() -> Recipes {
+        Recipes {
+            shaped: Vec::new(),
+            shapeless: Vec::new(),
+        }
+    }
+
+    pub fn load<P>(path: P, items: &Items) -> Result<Recipes>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let mut recipes = Recipes::new();
+
+        let shaped = try!(entries(&mut table, "shaped", path, &src));
+        for (i, entry) in shaped.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "shaped", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_recipe = match TomlShaped::decode(&mut decoder) {
+                Ok(r) => r,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let added = toml_recipe.into_shaped(items).and_then(|r| recipes.add_shaped(r));
+            if let Err(desc) = added {
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+        }
+
+        let shapeless = try!(entries(&mut table, "shapeless", path, &src));
+        for (i, entry) in shapeless.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "shapeless", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_recipe = match TomlShapeless::decode(&mut decoder) {
+                Ok(r) => r,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let added = toml_recipe.into_shapeless(items).and_then(|r| recipes.add_shapeless(r));
+            if let Err(desc) = added {
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+        }
+
+        Ok(recipes)
+    }
+
+    pub fn shaped(&self) -> &[Shaped] {
+        &self.shaped
+    }
+
+    pub fn shapeless(&self) -> &[Shapeless] {
+        &self.shapeless
+    }
+
+    // Adds a recipe unless a grid that makes it already makes another.
+    pub fn add_shaped(&mut self, recipe: Shaped) -> ::std::result::Result<(), String> {
+        let clash = self.shaped.iter()
+            .any(|r| r.matches(recipe.width, recipe.height, &recipe.cells));
+        let inputs = recipe.inputs();
+        if clash || self.shapeless.iter().any(|r| r.inputs == inputs) {
+            return Err(String::from("recipe is made from the same grid as an earlier one"));
+        }
+        self.shaped.push(recipe);
+        Ok(())
+    }
+
+    pub fn add_shapeless(&mut self, recipe: Shapeless) -> ::std::result::Result<(), String> {
+        if recipe.inputs.len() > GRID * GRID {
+            return Err(format!("recipe has more than {} inputs", GRID * GRID));
+        }
+        let clash = self.shapeless.iter().any(|r| r.inputs == recipe.inputs) ||
+            self.shaped.iter().any(|r| r.inputs() == recipe.inputs);
+        if clash {
+            return Err(String::from("recipe is made from the same grid as an earlier one"));
+        }
+        self.shapeless.push(recipe);
+        Ok(())
+    }
+
+    // What the `size` by `size` grid of `cells`, in rows, makes.
+    pub fn find(&self, size: usize, cells: &[Option<ItemId>]) -> Option<Stack> {
+        assert_eq!(cells.len(), size * size);
+        let (width, height, trimmed) = trim(size, size, cells);
+        if trimmed.is_empty() {
+            return None;
+        }
+        for recipe in self.shaped.iter() {
+            if recipe.matches(width, height, &trimmed) {
+                return Some(recipe.output);
+            }
+        }
+        let mut inputs: Vec<ItemId> = cells.iter().filter_map(|&c| c).collect();
+        inputs.sort();
+        self.shapeless.iter().find(|r| r.inputs == inputs).map(|r| r.output)
+    }
+}
+
+fn entries(table: &mut ::toml::Table, name: &str, path: &Path, src: &str) -> Result<Vec<Value>> {
+    match table.remove(name) {
+        Some(Value::Array(entries)) => Ok(entries),
+        Some(_) => {
+            let desc = format!("`{}` must be an array of tables", name);
+            Err(config::error_at(path, src, 0, desc))
+        },
+        None => Ok(Vec::new()),
+    }
+}
+
+// The smallest box around the cells holding items, as (width, height, cells).
+fn trim(width: usize, height: usize, cells: &[Option<ItemId>])
+        -> (usize, usize, Vec<Option<ItemId>>)
+{
+    let filled: Vec<(usize, usize)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| cells[y * width + x].is_some())
+        .collect();
+    if filled.is_empty() {
+        return (0, 0, Vec::new());
+    }
+    let x0 = filled.iter().map(|p| p.0).min().unwrap();
+    let x1 = filled.iter().map(|p| p.0).max().unwrap();
+    let y0 = filled.iter().map(|p| p.1).min().unwrap();
+    let y1 = filled.iter().map(|p| p.1).max().unwrap();
+    let mut trimmed = Vec::new();
+    for y in y0..y1 + 1 {
+        for x in x0..x1 + 1 {
+            trimmed.push(cells[y * width + x]);
+        }
+    }
+    (x1 - x0 + 1, y1 - y0 + 1, trimmed)
+}
+
+fn mirror(width: usize, height: usize, cells: &[Option<ItemId>]) -> Vec<Option<ItemId>> {
+    let mut mirrored = Vec::with_capacity(cells.len());
+    for y in 0..height {
+        for x in 0..width {
+            mirrored.push(cells[y * width + width - 1 - x]);
+        }
+    }
+    mirrored
+}
+
+fn output(items: &Items, name: &str, count: Option<u32>) -> ::std::result::Result<Stack, String> {
+    let item = try!(items.id(name).ok_or(format!("unknown item `{}`", name)));
+    let count = count.unwrap_or(1);
+    if count == 0 || count > items.get(item).stack() {
+        return Err(format!("`count` must be from 1 to the stack size of `{}`", name));
+    }
+    Ok(Stack::new(item, count))
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlShaped {
+    output: String,
+    count: Option<u32>,
+    // Rows of the grid, top first. `.` and spaces are empty cells.
+    pattern: Vec<String>,
+    key: HashMap<String, String>,
+}
+
+impl TomlShaped {
+    fn into_shaped(self, items: &Items) -> ::std::result::Result<Shaped, String> {
+        let output = try!(output(items, &self.output, self.count));
+
+        let height = self.pattern.len();
+        let width = self.pattern.first().map_or(0, |row| row.chars().count());
+        if height == 0 || height > GRID || width == 0 || width > GRID {
+            return Err(format!("`pattern` must be 1 to {} rows of 1 to {} cells", GRID, GRID));
+        }
+        if self.pattern.iter().any(|row| row.chars().count() != width) {
+            return Err(String::from("every row of `pattern` must be the same length"));
+        }
+
+        let mut key = HashMap::new();
+        for (symbol, name) in self.key.iter() {
+            let mut chars = symbol.chars();
+            let c = match (chars.next(), chars.next()) {
+                (Some(c), None) if c != '.' && c != ' ' => c,
+                _ => return Err(format!("key `{}` must be a single character other than `.` \
+                                         and space", symbol)),
+            };
+            let item = try!(items.id(name).ok_or(format!("unknown item `{}`", name)));
+            key.insert(c, item);
+        }
+
+        let mut cells = Vec::with_capacity(width * height);
+        for row in self.pattern.iter() {
+            for c in row.chars() {
+                if c == '.' || c == ' ' {
+                    cells.push(None);
+                } else {
+                    cells.push(Some(try!(key.get(&c)
+                                         .cloned()
+                                         .ok_or(format!("`{}` is not in `key`", c)))));
+                }
+            }
+        }
+        for (&c, _) in key.iter() {
+            if !self.pattern.iter().any(|row| row.contains(c)) {
+                return Err(format!("key `{}` is not used in `pattern`", c));
+            }
+        }
+        Ok(Shaped::new(width, height, cells, output))
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlShapeless {
+    output: String,
+    count: Option<u32>,
+    inputs: Vec<String>,
+}
+
+impl TomlShapeless {
+    fn into_shapeless(self, items: &Items) -> ::std::result::Result<Shapeless, String> {
+        let output = try!(output(items, &self.output, self.count));
+        if self.inputs.is_empty() {
+            return Err(String::from("`inputs` must not be empty"));
+        }
+        let mut inputs = Vec::with_capacity(self.inputs.len());
+        for name in self.inputs.iter() {
+            inputs.push(try!(items.id(name).ok_or(format!("unknown item `{}`", name))));
+        }
+        Ok(Shapeless::new(inputs, output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use inventory::{
+        ItemId,
+        Stack,
+    };
+    use super::{
+        Recipes,
+        Shaped,
+        Shapeless,
+        GRID,
+    };
+
+    const WOOD: ItemId = 0;
+    const STICK: ItemId = 1;
+    const STONE: ItemId = 2;
+    const PICK: ItemId = 3;
+    const SEED: ItemId = 4;
+    const BREAD: ItemId = 5;
+
+    fn recipes() -> Recipes {
+        let mut recipes = Recipes::new();
+        // An L of stone with a stick under its corner, which only mirroring
+        // turns the other way.
+        let pick = vec![Some(STONE), Some(STONE),
+                        Some(STICK), None];
+        recipes.add_shaped(Shaped::new(2, 2, pick, Stack::new(PICK, 1))).unwrap();
+        recipes.add_shaped(Shaped::new(1, 1, vec![Some(WOOD)], Stack::new(STICK, 4))).unwrap();
+        recipes.add_shapeless(Shapeless::new(vec![SEED, WOOD, SEED], Stack::new(BREAD, 1)))
+            .unwrap();
+        recipes
+    }
+
+    // A grid from rows of `GRID` cells.
+    fn grid(rows: [[Option<ItemId>; GRID]; GRID]) -> Vec<Option<ItemId>> {
+        rows.iter().flat_map(|row| row.iter().cloned()).collect()
+    }
+
+    #[test]
+    fn shaped_anywhere_in_the_grid() {
+        let recipes = recipes();
+        let corner = grid([[Some(STONE), Some(STONE), None],
+                           [Some(STICK), None, None],
+                           [None, None, None]]);
+        let moved = grid([[None, None, None],
+                          [None, Some(STONE), Some(STONE)],
+                          [None, Some(STICK), None]]);
+        assert_eq!(recipes.find(GRID, &corner), Some(Stack::new(PICK, 1)));
+        assert_eq!(recipes.find(GRID, &moved), Some(Stack::new(PICK, 1)));
+    }
+
+    #[test]
+    fn shaped_mirrored() {
+        let recipes = recipes();
+        let mirrored = grid([[Some(STONE), Some(STONE), None],
+                             [None, Some(STICK), None],
+                             [None, None, None]]);
+        let flipped = grid([[Some(STICK), None, None],
+                            [Some(STONE), Some(STONE), None],
+                            [None, None, None]]);
+        assert_eq!(recipes.find(GRID, &mirrored), Some(Stack::new(PICK, 1)));
+        assert_eq!(recipes.find(GRID, &flipped), None);
+    }
+
+    #[test]
+    fn shapeless_in_any_cells() {
+        let recipes = recipes();
+        let spread = grid([[Some(SEED), None, None],
+                           [None, None, Some(WOOD)],
+                           [None, Some(SEED), None]]);
+        assert_eq!(recipes.find(GRID, &spread), Some(Stack::new(BREAD, 1)));
+        let short = grid([[Some(SEED), Some(WOOD), None],
+                          [None, None, None],
+                          [None, None, None]]);
+        assert_eq!(recipes.find(GRID, &short), None);
+    }
+
+    #[test]
+    fn extra_items_spoil_a_recipe() {
+        let recipes = recipes();
+        let single = grid([[None, None, None],
+                           [None, Some(WOOD), None],
+                           [None, None, None]]);
+        let double = grid([[None, None, None],
+                           [None, Some(WOOD), Some(WOOD)],
+                           [None, None, None]]);
+        assert_eq!(recipes.find(GRID, &single), Some(Stack::new(STICK, 4)));
+        assert_eq!(recipes.find(GRID, &double), None);
+        assert_eq!(recipes.find(GRID, &vec![None; GRID * GRID]), None);
+    }
+
+    #[test]
+    fn clashing_recipes_are_rejected() {
+        let mut recipes = recipes();
+        let stick = Shaped::new(2, 1, vec![None, Some(WOOD)], Stack::new(WOOD, 1));
+        assert!(recipes.add_shaped(stick).is_err());
+        let bread = Shapeless::new(vec![SEED, SEED, WOOD], Stack::new(STICK, 1));
+        assert!(recipes.add_shapeless(bread).is_err());
+        // A shapeless recipe of the same items as a one-cell shaped one.
+        assert!(recipes.add_shapeless(Shapeless::new(vec![WOOD], Stack::new(SEED, 1))).is_err());
+    }
+}
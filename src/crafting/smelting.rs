@@ -0,0 +1,537 @@
+use std::mem;
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::{
+    self,
+    File,
+};
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use toml::{
+    self,
+    Decoder,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use inventory::{
+    Items,
+    ItemId,
+    Stack,
+};
+use world::BlockPos;
+use errors::Result;
+use super::entries;
+
+// Turns one `input` into `output` after `time` seconds in a lit oven.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Smelt {
+    pub input: ItemId,
+    pub output: Stack,
+    pub time: f32,
+}
+
+// What ovens can smelt, and what they burn.
+pub struct Smelting {
+    smelts: Vec<Smelt>,
+    // Items that burn, and for how many seconds.
+    fuels: Vec<(ItemId, f32)>,
+}
+
+impl Smelting {
+    pub fn new() -> Smelting {
+        Smelting {
+            smelts: Vec::new(),
+            fuels: Vec::new(),
+        }
+    }
+
+    pub fn load<P>(path: P, items: &Items) -> Result<Smelting>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let mut smelting = Smelting::new();
+
+        let smelts = try!(entries(&mut table, "smelt", path, &src));
+        for (i, entry) in smelts.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "smelt", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_smelt = match TomlSmelt::decode(&mut decoder) {
+                Ok(s) => s,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let added = toml_smelt.into_smelt(items).and_then(|s| smelting.add_smelt(s));
+            if let Err(desc) = added {
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+        }
+
+        let fuels = try!(entries(&mut table, "fuel", path, &src));
+        for (i, entry) in fuels.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "fuel", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_fuel = match TomlFuel::decode(&mut decoder) {
+                Ok(f) => f,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let item = match items.id(&toml_fuel.item) {
+                Some(item) => item,
+                None => {
+                    let desc = format!("unknown item `{}`", toml_fuel.item);
+                    return Err(config::error_at(path, &src, offset, desc));
+                },
+            };
+            if let Err(desc) = smelting.add_fuel(item, toml_fuel.burn) {
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+        }
+
+        Ok(smelting)
+    }
+
+    pub fn add_smelt(&mut self, smelt: Smelt) -> ::std::result::Result<(), String> {
+        if smelt.time <= 0.0 {
+            return Err(String::from("`time` must be positive"));
+        }
+        if self.smelt(smelt.input).is_some() {
+            return Err(String::from("the input is already smelted by an earlier entry"));
+        }
+        self.smelts.push(smelt);
+        Ok(())
+    }
+
+    pub fn add_fuel(&mut self, item: ItemId, burn: f32) -> ::std::result::Result<(), String> {
+        if burn <= 0.0 {
+            return Err(String::from("`burn` must be positive"));
+        }
+        if self.burn_time(item).is_some() {
+            return Err(String::from("the item is already a fuel"));
+        }
+        self.fuels.push((item, burn));
+        Ok(())
+    }
+
+    pub fn smelt(&self, input: ItemId) -> Option<&Smelt> {
+        self.smelts.iter().find(|s| s.input == input)
+    }
+
+    // Seconds `item` keeps an oven lit for, if it burns.
+    pub fn burn_time(&self, item: ItemId) -> Option<f32> {
+        self.fuels.iter().find(|f| f.0 == item).map(|f| f.1)
+    }
+}
+
+// An oven with an input, a fuel and an output slot. A piece of fuel is burnt
+// whenever the last one is out and there is something to smelt, and smelting
+// only makes progress while the oven is lit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Oven {
+    input: Option<Stack>,
+    fuel: Option<Stack>,
+    output: Option<Stack>,
+    // Seconds until the current fuel is spent.
+    burning: f32,
+    // Seconds spent on the current input.
+    progress: f32,
+}
+
+impl Oven {
+    pub fn new() -> Oven {
+        Oven {
+            input: None,
+            fuel: None,
+            output: None,
+            burning: 0.0,
+            progress: 0.0,
+        }
+    }
+
+    pub fn input(&self) -> Option<&Stack> {
+        self.input.as_ref()
+    }
+
+    pub fn fuel(&self) -> Option<&Stack> {
+        self.fuel.as_ref()
+    }
+
+    pub fn output(&self) -> Option<&Stack> {
+        self.output.as_ref()
+    }
+
+    pub fn is_lit(&self) -> bool {
+        self.burning > 0.0
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    // Replaces the input, returning what was there. Progress is lost.
+    pub fn set_input(&mut self, input: Option<Stack>) -> Option<Stack> {
+        self.progress = 0.0;
+        mem::replace(&mut self.input, input)
+    }
+
+    // Replaces the fuel, returning what was there.
+    pub fn set_fuel(&mut self, fuel: Option<Stack>) -> Option<Stack> {
+        mem::replace(&mut self.fuel, fuel)
+    }
+
+    pub fn take_output(&mut self) -> Option<Stack> {
+        self.output.take()
+    }
+
+    // Adds `stack` to the input, returning whatever does not fit.
+    pub fn put_input(&mut self, stack: Stack, items: &Items) -> Option<Stack> {
+        merge(&mut self.input, stack, items)
+    }
+
+    // Adds `stack` to the fuel, returning whatever does not fit.
+    pub fn put_fuel(&mut self, stack: Stack, items: &Items) -> Option<Stack> {
+        merge(&mut self.fuel, stack, items)
+    }
+
+    // The smelt for the current input, if its output has room.
+    fn ready<'a>(&self, smelting: &'a Smelting, items: &Items) -> Option<&'a Smelt> {
+        let smelt = match self.input.and_then(|input| smelting.smelt(input.item)) {
+            Some(smelt) => smelt,
+            None => return None,
+        };
+        let room = match self.output {
+            Some(out) => {
+                out.item == smelt.output.item &&
+                    out.count + smelt.output.count <= items.get(out.item).stack()
+            },
+            None => true,
+        };
+        if room { Some(smelt) } else { None }
+    }
+
+    pub fn update(&mut self, smelting: &Smelting, items: &Items, dt: f32) {
+        let mut dt = dt;
+        while dt > 0.0 {
+            let smelt = self.ready(smelting, items).cloned();
+            if smelt.is_none() {
+                self.progress = 0.0;
+            }
+            if self.burning <= 0.0 {
+                let burn = self.fuel.and_then(|fuel| smelting.burn_time(fuel.item));
+                match (smelt, burn) {
+                    (Some(_), Some(burn)) => {
+                        self.fuel = take_one(self.fuel);
+                        self.burning = burn;
+                    },
+                    _ => {
+                        self.burning = 0.0;
+                        return;
+                    },
+                }
+            }
+
+            let mut step = if dt < self.burning { dt } else { self.burning };
+            if let Some(smelt) = smelt {
+                let left = smelt.time - self.progress;
+                if step < left {
+                    self.progress += step;
+                } else {
+                    step = left;
+                    self.progress = 0.0;
+                    self.input = take_one(self.input);
+                    self.output = Some(match self.output {
+                        Some(out) => Stack::new(out.item, out.count + smelt.output.count),
+                        None => smelt.output,
+                    });
+                }
+            }
+            self.burning -= step;
+            dt -= step;
+        }
+    }
+}
+
+// Reads the ovens written by `save_ovens`. Like inventories, their items are
+// stored by name, and must exist and fit in their slots.
+pub fn load_ovens<P>(path: P, items: &Items) -> Result<HashMap<BlockPos, Oven>>
+    where P: AsRef<Path>
+{
+    let path = path.as_ref();
+    let mut file = try!(File::open(path));
+    let mut src = String::new();
+    try!(file.read_to_string(&mut src));
+
+    let mut table = try!(config::parse(&src, path));
+    let mut ovens = HashMap::new();
+    for (i, entry) in try!(entries(&mut table, "ovens", path, &src)).into_iter().enumerate() {
+        let offset = config::entry_offset(&src, "ovens", i);
+        let mut decoder = Decoder::new(entry);
+        let toml_oven = match TomlOven::decode(&mut decoder) {
+            Ok(o) => o,
+            Err(e) => return Err(config::error_at(path, &src, offset, e)),
+        };
+        let pos = BlockPos::new(toml_oven.x, toml_oven.y, toml_oven.z);
+        let oven = match toml_oven.into_oven(items) {
+            Ok(o) => o,
+            Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+        };
+        if ovens.insert(pos, oven).is_some() {
+            let desc = format!("more than one oven at {}, {}, {}", pos.x, pos.y, pos.z);
+            return Err(config::error_at(path, &src, offset, desc));
+        }
+    }
+    Ok(ovens)
+}
+
+// Writes to a temporary file first so a failed save leaves the last one
+// intact.
+pub fn save_ovens<P>(path: P, ovens: &HashMap<BlockPos, Oven>, items: &Items) -> Result<()>
+    where P: AsRef<Path>
+{
+    let path = path.as_ref();
+    let stack = |stack: Option<Stack>| stack.map(|s| TomlStack {
+        item: String::from(items.get(s.item).name()),
+        count: s.count,
+    });
+    let saved = TomlOvens {
+        ovens: ovens.iter()
+            .map(|(pos, oven)| TomlOven {
+                x: pos.x,
+                y: pos.y,
+                z: pos.z,
+                input: stack(oven.input),
+                fuel: stack(oven.fuel),
+                output: stack(oven.output),
+                burning: oven.burning,
+                progress: oven.progress,
+            })
+            .collect(),
+    };
+
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = try!(File::create(&tmp));
+        try!(file.write_all(toml::encode_str(&saved).as_bytes()));
+    }
+    try!(fs::rename(&tmp, path));
+    Ok(())
+}
+
+fn merge(slot: &mut Option<Stack>, stack: Stack, items: &Items) -> Option<Stack> {
+    let held = match *slot {
+        Some(held) => held,
+        None => {
+            *slot = Some(stack);
+            return None;
+        },
+    };
+    if held.item != stack.item {
+        return Some(stack);
+    }
+    let room = items.get(held.item).stack().saturating_sub(held.count);
+    let moved = if stack.count < room { stack.count } else { room };
+    *slot = Some(Stack { count: held.count + moved, ..held });
+    if moved < stack.count { Some(Stack { count: stack.count - moved, ..stack }) } else { None }
+}
+
+fn take_one(stack: Option<Stack>) -> Option<Stack> {
+    stack.and_then(|s| if s.count > 1 { Some(Stack { count: s.count - 1, ..s }) } else { None })
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlSmelt {
+    input: String,
+    output: String,
+    count: Option<u32>,
+    time: f32,
+}
+
+impl TomlSmelt {
+    fn into_smelt(self, items: &Items) -> ::std::result::Result<Smelt, String> {
+        let input = try!(items.id(&self.input).ok_or(format!("unknown item `{}`", self.input)));
+        Ok(Smelt {
+            input: input,
+            output: try!(super::output(items, &self.output, self.count)),
+            time: self.time,
+        })
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlFuel {
+    item: String,
+    burn: f32,
+}
+
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+struct TomlOvens {
+    ovens: Vec<TomlOven>,
+}
+
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+struct TomlOven {
+    x: i32,
+    y: i32,
+    z: i32,
+    input: Option<TomlStack>,
+    fuel: Option<TomlStack>,
+    output: Option<TomlStack>,
+    burning: f32,
+    progress: f32,
+}
+
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+struct TomlStack {
+    item: String,
+    count: u32,
+}
+
+impl TomlStack {
+    fn into_stack(self, items: &Items) -> ::std::result::Result<Stack, String> {
+        let item = try!(items.id(&self.item).ok_or(format!("no item named `{}`", self.item)));
+        if self.count == 0 || self.count > items.get(item).stack() {
+            return Err(format!("{} `{}` is not from 1 to its stack size", self.count, self.item));
+        }
+        Ok(Stack::new(item, self.count))
+    }
+}
+
+impl TomlOven {
+    fn into_oven(self, items: &Items) -> ::std::result::Result<Oven, String> {
+        if self.burning < 0.0 || self.progress < 0.0 {
+            return Err(String::from("`burning` and `progress` must not be negative"));
+        }
+        let stack = |stack: Option<TomlStack>| match stack {
+            Some(s) => s.into_stack(items).map(Some),
+            None => Ok(None),
+        };
+        Ok(Oven {
+            input: try!(stack(self.input)),
+            fuel: try!(stack(self.fuel)),
+            output: try!(stack(self.output)),
+            burning: self.burning,
+            progress: self.progress,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::collections::hash_map::{
+        HashMap,
+    };
+
+    use inventory::{
+        Items,
+        ItemId,
+        ItemType,
+        Stack,
+    };
+    use world::BlockPos;
+    use super::{
+        load_ovens,
+        save_ovens,
+        Oven,
+        Smelt,
+        Smelting,
+    };
+
+    const ORE: ItemId = 0;
+    const INGOT: ItemId = 1;
+    const COAL: ItemId = 2;
+
+    fn items() -> Items {
+        Items::with_types(vec![
+            ItemType::new("iron_ore"),
+            ItemType::new("iron_ingot").with_stack(2),
+            ItemType::new("coal"),
+        ])
+    }
+
+    // Ore smelts in 2 seconds, and coal burns for 3.
+    fn smelting() -> Smelting {
+        let mut smelting = Smelting::new();
+        smelting.add_smelt(Smelt { input: ORE, output: Stack::new(INGOT, 1), time: 2.0 }).unwrap();
+        smelting.add_fuel(COAL, 3.0).unwrap();
+        smelting
+    }
+
+    fn oven(ore: u32, coal: u32) -> Oven {
+        let items = items();
+        let mut oven = Oven::new();
+        assert_eq!(oven.put_input(Stack::new(ORE, ore), &items), None);
+        if coal > 0 {
+            assert_eq!(oven.put_fuel(Stack::new(COAL, coal), &items), None);
+        }
+        oven
+    }
+
+    #[test]
+    fn smelting_burns_fuel() {
+        let (items, smelting) = (items(), smelting());
+        let mut oven = oven(2, 1);
+        oven.update(&smelting, &items, 1.0);
+        assert!(oven.is_lit());
+        assert_eq!(oven.fuel(), None);
+        assert_eq!(oven.progress(), 1.0);
+        assert_eq!(oven.output(), None);
+
+        oven.update(&smelting, &items, 1.5);
+        assert_eq!(oven.output(), Some(&Stack::new(INGOT, 1)));
+        assert_eq!(oven.input(), Some(&Stack::new(ORE, 1)));
+        assert_eq!(oven.progress(), 0.5);
+
+        // The coal runs out half way through the second ore.
+        oven.update(&smelting, &items, 5.0);
+        assert!(!oven.is_lit());
+        assert_eq!(oven.progress(), 1.0);
+        assert_eq!(oven.output(), Some(&Stack::new(INGOT, 1)));
+    }
+
+    #[test]
+    fn nothing_happens_without_fuel() {
+        let (items, smelting) = (items(), smelting());
+        let mut oven = oven(1, 0);
+        oven.update(&smelting, &items, 10.0);
+        assert!(!oven.is_lit());
+        assert_eq!(oven.progress(), 0.0);
+        assert_eq!(oven.input(), Some(&Stack::new(ORE, 1)));
+    }
+
+    #[test]
+    fn full_output_stops_smelting() {
+        let (items, smelting) = (items(), smelting());
+        let mut oven = oven(3, 4);
+        oven.update(&smelting, &items, 20.0);
+        // Ingots stack to 2, so the last ore waits, and no more fuel is lit.
+        assert_eq!(oven.output(), Some(&Stack::new(INGOT, 2)));
+        assert_eq!(oven.input(), Some(&Stack::new(ORE, 1)));
+        assert_eq!(oven.fuel(), Some(&Stack::new(COAL, 2)));
+        assert_eq!(oven.progress(), 0.0);
+
+        assert_eq!(oven.take_output(), Some(Stack::new(INGOT, 2)));
+        oven.update(&smelting, &items, 20.0);
+        assert_eq!(oven.output(), Some(&Stack::new(INGOT, 1)));
+        assert_eq!(oven.input(), None);
+    }
+
+    #[test]
+    fn ovens_round_trip() {
+        let (items, smelting) = (items(), smelting());
+        let mut ovens = HashMap::new();
+        let mut lit = oven(2, 2);
+        lit.update(&smelting, &items, 2.5);
+        ovens.insert(BlockPos::new(-3, 40, 7), lit);
+        ovens.insert(BlockPos::new(0, 1, 0), Oven::new());
+
+        let path = env::temp_dir().join("cube-ovens-round-trip.toml");
+        save_ovens(&path, &ovens, &items).unwrap();
+        assert_eq!(load_ovens(&path, &items).unwrap(), ovens);
+    }
+}
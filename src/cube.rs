@@ -33,21 +33,117 @@ use errors::{
     Result,
 };
 
-#[derive(Copy, Clone)]
-struct Vertex {
+#[derive(Copy, Clone, Debug)]
+pub struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
     tex_coords: [f32; 2],
+    // The part of the texture `tex_coords` repeat across, as (u0, v0, u1, v1).
+    tile: [f32; 4],
+    // Specular exponent of the surface, or 0 to use the `shine` uniform.
+    surface_shine: f32,
+    // Opacity of the surface, on top of its texels'.
+    surface_alpha: f32,
 }
-implement_vertex!(Vertex, position, normal, tex_coords);
+implement_vertex!(Vertex, position, normal, tex_coords, tile, surface_shine, surface_alpha);
 
 impl Vertex {
-    fn new(x: f32, y: f32, z: f32, nx: f32, ny: f32, nz: f32, tx: f32, ty: f32) -> Vertex {
+    pub fn new(x: f32, y: f32, z: f32, nx: f32, ny: f32, nz: f32, tx: f32, ty: f32) -> Vertex {
         Vertex {
             position: [x, y ,z],
             normal: [nx, ny, nz],
             tex_coords: [tx, ty],
-        } }
+            tile: [0.0, 0.0, 1.0, 1.0],
+            surface_shine: 0.0,
+            surface_alpha: 1.0,
+        }
+    }
+
+    // Texture coordinates count whole repeats of `tile`, so one vertex can
+    // span several copies of an atlas tile.
+    pub fn with_tile(self, tile: [f32; 4]) -> Vertex {
+        Vertex {
+            tile: tile,
+            ..self
+        }
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    pub fn normal(&self) -> [f32; 3] {
+        self.normal
+    }
+
+    pub fn with_shine(self, shine: f32) -> Vertex {
+        Vertex {
+            surface_shine: shine,
+            ..self
+        }
+    }
+
+    pub fn with_alpha(self, alpha: f32) -> Vertex {
+        Vertex {
+            surface_alpha: alpha,
+            ..self
+        }
+    }
+
+    pub fn tex_coords(&self) -> [f32; 2] {
+        self.tex_coords
+    }
+
+    pub fn tile(&self) -> [f32; 4] {
+        self.tile
+    }
+}
+
+// The 36 vertices of a [-1, 1] cube, two triangles per face.
+pub fn vertices() -> [Vertex; 36] {
+    [
+        Vertex::new(-1.0, -1.0, -1.0, 0.0, 0.0, -1.0, 0.0, 0.0),
+        Vertex::new( 1.0, -1.0, -1.0, 0.0, 0.0, -1.0, 1.0, 0.0),
+        Vertex::new( 1.0,  1.0, -1.0, 0.0, 0.0, -1.0, 1.0, 1.0),
+        Vertex::new( 1.0,  1.0, -1.0, 0.0, 0.0, -1.0, 1.0, 1.0),
+        Vertex::new(-1.0,  1.0, -1.0, 0.0, 0.0, -1.0, 0.0, 1.0),
+        Vertex::new(-1.0, -1.0, -1.0, 0.0, 0.0, -1.0, 0.0, 0.0),
+
+        Vertex::new(-1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 0.0, 0.0),
+        Vertex::new( 1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 1.0, 0.0),
+        Vertex::new( 1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 1.0, 1.0),
+        Vertex::new( 1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 1.0, 1.0),
+        Vertex::new(-1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 0.0, 1.0),
+        Vertex::new(-1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 0.0, 0.0),
+
+        Vertex::new(-1.0,  1.0,  1.0, -1.0, 0.0, 0.0, 1.0, 0.0),
+        Vertex::new(-1.0,  1.0, -1.0, -1.0, 0.0, 0.0, 1.0, 1.0),
+        Vertex::new(-1.0, -1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0),
+        Vertex::new(-1.0, -1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0),
+        Vertex::new(-1.0, -1.0,  1.0, -1.0, 0.0, 0.0, 0.0, 0.0),
+        Vertex::new(-1.0,  1.0,  1.0, -1.0, 0.0, 0.0, 1.0, 0.0),
+
+        Vertex::new( 1.0,  1.0,  1.0, 1.0, 0.0, 0.0, 1.0, 0.0),
+        Vertex::new( 1.0,  1.0, -1.0, 1.0, 0.0, 0.0, 1.0, 1.0),
+        Vertex::new( 1.0, -1.0, -1.0, 1.0, 0.0, 0.0, 0.0, 1.0),
+        Vertex::new( 1.0, -1.0, -1.0, 1.0, 0.0, 0.0, 0.0, 1.0),
+        Vertex::new( 1.0, -1.0,  1.0, 1.0, 0.0, 0.0, 0.0, 0.0),
+        Vertex::new( 1.0,  1.0,  1.0, 1.0, 0.0, 0.0, 1.0, 0.0),
+
+        Vertex::new(-1.0, -1.0, -1.0, 0.0, -1.0, 0.0, 0.0, 1.0),
+        Vertex::new( 1.0, -1.0, -1.0, 0.0, -1.0, 0.0, 1.0, 1.0),
+        Vertex::new( 1.0, -1.0,  1.0, 0.0, -1.0, 0.0, 1.0, 0.0),
+        Vertex::new( 1.0, -1.0,  1.0, 0.0, -1.0, 0.0, 1.0, 0.0),
+        Vertex::new(-1.0, -1.0,  1.0, 0.0, -1.0, 0.0, 0.0, 0.0),
+        Vertex::new(-1.0, -1.0, -1.0, 0.0, -1.0, 0.0, 0.0, 1.0),
+
+        Vertex::new(-1.0,  1.0, -1.0, 0.0, 1.0, 0.0, 0.0, 1.0),
+        Vertex::new( 1.0,  1.0, -1.0, 0.0, 1.0, 0.0, 1.0, 1.0),
+        Vertex::new( 1.0,  1.0,  1.0, 0.0, 1.0, 0.0, 1.0, 0.0),
+        Vertex::new( 1.0,  1.0,  1.0, 0.0, 1.0, 0.0, 1.0, 0.0),
+        Vertex::new(-1.0,  1.0,  1.0, 0.0, 1.0, 0.0, 0.0, 0.0),
+        Vertex::new(-1.0,  1.0, -1.0, 0.0, 1.0, 0.0, 0.0, 1.0),
+    ]
 }
 
 pub struct Cube {
@@ -61,49 +157,7 @@ impl Cube {
     pub fn new<F>(facade: &F) -> Result<Cube>
         where F: Facade
     {
-        let ref vertices = [
-            Vertex::new(-1.0, -1.0, -1.0, 0.0, 0.0, -1.0, 0.0, 0.0),
-            Vertex::new( 1.0, -1.0, -1.0, 0.0, 0.0, -1.0, 1.0, 0.0),
-            Vertex::new( 1.0,  1.0, -1.0, 0.0, 0.0, -1.0, 1.0, 1.0),
-            Vertex::new( 1.0,  1.0, -1.0, 0.0, 0.0, -1.0, 1.0, 1.0),
-            Vertex::new(-1.0,  1.0, -1.0, 0.0, 0.0, -1.0, 0.0, 1.0),
-            Vertex::new(-1.0, -1.0, -1.0, 0.0, 0.0, -1.0, 0.0, 0.0),
-
-            Vertex::new(-1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 0.0, 0.0),
-            Vertex::new( 1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 1.0, 0.0),
-            Vertex::new( 1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 1.0, 1.0),
-            Vertex::new( 1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 1.0, 1.0),
-            Vertex::new(-1.0,  1.0,  1.0, 0.0, 0.0,  1.0, 0.0, 1.0),
-            Vertex::new(-1.0, -1.0,  1.0, 0.0, 0.0,  1.0, 0.0, 0.0),
-
-            Vertex::new(-1.0,  1.0,  1.0, -1.0, 0.0, 0.0, 1.0, 0.0),
-            Vertex::new(-1.0,  1.0, -1.0, -1.0, 0.0, 0.0, 1.0, 1.0),
-            Vertex::new(-1.0, -1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0),
-            Vertex::new(-1.0, -1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0),
-            Vertex::new(-1.0, -1.0,  1.0, -1.0, 0.0, 0.0, 0.0, 0.0),
-            Vertex::new(-1.0,  1.0,  1.0, -1.0, 0.0, 0.0, 1.0, 0.0),
-
-            Vertex::new( 1.0,  1.0,  1.0, 1.0, 0.0, 0.0, 1.0, 0.0),
-            Vertex::new( 1.0,  1.0, -1.0, 1.0, 0.0, 0.0, 1.0, 1.0),
-            Vertex::new( 1.0, -1.0, -1.0, 1.0, 0.0, 0.0, 0.0, 1.0),
-            Vertex::new( 1.0, -1.0, -1.0, 1.0, 0.0, 0.0, 0.0, 1.0),
-            Vertex::new( 1.0, -1.0,  1.0, 1.0, 0.0, 0.0, 0.0, 0.0),
-            Vertex::new( 1.0,  1.0,  1.0, 1.0, 0.0, 0.0, 1.0, 0.0),
-
-            Vertex::new(-1.0, -1.0, -1.0, 0.0, -1.0, 0.0, 0.0, 1.0),
-            Vertex::new( 1.0, -1.0, -1.0, 0.0, -1.0, 0.0, 1.0, 1.0),
-            Vertex::new( 1.0, -1.0,  1.0, 0.0, -1.0, 0.0, 1.0, 0.0),
-            Vertex::new( 1.0, -1.0,  1.0, 0.0, -1.0, 0.0, 1.0, 0.0),
-            Vertex::new(-1.0, -1.0,  1.0, 0.0, -1.0, 0.0, 0.0, 0.0),
-            Vertex::new(-1.0, -1.0, -1.0, 0.0, -1.0, 0.0, 0.0, 1.0),
-
-            Vertex::new(-1.0,  1.0, -1.0, 0.0, 1.0, 0.0, 0.0, 1.0),
-            Vertex::new( 1.0,  1.0, -1.0, 0.0, 1.0, 0.0, 1.0, 1.0),
-            Vertex::new( 1.0,  1.0,  1.0, 0.0, 1.0, 0.0, 1.0, 0.0),
-            Vertex::new( 1.0,  1.0,  1.0, 0.0, 1.0, 0.0, 1.0, 0.0),
-            Vertex::new(-1.0,  1.0,  1.0, 0.0, 1.0, 0.0, 0.0, 0.0),
-            Vertex::new(-1.0,  1.0, -1.0, 0.0, 1.0, 0.0, 0.0, 1.0),
-        ];
+        let ref vertices = vertices();
 
         let vtxbuf = try!(VertexBuffer::new(facade, vertices));
         let idxbuf = NoIndices(PrimitiveType::TrianglesList);
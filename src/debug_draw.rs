@@ -0,0 +1,193 @@
+use std::f32::consts::PI;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use gel::{
+    self,
+    Mat4,
+    Vec3,
+    Cross,
+    Normalize,
+};
+
+use errors::{
+    Result,
+};
+
+// Without the `debug-draw` feature every call returns straight away and the
+// optimiser drops them.
+const COMPILED: bool = cfg!(feature = "debug-draw");
+
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+implement_vertex!(Vertex, position, color);
+
+// Immediate-mode line drawing. Shapes are queued during a frame and drawn
+// together by `flush`, which also empties the queue.
+pub struct DebugDraw {
+    lines: Vec<Vertex>,
+    enabled: bool,
+}
+
+impl DebugDraw {
+    pub fn new() -> DebugDraw {
+        DebugDraw {
+            lines: Vec::new(),
+            enabled: false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        COMPILED && self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.lines.clear();
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        let enabled = !self.enabled;
+        self.set_enabled(enabled);
+    }
+
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: Vec3) {
+        if !self.is_enabled() {
+            return;
+        }
+        let color = [color.x, color.y, color.z];
+        self.lines.push(Vertex { position: [a.x, a.y, a.z], color: color });
+        self.lines.push(Vertex { position: [b.x, b.y, b.z], color: color });
+    }
+
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: Vec3) {
+        if !self.is_enabled() {
+            return;
+        }
+        let corner = |i: usize| {
+            Vec3::new(if i & 1 == 0 { min.x } else { max.x },
+                      if i & 2 == 0 { min.y } else { max.y },
+                      if i & 4 == 0 { min.z } else { max.z })
+        };
+        // Corners differing in exactly one bit share an edge.
+        for i in 0..8 {
+            for bit in [1, 2, 4].iter() {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    // Red, green and blue lines along the x, y and z axes.
+    pub fn axes(&mut self, origin: Vec3, size: f32) {
+        self.line(origin, origin + Vec3::new(size, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        self.line(origin, origin + Vec3::new(0.0, size, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        self.line(origin, origin + Vec3::new(0.0, 0.0, size), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    // The view volume of a perspective camera at `eye` looking at `target`, with
+    // `fov` the vertical field of view in degrees.
+    pub fn frustum(&mut self,
+                   eye: Vec3,
+                   target: Vec3,
+                   up: Vec3,
+                   fov: f32,
+                   aspect: f32,
+                   near: f32,
+                   far: f32,
+                   color: Vec3)
+    {
+        if !self.is_enabled() {
+            return;
+        }
+        let forward = (target - eye).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        let slope = (gel::radians(fov) / 2.0).tan();
+
+        let plane = |d: f32| {
+            let center = eye + forward * d;
+            let (h, w) = (up * (slope * d), right * (slope * d * aspect));
+            [center - w - h, center + w - h, center + w + h, center - w + h]
+        };
+        let (n, f) = (plane(near), plane(far));
+        for i in 0..4 {
+            let j = (i + 1) % 4;
+            self.line(n[i], n[j], color);
+            self.line(f[i], f[j], color);
+            self.line(n[i], f[i], color);
+        }
+    }
+
+    // Three great circles, one around each axis.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec3) {
+        if !self.is_enabled() {
+            return;
+        }
+        let segments = 24;
+        let point = |axis: usize, i: usize| {
+            let t = i as f32 / segments as f32 * 2.0 * PI;
+            let (s, c) = (t.sin() * radius, t.cos() * radius);
+            center + match axis {
+                0 => Vec3::new(0.0, c, s),
+                1 => Vec3::new(c, 0.0, s),
+                _ => Vec3::new(c, s, 0.0),
+            }
+        };
+        for axis in 0..3 {
+            for i in 0..segments {
+                self.line(point(axis, i), point(axis, i + 1), color);
+            }
+        }
+    }
+
+    // Draws everything queued since the last flush in a single call.
+    pub fn flush<F, S>(&mut self,
+                       facade: &F,
+                       surface: &mut S,
+                       program: &Program,
+                       view: Mat4,
+                       projection: Mat4) -> Result<()>
+        where F: Facade,
+              S: Surface,
+    {
+        if self.lines.is_empty() {
+            return Ok(());
+        }
+        let vtxbuf = try!(VertexBuffer::new(facade, &self.lines));
+        let uniforms = uniform! {
+            view: view,
+            projection: projection,
+        };
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: true,
+            ..Default::default()
+        };
+        try!(surface.draw(&vtxbuf,
+                          NoIndices(PrimitiveType::LinesList),
+                          program,
+                          &uniforms,
+                          draw_params));
+        self.lines.clear();
+        Ok(())
+    }
+}
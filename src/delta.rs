@@ -26,3 +26,41 @@ impl Delta {
         self.delta
     }
 }
+
+// Turns variable frame times into a whole number of fixed-length steps, carrying
+// the remainder over to the next frame.
+#[derive(Debug, Clone)]
+pub struct FixedStep {
+    interval: f32,
+    accum: f32,
+}
+
+impl FixedStep {
+    pub fn new(interval: f32) -> FixedStep {
+        FixedStep {
+            interval: interval,
+            accum: 0.0,
+        }
+    }
+
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    // The number of steps due after `dt` more seconds. At most `max` are
+    // returned so a long stall does not turn into a burst of catching up.
+    pub fn advance(&mut self, dt: f32, max: u32) -> u32 {
+        self.accum += dt;
+        let mut steps = 0;
+        while self.accum >= self.interval {
+            self.accum -= self.interval;
+            steps += 1;
+        }
+        if steps > max {
+            self.accum = 0.0;
+            max
+        } else {
+            steps
+        }
+    }
+}
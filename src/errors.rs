@@ -140,6 +140,12 @@ impl From<glium::vertex::BufferCreationError> for Error {
     }
 }
 
+impl From<glium::framebuffer::ValidationError> for Error {
+    fn from(e: glium::framebuffer::ValidationError) -> Error {
+        Error::with_detail("framebuffer error", format!("{:?}", e))
+    }
+}
+
 impl From<String> for Error {
     fn from(e: String) -> Error {
         Error::with_detail("error", e)
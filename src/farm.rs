@@ -0,0 +1,126 @@
+use rand::Rng;
+
+use block::Registry;
+use world::{
+    Blocks,
+    BlocksMut,
+    BlockId,
+    BlockPos,
+    Face,
+    AIR,
+};
+use errors::Result;
+
+// What harvesting a ripe crop yields.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Harvest {
+    pub wheat: u32,
+    pub seeds: u32,
+}
+
+// The most a harvest can yield.
+pub const MOST_HARVEST: Harvest = Harvest { wheat: 1, seeds: 3 };
+
+// Tilling, planting, growing and harvesting wheat.
+pub struct Farming {
+    dirt: BlockId,
+    grass: BlockId,
+    farmland: BlockId,
+    stages: [BlockId; 4],
+    // Chance that a random tick advances a crop by one stage.
+    growth: f32,
+}
+
+impl Farming {
+    pub fn new(registry: &Registry, growth: f32) -> Result<Farming> {
+        let stages = [try!(registry.require("wheat_stage1")),
+                      try!(registry.require("wheat_stage2")),
+                      try!(registry.require("wheat_stage3")),
+                      try!(registry.require("wheat_stage4"))];
+        Ok(Farming::with_blocks(try!(registry.require("dirt")),
+                                try!(registry.require("grass")),
+                                try!(registry.require("farmland")),
+                                stages,
+                                growth))
+    }
+
+    pub fn with_blocks(dirt: BlockId,
+                       grass: BlockId,
+                       farmland: BlockId,
+                       stages: [BlockId; 4],
+                       growth: f32) -> Farming
+    {
+        Farming {
+            dirt: dirt,
+            grass: grass,
+            farmland: farmland,
+            stages: stages,
+            growth: growth,
+        }
+    }
+
+    // 0 to 3 for wheat, None for anything else.
+    pub fn stage(&self, id: BlockId) -> Option<usize> {
+        self.stages.iter().position(|&s| s == id)
+    }
+
+    pub fn is_ripe(&self, id: BlockId) -> bool {
+        self.stage(id) == Some(self.stages.len() - 1)
+    }
+
+    pub fn random_tick<W, R>(&self, world: &mut W, pos: BlockPos, rng: &mut R)
+        where W: BlocksMut,
+              R: Rng,
+    {
+        let id = world.block(pos);
+        if let Some(stage) = self.stage(id) {
+            if world.block(pos.neighbour(Face::Bottom)) != self.farmland {
+                // The farmland went out from under it.
+                world.set_block(pos, AIR);
+            } else if stage + 1 < self.stages.len() && rng.gen::<f32>() < self.growth {
+                world.set_block(pos, self.stages[stage + 1]);
+            }
+        }
+    }
+
+    // Turns dirt or grass with nothing on top into farmland.
+    pub fn hoe<W>(&self, world: &mut W, pos: BlockPos) -> bool
+        where W: BlocksMut
+    {
+        let id = world.block(pos);
+        if (id == self.dirt || id == self.grass) && world.block(pos.neighbour(Face::Top)) == AIR {
+            world.set_block(pos, self.farmland);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Plants a seed on top of the farmland at `pos`.
+    pub fn plant<W>(&self, world: &mut W, pos: BlockPos) -> bool
+        where W: BlocksMut
+    {
+        let above = pos.neighbour(Face::Top);
+        if world.block(pos) == self.farmland && world.block(above) == AIR {
+            world.set_block(above, self.stages[0]);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Picks a ripe crop, leaving the farmland ready to be planted again.
+    pub fn harvest<W, R>(&self, world: &mut W, pos: BlockPos, rng: &mut R) -> Option<Harvest>
+        where W: BlocksMut,
+              R: Rng,
+    {
+        if !self.is_ripe(world.block(pos)) {
+            return None;
+        }
+        world.set_block(pos, AIR);
+        Some(Harvest {
+            wheat: MOST_HARVEST.wheat,
+            seeds: rng.gen_range(1, MOST_HARVEST.seeds + 1),
+        })
+    }
+}
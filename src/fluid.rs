@@ -0,0 +1,428 @@
+use std::mem;
+use std::collections::btree_set::{
+    BTreeSet,
+};
+
+use block::Registry;
+use config::FluidParams;
+use delta::FixedStep;
+use world::{
+    Blocks,
+    BlocksMut,
+    BlockId,
+    BlockPos,
+    Face,
+    AIR,
+};
+use errors::Result;
+
+pub const WATER: usize = 0;
+pub const LAVA: usize = 1;
+
+const HORIZONTAL: [Face; 4] = [Face::Left, Face::Right, Face::Back, Face::Front];
+
+// One kind of fluid. Level 0 is the source block; flowing blocks are levels 1
+// and up, one more for every block away from the source, and dry up past the
+// last level.
+struct Fluid {
+    blocks: Vec<BlockId>,
+    step: FixedStep,
+    // Cells to update on the next step, ordered so updates are deterministic.
+    pending: BTreeSet<(i32, i32, i32)>,
+}
+
+impl Fluid {
+    // The source block `name` and its flowing levels `name_flow1`, `name_flow2`,
+    // ... as far as they are declared.
+    fn from_registry(registry: &Registry, name: &str, rate: f32) -> Result<Fluid> {
+        let mut blocks = vec![try!(registry.require(name))];
+        while let Some(id) = registry.id(&format!("{}_flow{}", name, blocks.len())) {
+            blocks.push(id);
+        }
+        Ok(Fluid::new(blocks, rate))
+    }
+
+    fn new(blocks: Vec<BlockId>, rate: f32) -> Fluid {
+        Fluid {
+            blocks: blocks,
+            step: FixedStep::new(1.0 / rate),
+            pending: BTreeSet::new(),
+        }
+    }
+
+    fn level(&self, id: BlockId) -> Option<usize> {
+        self.blocks.iter().position(|&b| b == id)
+    }
+
+    fn max_level(&self) -> usize {
+        self.blocks.len() - 1
+    }
+}
+
+// Water and lava as cellular automata. Only cells near a change are updated,
+// and each fluid advances at its own rate. Lava touching water sets into
+// greystone where it is a source and stone where it is flowing.
+pub struct Fluids {
+    fluids: [Fluid; 2],
+    stone: BlockId,
+    greystone: BlockId,
+}
+
+impl Fluids {
+    pub fn new(registry: &Registry, params: &FluidParams) -> Result<Fluids> {
+        Ok(Fluids {
+            fluids: [try!(Fluid::from_registry(registry, "water", params.water_rate())),
+                     try!(Fluid::from_registry(registry, "lava", params.lava_rate()))],
+            stone: try!(registry.require("stone")),
+            greystone: try!(registry.require("greystone")),
+        })
+    }
+
+    // `water` and `lava` each list the source block and then the flowing
+    // levels. Rates are in updates per second.
+    pub fn with_blocks(water: Vec<BlockId>,
+                       water_rate: f32,
+                       lava: Vec<BlockId>,
+                       lava_rate: f32,
+                       stone: BlockId,
+                       greystone: BlockId) -> Fluids
+    {
+        Fluids {
+            fluids: [Fluid::new(water, water_rate), Fluid::new(lava, lava_rate)],
+            stone: stone,
+            greystone: greystone,
+        }
+    }
+
+    // Fluid and level of `id`, if it is a fluid.
+    pub fn kind(&self, id: BlockId) -> Option<(usize, usize)> {
+        for (k, fluid) in self.fluids.iter().enumerate() {
+            if let Some(level) = fluid.level(id) {
+                return Some((k, level));
+            }
+        }
+        None
+    }
+
+    pub fn is_fluid(&self, id: BlockId) -> bool {
+        self.kind(id).is_some()
+    }
+
+    // The block for `level` of fluid `k`.
+    pub fn block(&self, k: usize, level: usize) -> BlockId {
+        self.fluids[k].blocks[level]
+    }
+
+    // Call whenever the block at `pos` changes, so fluids around it react.
+    pub fn notify<W>(&mut self, world: &W, pos: BlockPos)
+        where W: Blocks
+    {
+        self.schedule(world, pos);
+        for face in Face::all().iter() {
+            self.schedule(world, pos.neighbour(*face));
+        }
+    }
+
+    fn schedule<W>(&mut self, world: &W, pos: BlockPos)
+        where W: Blocks
+    {
+        if let Some((k, _)) = self.kind(world.block(pos)) {
+            self.fluids[k].pending.insert((pos.x, pos.y, pos.z));
+        }
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.fluids.iter().all(|f| f.pending.is_empty())
+    }
+
+    // Runs the steps of each fluid that are due after `dt` seconds.
+    pub fn update<W>(&mut self, world: &mut W, dt: f32)
+        where W: BlocksMut
+    {
+        for k in 0..self.fluids.len() {
+            let steps = self.fluids[k].step.advance(dt, 4);
+            for _ in 0..steps {
+                self.step(world, k);
+            }
+        }
+    }
+
+    // Updates every cell of fluid `k` scheduled before this step. Cells changed
+    // during the step are updated on the next one.
+    pub fn step<W>(&mut self, world: &mut W, k: usize)
+        where W: BlocksMut
+    {
+        let pending = mem::replace(&mut self.fluids[k].pending, BTreeSet::new());
+        for (x, y, z) in pending {
+            self.update_cell(world, k, BlockPos::new(x, y, z));
+        }
+    }
+
+    // Fluids stop at the edge of what is loaded rather than flowing into it.
+    fn set<W>(&mut self, world: &mut W, pos: BlockPos, id: BlockId)
+        where W: BlocksMut
+    {
+        if world.contains(pos) && world.block(pos) != id {
+            world.set_block(pos, id);
+            self.notify(world, pos);
+        }
+    }
+
+    fn update_cell<W>(&mut self, world: &mut W, k: usize, pos: BlockPos)
+        where W: BlocksMut
+    {
+        let mut level = match self.fluids[k].level(world.block(pos)) {
+            Some(level) => level,
+            None => return,
+        };
+
+        if self.react(world, k, pos, level) {
+            return;
+        }
+
+        if level > 0 {
+            match self.expected_level(world, k, pos) {
+                Some(l) if l == level => {},
+                Some(l) => {
+                    let id = self.block(k, l);
+                    self.set(world, pos, id);
+                    level = l;
+                },
+                None => {
+                    self.set(world, pos, AIR);
+                    return;
+                },
+            }
+        }
+
+        // Fall first, and only spread sideways when there is nowhere to fall.
+        let below = pos.neighbour(Face::Bottom);
+        let under = world.block(below);
+        if under == AIR || self.fluids[k].level(under).map_or(false, |l| l > 1) {
+            let id = self.block(k, 1);
+            self.set(world, below, id);
+            return;
+        }
+        if !self.spreads(world, k, pos) || level >= self.fluids[k].max_level() {
+            return;
+        }
+        for face in HORIZONTAL.iter() {
+            let side = pos.neighbour(*face);
+            let id = world.block(side);
+            if id == AIR || self.fluids[k].level(id).map_or(false, |l| l > level + 1) {
+                let id = self.block(k, level + 1);
+                self.set(world, side, id);
+            }
+        }
+    }
+
+    // Whether the fluid at `pos` spreads sideways, which it does once it rests
+    // on something other than air or more of itself.
+    fn spreads<W>(&self, world: &W, k: usize, pos: BlockPos) -> bool
+        where W: Blocks
+    {
+        let under = world.block(pos.neighbour(Face::Bottom));
+        under != AIR && self.fluids[k].level(under).is_none()
+    }
+
+    // The level a flowing cell at `pos` should have given its neighbours, or
+    // `None` if nothing feeds it.
+    fn expected_level<W>(&self, world: &W, k: usize, pos: BlockPos) -> Option<usize>
+        where W: Blocks
+    {
+        let fluid = &self.fluids[k];
+        if fluid.level(world.block(pos.neighbour(Face::Top))).is_some() {
+            return Some(1);
+        }
+        let mut best = None;
+        for face in HORIZONTAL.iter() {
+            let side = pos.neighbour(*face);
+            if let Some(l) = fluid.level(world.block(side)) {
+                if self.spreads(world, k, side) && best.map_or(true, |b| l + 1 < b) {
+                    best = Some(l + 1);
+                }
+            }
+        }
+        best.and_then(|l| if l <= fluid.max_level() { Some(l) } else { None })
+    }
+
+    // Sets lava into rock where it meets water. Returns true if the cell at
+    // `pos` stopped being fluid.
+    fn react<W>(&mut self, world: &mut W, k: usize, pos: BlockPos, level: usize) -> bool
+        where W: BlocksMut
+    {
+        let other = if k == WATER { LAVA } else { WATER };
+        for face in Face::all().iter() {
+            let side = pos.neighbour(*face);
+            let side_level = match self.fluids[other].level(world.block(side)) {
+                Some(l) => l,
+                None => continue,
+            };
+            if k == LAVA {
+                let rock = self.rock(level);
+                self.set(world, pos, rock);
+                return true;
+            }
+            let rock = self.rock(side_level);
+            self.set(world, side, rock);
+        }
+        false
+    }
+
+    fn rock(&self, lava_level: usize) -> BlockId {
+        if lava_level == 0 { self.greystone } else { self.stone }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use world::{
+        Blocks,
+        BlocksMut,
+        BlockId,
+        BlockPos,
+        Grid,
+        AIR,
+    };
+    use super::{
+        Fluids,
+        WATER,
+        LAVA,
+    };
+
+    const STONE: BlockId = 1;
+    const GREYSTONE: BlockId = 2;
+    const WATERS: [BlockId; 4] = [10, 11, 12, 13];
+    const LAVAS: [BlockId; 3] = [20, 21, 22];
+
+    fn fluids() -> Fluids {
+        Fluids::with_blocks(WATERS.to_vec(), 4.0, LAVAS.to_vec(), 1.0, STONE, GREYSTONE)
+    }
+
+    // A stone floor with room above it.
+    fn grid() -> Grid {
+        let mut grid = Grid::new(9, 5, 9);
+        for z in 0..9 {
+            for x in 0..9 {
+                grid.set_block(BlockPos::new(x, 0, z), STONE);
+            }
+        }
+        grid
+    }
+
+    fn place(fluids: &mut Fluids, grid: &mut Grid, x: i32, y: i32, z: i32, id: BlockId) {
+        let pos = BlockPos::new(x, y, z);
+        grid.set_block(pos, id);
+        fluids.notify(&*grid, pos);
+    }
+
+    // Steps fluid `k` until nothing is left to update.
+    fn settle(fluids: &mut Fluids, grid: &mut Grid, k: usize) {
+        for _ in 0..100 {
+            if fluids.is_settled() {
+                return;
+            }
+            fluids.step(grid, k);
+        }
+        panic!("fluid never settled");
+    }
+
+    fn at(grid: &Grid, x: i32, y: i32, z: i32) -> BlockId {
+        grid.block(BlockPos::new(x, y, z))
+    }
+
+    #[test]
+    fn water_spreads_one_level_per_block() {
+        let (mut fluids, mut grid) = (fluids(), grid());
+        place(&mut fluids, &mut grid, 4, 1, 4, WATERS[0]);
+        settle(&mut fluids, &mut grid, WATER);
+
+        assert_eq!(at(&grid, 4, 1, 4), WATERS[0]);
+        assert_eq!(at(&grid, 5, 1, 4), WATERS[1]);
+        assert_eq!(at(&grid, 6, 1, 4), WATERS[2]);
+        assert_eq!(at(&grid, 7, 1, 4), WATERS[3]);
+        assert_eq!(at(&grid, 8, 1, 4), AIR);
+        assert_eq!(at(&grid, 5, 1, 5), WATERS[2]);
+        assert_eq!(at(&grid, 4, 2, 4), AIR);
+    }
+
+    #[test]
+    fn water_falls_before_spreading() {
+        let (mut fluids, mut grid) = (fluids(), grid());
+        place(&mut fluids, &mut grid, 4, 3, 4, WATERS[0]);
+        settle(&mut fluids, &mut grid, WATER);
+
+        assert_eq!(at(&grid, 5, 3, 4), AIR);
+        assert_eq!(at(&grid, 4, 2, 4), WATERS[1]);
+        assert_eq!(at(&grid, 4, 1, 4), WATERS[1]);
+        assert_eq!(at(&grid, 5, 1, 4), WATERS[2]);
+        assert_eq!(at(&grid, 6, 1, 4), WATERS[3]);
+        assert_eq!(at(&grid, 7, 1, 4), AIR);
+    }
+
+    #[test]
+    fn flows_dry_up_without_a_source() {
+        let (mut fluids, mut grid) = (fluids(), grid());
+        place(&mut fluids, &mut grid, 4, 1, 4, WATERS[0]);
+        settle(&mut fluids, &mut grid, WATER);
+        place(&mut fluids, &mut grid, 4, 1, 4, AIR);
+        settle(&mut fluids, &mut grid, WATER);
+
+        for z in 0..9 {
+            for x in 0..9 {
+                assert_eq!(at(&grid, x, 1, z), AIR);
+            }
+        }
+    }
+
+    #[test]
+    fn water_stops_at_the_edge() {
+        let (mut fluids, mut grid) = (fluids(), grid());
+        place(&mut fluids, &mut grid, 8, 1, 4, WATERS[0]);
+        settle(&mut fluids, &mut grid, WATER);
+
+        assert_eq!(at(&grid, 7, 1, 4), WATERS[1]);
+        assert_eq!(at(&grid, 9, 1, 4), AIR);
+    }
+
+    #[test]
+    fn lava_source_and_water_make_greystone() {
+        let (mut fluids, mut grid) = (fluids(), grid());
+        place(&mut fluids, &mut grid, 3, 1, 4, LAVAS[0]);
+        place(&mut fluids, &mut grid, 4, 1, 4, WATERS[0]);
+        fluids.step(&mut grid, WATER);
+
+        assert_eq!(at(&grid, 3, 1, 4), GREYSTONE);
+        assert_eq!(at(&grid, 4, 1, 4), WATERS[0]);
+    }
+
+    #[test]
+    fn flowing_lava_and_water_make_stone() {
+        let (mut fluids, mut grid) = (fluids(), grid());
+        place(&mut fluids, &mut grid, 3, 1, 4, LAVAS[1]);
+        place(&mut fluids, &mut grid, 4, 1, 4, WATERS[0]);
+        fluids.step(&mut grid, LAVA);
+
+        assert_eq!(at(&grid, 3, 1, 4), STONE);
+    }
+
+    #[test]
+    fn each_fluid_flows_at_its_own_rate() {
+        let (mut fluids, mut grid) = (fluids(), grid());
+        place(&mut fluids, &mut grid, 1, 1, 1, LAVAS[0]);
+        place(&mut fluids, &mut grid, 6, 1, 6, WATERS[0]);
+
+        // A quarter second is one water step and no lava.
+        fluids.update(&mut grid, 0.25);
+        assert_eq!(at(&grid, 6, 1, 5), WATERS[1]);
+        assert_eq!(at(&grid, 2, 1, 1), AIR);
+
+        // A second in all is four water steps and one lava step.
+        for _ in 0..3 {
+            fluids.update(&mut grid, 0.25);
+        }
+        assert_eq!(at(&grid, 6, 1, 3), WATERS[3]);
+        assert_eq!(at(&grid, 2, 1, 1), LAVAS[1]);
+        assert_eq!(at(&grid, 3, 1, 1), AIR);
+    }
+}
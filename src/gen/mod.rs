@@ -0,0 +1,71 @@
+pub mod noise;
+pub mod terrain;
+pub mod underground;
+pub mod structure;
+
+use std::cmp;
+
+use block::Registry;
+use config::{
+    TerrainParams,
+    UndergroundParams,
+};
+use world::{
+    Chunk,
+    ChunkPos,
+};
+use errors::Result;
+
+use self::terrain::{
+    Terrain,
+    TerrainBlocks,
+};
+use self::underground::Underground;
+use self::structure::Structures;
+
+// Produces the initial contents of chunks from the world seed.
+pub struct Generator {
+    terrain: Terrain,
+    underground: Underground,
+    structures: Structures,
+}
+
+impl Generator {
+    pub fn new(terrain: &TerrainParams,
+               underground: &UndergroundParams,
+               structures: Structures,
+               registry: &Registry) -> Result<Generator>
+    {
+        let blocks = try!(TerrainBlocks::from_registry(registry));
+        Ok(Generator {
+            terrain: Terrain::new(terrain, blocks),
+            underground: try!(Underground::new(terrain.seed(), underground, registry)),
+            structures: structures,
+        })
+    }
+
+    pub fn terrain(&self) -> &Terrain {
+        &self.terrain
+    }
+
+    pub fn underground(&self) -> &Underground {
+        &self.underground
+    }
+
+    pub fn structures(&self) -> &Structures {
+        &self.structures
+    }
+
+    // y of the first free block above the ground or water at `(x, z)`.
+    pub fn spawn_height(&self, x: i32, z: i32) -> i32 {
+        cmp::max(self.terrain.column(x, z).height, self.terrain.sea_level()) + 1
+    }
+
+    pub fn generate(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = self.terrain.generate(pos);
+        self.underground.carve(&mut chunk, &self.terrain);
+        self.underground.place_ores(&mut chunk);
+        self.structures.place(&mut chunk, &self.terrain);
+        chunk
+    }
+}
@@ -0,0 +1,154 @@
+use rand::{
+    Rng,
+    SeedableRng,
+    XorShiftRng,
+};
+
+// Seeds an rng from a world seed and a salt, so that independent generators
+// sharing one world seed do not produce correlated output.
+pub fn rng(seed: u32, salt: u32) -> XorShiftRng {
+    let s = hash(seed, salt);
+    XorShiftRng::from_seed([s | 1, hash(s, 1), hash(s, 2), hash(s, 3)])
+}
+
+// A well-mixed 32 bit hash of two values.
+pub fn hash(a: u32, b: u32) -> u32 {
+    let mut h = a.wrapping_mul(0x9e3779b1) ^ b.wrapping_mul(0x85ebca6b);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a2d39);
+    h ^= h >> 15;
+    h
+}
+
+// Hash of an integer position, for per-block decisions that must not depend on
+// generation order.
+pub fn hash3(seed: u32, x: i32, y: i32, z: i32) -> u32 {
+    hash(hash(hash(seed, x as u32), y as u32), z as u32)
+}
+
+// `hash3` mapped to [0, 1).
+pub fn unit3(seed: u32, x: i32, y: i32, z: i32) -> f64 {
+    (hash3(seed, x, y, z) >> 8) as f64 / (1 << 24) as f64
+}
+
+// Ken Perlin's improved gradient noise over a seeded permutation table.
+#[derive(Clone)]
+pub struct Perlin {
+    perm: Vec<u8>,
+}
+
+impl Perlin {
+    pub fn new(seed: u32, salt: u32) -> Perlin {
+        let mut table: Vec<u8> = (0..256).map(|i| i as u8).collect();
+        rng(seed, salt).shuffle(&mut table);
+        let mut perm = table.clone();
+        perm.extend(table.into_iter());
+        Perlin {
+            perm: perm,
+        }
+    }
+
+    fn p(&self, i: usize) -> usize {
+        self.perm[i] as usize
+    }
+
+    // Roughly in [-1, 1].
+    pub fn noise2(&self, x: f64, y: f64) -> f64 {
+        let (xf, yf) = (x.floor(), y.floor());
+        let (xi, yi) = ((xf as i64 & 255) as usize, (yf as i64 & 255) as usize);
+        let (x, y) = (x - xf, y - yf);
+        let (u, v) = (fade(x), fade(y));
+
+        let aa = self.p(self.p(xi) + yi);
+        let ab = self.p(self.p(xi) + yi + 1);
+        let ba = self.p(self.p(xi + 1) + yi);
+        let bb = self.p(self.p(xi + 1) + yi + 1);
+
+        lerp(v,
+             lerp(u, grad2(aa, x, y), grad2(ba, x - 1.0, y)),
+             lerp(u, grad2(ab, x, y - 1.0), grad2(bb, x - 1.0, y - 1.0)))
+    }
+
+    // Roughly in [-1, 1].
+    pub fn noise3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (xf, yf, zf) = (x.floor(), y.floor(), z.floor());
+        let xi = (xf as i64 & 255) as usize;
+        let yi = (yf as i64 & 255) as usize;
+        let zi = (zf as i64 & 255) as usize;
+        let (x, y, z) = (x - xf, y - yf, z - zf);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a = self.p(xi) + yi;
+        let aa = self.p(a) + zi;
+        let ab = self.p(a + 1) + zi;
+        let b = self.p(xi + 1) + yi;
+        let ba = self.p(b) + zi;
+        let bb = self.p(b + 1) + zi;
+
+        lerp(w,
+             lerp(v,
+                  lerp(u, grad3(self.p(aa), x, y, z),
+                          grad3(self.p(ba), x - 1.0, y, z)),
+                  lerp(u, grad3(self.p(ab), x, y - 1.0, z),
+                          grad3(self.p(bb), x - 1.0, y - 1.0, z))),
+             lerp(v,
+                  lerp(u, grad3(self.p(aa + 1), x, y, z - 1.0),
+                          grad3(self.p(ba + 1), x - 1.0, y, z - 1.0)),
+                  lerp(u, grad3(self.p(ab + 1), x, y - 1.0, z - 1.0),
+                          grad3(self.p(bb + 1), x - 1.0, y - 1.0, z - 1.0))))
+    }
+
+    // Fractal sum of `octaves` layers, each at twice the frequency and half the
+    // amplitude of the last. Normalised to roughly [-1, 1].
+    pub fn fbm2(&self, x: f64, y: f64, octaves: u32) -> f64 {
+        let (mut sum, mut amp, mut freq, mut norm) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..octaves {
+            sum += amp * self.noise2(x * freq, y * freq);
+            norm += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        if norm > 0.0 { sum / norm } else { 0.0 }
+    }
+
+    pub fn fbm3(&self, x: f64, y: f64, z: f64, octaves: u32) -> f64 {
+        let (mut sum, mut amp, mut freq, mut norm) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..octaves {
+            sum += amp * self.noise3(x * freq, y * freq, z * freq);
+            norm += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        if norm > 0.0 { sum / norm } else { 0.0 }
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn grad2(hash: usize, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+fn grad3(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
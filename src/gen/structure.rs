@@ -0,0 +1,407 @@
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use block::Registry;
+use gen::noise;
+use gen::terrain::{
+    Biome,
+    Column,
+    Terrain,
+};
+use world::{
+    BlockId,
+    Chunk,
+    AIR,
+    CHUNK_WIDTH,
+    CHUNK_DEPTH,
+    CHUNK_HEIGHT,
+};
+use errors::Result;
+
+// A block of a template, relative to the position it is placed at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TemplateBlock {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub id: BlockId,
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    name: String,
+    blocks: Vec<TemplateBlock>,
+    // Furthest horizontal distance of any block from the origin column.
+    reach: i32,
+}
+
+impl Template {
+    pub fn new<S>(name: S, blocks: Vec<TemplateBlock>) -> Template
+        where S: Into<String>
+    {
+        let reach = blocks.iter()
+            .map(|b| if b.x.abs() > b.z.abs() { b.x.abs() } else { b.z.abs() })
+            .max()
+            .unwrap_or(0);
+        Template {
+            name: name.into(),
+            blocks: blocks,
+            reach: reach,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn blocks(&self) -> &[TemplateBlock] {
+        &self.blocks
+    }
+
+    pub fn reach(&self) -> i32 {
+        self.reach
+    }
+}
+
+// Where a template may be placed.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub biomes: Vec<Biome>,
+    // Surface blocks the template may stand on.
+    pub ground: Vec<BlockId>,
+    // Chance per surface column.
+    pub chance: f32,
+}
+
+// Places templates on generated terrain. Whether a template stands on a column
+// depends only on the seed and the column, and every chunk considers the columns
+// of templates that could reach into it, so a structure crossing chunk borders
+// comes out whole whichever side is generated first. Templates only ever fill
+// air, and overlapping templates are resolved in a fixed column order.
+pub struct Structures {
+    seed: u32,
+    entries: Vec<(Template, Rule)>,
+    reach: i32,
+}
+
+impl Structures {
+    pub fn new(seed: u32, entries: Vec<(Template, Rule)>) -> Structures {
+        let reach = entries.iter().map(|&(ref t, _)| t.reach()).max().unwrap_or(0);
+        Structures {
+            seed: seed,
+            entries: entries,
+            reach: reach,
+        }
+    }
+
+    pub fn load<P>(seed: u32, path: P, registry: &Registry) -> Result<Structures>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let entries = match table.remove("structures") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => {
+                let desc = "`structures` must be an array of tables";
+                return Err(config::error_at(path, &src, 0, desc));
+            },
+            None => Vec::new(),
+        };
+
+        let mut structures = Vec::new();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "structures", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_structure = match TomlStructure::decode(&mut decoder) {
+                Ok(s) => s,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            match toml_structure.into_entry(registry) {
+                Ok(entry) => structures.push(entry),
+                Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+            }
+        }
+
+        Ok(Structures::new(seed, structures))
+    }
+
+    pub fn entries(&self) -> &[(Template, Rule)] {
+        &self.entries
+    }
+
+    // Index of the template rooted at `column`, found at `(x, z)`, if any.
+    pub fn at(&self, terrain: &Terrain, column: Column, x: i32, z: i32) -> Option<usize> {
+        if terrain.is_submerged(column) {
+            return None;
+        }
+        let ground = terrain.block(column, column.height);
+        for (i, &(_, ref rule)) in self.entries.iter().enumerate() {
+            if !rule.biomes.contains(&column.biome) || !rule.ground.contains(&ground) {
+                continue;
+            }
+            let roll = noise::unit3(noise::hash(self.seed, i as u32), x, 0, z);
+            if roll < rule.chance as f64 {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    pub fn place(&self, chunk: &mut Chunk, terrain: &Terrain) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let origin = chunk.origin();
+        let r = self.reach;
+        for z in origin.z - r..origin.z + CHUNK_DEPTH as i32 + r {
+            for x in origin.x - r..origin.x + CHUNK_WIDTH as i32 + r {
+                let column = terrain.column(x, z);
+                let index = match self.at(terrain, column, x, z) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                let base = column.height + 1;
+                let template = &self.entries[index].0;
+                for b in template.blocks() {
+                    let (lx, y, lz) = (x + b.x - origin.x, base + b.y, z + b.z - origin.z);
+                    if lx < 0 || lz < 0 || y < 0 || lx >= CHUNK_WIDTH as i32 ||
+                        lz >= CHUNK_DEPTH as i32 || y >= CHUNK_HEIGHT as i32
+                    {
+                        continue;
+                    }
+                    if chunk.get(lx, y, lz) == AIR {
+                        chunk.set(lx, y, lz, b.id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlStructure {
+    name: String,
+    biomes: Vec<String>,
+    ground: Vec<String>,
+    chance: f32,
+    palette: HashMap<String, String>,
+    // Column of the layers that stands on the ground, as [x, z].
+    origin: Vec<i32>,
+    // Bottom layer first; rows run along z and characters along x.
+    layers: Vec<Vec<String>>,
+}
+
+impl TomlStructure {
+    fn into_entry(self, registry: &Registry) -> ::std::result::Result<(Template, Rule), String> {
+        let name = self.name;
+
+        let mut biomes = Vec::new();
+        for biome in self.biomes.iter() {
+            biomes.push(try!(Biome::from_name(biome)
+                             .ok_or(format!("structure `{}` has unknown biome `{}`",
+                                            name, biome))));
+        }
+
+        let mut ground = Vec::new();
+        for block in self.ground.iter() {
+            ground.push(try!(registry.id(block)
+                             .ok_or(format!("structure `{}` has unknown ground block `{}`",
+                                            name, block))));
+        }
+
+        if self.chance < 0.0 || self.chance > 1.0 {
+            return Err(format!("structure `{}` has chance outside [0, 1]", name));
+        }
+
+        let mut palette = HashMap::new();
+        for (key, block) in self.palette.iter() {
+            let mut chars = key.chars();
+            let c = match (chars.next(), chars.next()) {
+                (Some(c), None) if c != '.' && c != ' ' => c,
+                _ => return Err(format!("structure `{}` has bad palette key `{}`, expected \
+                                         a single character other than `.`", name, key)),
+            };
+            let id = try!(registry.id(block)
+                          .ok_or(format!("structure `{}` has unknown block `{}`", name, block)));
+            palette.insert(c, id);
+        }
+
+        if self.origin.len() != 2 {
+            return Err(format!("structure `{}` needs `origin = [x, z]`", name));
+        }
+        let (ox, oz) = (self.origin[0], self.origin[1]);
+
+        let mut blocks = Vec::new();
+        for (y, layer) in self.layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if c == '.' || c == ' ' {
+                        continue;
+                    }
+                    let id = try!(palette.get(&c).cloned()
+                                  .ok_or(format!("structure `{}` uses `{}` which is not \
+                                                  in its palette", name, c)));
+                    blocks.push(TemplateBlock {
+                        x: x as i32 - ox,
+                        y: y as i32,
+                        z: z as i32 - oz,
+                        id: id,
+                    });
+                }
+            }
+        }
+        if blocks.is_empty() {
+            return Err(format!("structure `{}` has no blocks", name));
+        }
+
+        let rule = Rule {
+            biomes: biomes,
+            ground: ground,
+            chance: self.chance,
+        };
+        Ok((Template::new(name, blocks), rule))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::HashMap;
+
+    use config::TerrainParams;
+    use gen::terrain::{
+        Biome,
+        Terrain,
+        TerrainBlocks,
+    };
+    use world::{
+        BlockId,
+        BlockPos,
+        Chunk,
+        ChunkPos,
+        AIR,
+        CHUNK_WIDTH,
+        CHUNK_DEPTH,
+    };
+    use super::{
+        Rule,
+        Structures,
+        Template,
+        TemplateBlock,
+    };
+
+    const LOG: BlockId = 20;
+    const LEAVES: BlockId = 21;
+
+    fn terrain() -> Terrain {
+        let blocks = TerrainBlocks {
+            stone: 1,
+            dirt: 2,
+            grass: 3,
+            snowy_dirt: 4,
+            snow: 5,
+            sand: 6,
+            redsand: 7,
+            redstone: 8,
+            water: 9,
+        };
+        Terrain::new(&TerrainParams::new(3), blocks)
+    }
+
+    // A trunk with a wide crown, so trees near a border reach across it.
+    fn structures() -> Structures {
+        let mut blocks = Vec::new();
+        for y in 0..4 {
+            blocks.push(TemplateBlock { x: 0, y: y, z: 0, id: LOG });
+        }
+        for z in -2..3 {
+            for x in -2..3 {
+                blocks.push(TemplateBlock { x: x, y: 4, z: z, id: LEAVES });
+            }
+        }
+        let rule = Rule {
+            biomes: vec![Biome::Grassland, Biome::Snow, Biome::Desert, Biome::Mesa],
+            ground: (1..9).collect(),
+            chance: 0.02,
+        };
+        Structures::new(3, vec![(Template::new("tree", blocks), rule)])
+    }
+
+    fn generate(terrain: &Terrain, structures: &Structures, pos: ChunkPos) -> Chunk {
+        let mut chunk = terrain.generate(pos);
+        structures.place(&mut chunk, terrain);
+        chunk
+    }
+
+    fn positions() -> Vec<ChunkPos> {
+        let mut positions = Vec::new();
+        for z in -1..2 {
+            for x in -1..2 {
+                positions.push(ChunkPos::new(x, z));
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn placement_is_order_independent() {
+        let (terrain, structures) = (terrain(), structures());
+        let mut forward = HashMap::new();
+        for pos in positions() {
+            forward.insert(pos, generate(&terrain, &structures, pos));
+        }
+        let mut backward = HashMap::new();
+        for pos in positions().into_iter().rev() {
+            backward.insert(pos, generate(&terrain, &structures, pos));
+        }
+        for pos in positions() {
+            assert!(forward[&pos].blocks() == backward[&pos].blocks());
+        }
+    }
+
+    #[test]
+    fn structures_cross_borders_whole() {
+        let (terrain, structures) = (terrain(), structures());
+        let mut chunks = HashMap::new();
+        for pos in positions() {
+            chunks.insert(pos, generate(&terrain, &structures, pos));
+        }
+        let (w, d) = (CHUNK_WIDTH as i32, CHUNK_DEPTH as i32);
+        let template = &structures.entries()[0].0;
+
+        // Roots in the middle chunk, whose crowns all land in loaded chunks.
+        let mut crossing = 0;
+        for z in 0..d {
+            for x in 0..w {
+                let column = terrain.column(x, z);
+                if structures.at(&terrain, column, x, z).is_none() {
+                    continue;
+                }
+                for b in template.blocks() {
+                    let p = BlockPos::new(x + b.x, column.height + 1 + b.y, z + b.z);
+                    if p.chunk() != ChunkPos::new(0, 0) {
+                        crossing += 1;
+                    }
+                    let (lx, y, lz) = p.local();
+                    // Templates only fill air, so something must be there.
+                    assert!(chunks[&p.chunk()].get(lx, y, lz) != AIR);
+                }
+            }
+        }
+        assert!(crossing > 0);
+    }
+}
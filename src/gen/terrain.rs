@@ -0,0 +1,277 @@
+use std::cmp;
+
+use block::Registry;
+use config::TerrainParams;
+use gen::noise::Perlin;
+use world::{
+    BlockId,
+    Chunk,
+    ChunkPos,
+    AIR,
+    CHUNK_WIDTH,
+    CHUNK_DEPTH,
+    CHUNK_HEIGHT,
+};
+use errors::Result;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Grassland,
+    Snow,
+    Desert,
+    Mesa,
+}
+
+impl Biome {
+    pub fn from_name(name: &str) -> Option<Biome> {
+        match name {
+            "grassland" => Some(Biome::Grassland),
+            "snow" => Some(Biome::Snow),
+            "desert" => Some(Biome::Desert),
+            "mesa" => Some(Biome::Mesa),
+            _ => None,
+        }
+    }
+}
+
+// The blocks terrain is built from.
+#[derive(Debug, Copy, Clone)]
+pub struct TerrainBlocks {
+    pub stone: BlockId,
+    pub dirt: BlockId,
+    pub grass: BlockId,
+    pub snowy_dirt: BlockId,
+    pub snow: BlockId,
+    pub sand: BlockId,
+    pub redsand: BlockId,
+    pub redstone: BlockId,
+    pub water: BlockId,
+}
+
+impl TerrainBlocks {
+    pub fn from_registry(registry: &Registry) -> Result<TerrainBlocks> {
+        Ok(TerrainBlocks {
+            stone: try!(registry.require("stone")),
+            dirt: try!(registry.require("dirt")),
+            grass: try!(registry.require("grass")),
+            snowy_dirt: try!(registry.require("snowy_dirt")),
+            snow: try!(registry.require("snow")),
+            sand: try!(registry.require("sand")),
+            redsand: try!(registry.require("redsand")),
+            redstone: try!(registry.require("redstone")),
+            water: try!(registry.require("water")),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub biome: Biome,
+    // y of the topmost solid block.
+    pub height: i32,
+}
+
+// Heightmap terrain. Every value is a pure function of the seed and the world
+// coordinate, so chunks come out the same whatever order they are generated in.
+pub struct Terrain {
+    params: TerrainParams,
+    blocks: TerrainBlocks,
+    height: Perlin,
+    temperature: Perlin,
+    humidity: Perlin,
+}
+
+impl Terrain {
+    pub fn new(params: &TerrainParams, blocks: TerrainBlocks) -> Terrain {
+        let seed = params.seed();
+        Terrain {
+            params: params.clone(),
+            blocks: blocks,
+            height: Perlin::new(seed, 1),
+            temperature: Perlin::new(seed, 2),
+            humidity: Perlin::new(seed, 3),
+        }
+    }
+
+    pub fn blocks(&self) -> &TerrainBlocks {
+        &self.blocks
+    }
+
+    pub fn biome(&self, x: i32, z: i32) -> Biome {
+        let s = self.params.biome_scale() as f64;
+        let (x, z) = (x as f64 * s, z as f64 * s);
+        let temperature = self.temperature.fbm2(x, z, 2);
+        let humidity = self.humidity.fbm2(x, z, 2);
+
+        if temperature < -0.2 {
+            Biome::Snow
+        } else if temperature > 0.2 && humidity < -0.15 {
+            Biome::Mesa
+        } else if temperature > 0.2 && humidity < 0.2 {
+            Biome::Desert
+        } else {
+            Biome::Grassland
+        }
+    }
+
+    pub fn column(&self, x: i32, z: i32) -> Column {
+        let biome = self.biome(x, z);
+        let s = self.params.scale() as f64;
+        let n = self.height.fbm2(x as f64 * s, z as f64 * s, self.params.octaves());
+        let amplitude = self.params.amplitude() as f64;
+
+        let offset = match biome {
+            Biome::Grassland => n * amplitude,
+            Biome::Snow => n.abs() * amplitude * 1.5,
+            Biome::Desert => n * amplitude * 0.4,
+            // Mesas rise in flat terraces.
+            Biome::Mesa => (n.abs() * amplitude * 1.5 / 4.0).floor() * 4.0,
+        };
+
+        let height = self.params.base() as f64 + offset;
+        let max = CHUNK_HEIGHT as f64 - 16.0;
+        Column {
+            biome: biome,
+            height: if height < 1.0 { 1 } else if height > max { max as i32 } else { height as i32 },
+        }
+    }
+
+    pub fn sea_level(&self) -> i32 {
+        self.params.sea_level()
+    }
+
+    // Whether the top of `column` is under water.
+    pub fn is_submerged(&self, column: Column) -> bool {
+        column.height < self.params.sea_level()
+    }
+
+    // The block at `y` in `column`.
+    pub fn block(&self, column: Column, y: i32) -> BlockId {
+        let b = &self.blocks;
+        let depth = column.height - y;
+        if depth < 0 {
+            return if y <= self.params.sea_level() { b.water } else { AIR };
+        }
+        // Lake beds are sand whatever the biome.
+        if self.is_submerged(column) && depth <= 2 {
+            return b.sand;
+        }
+
+        match column.biome {
+            Biome::Grassland => match depth {
+                0 => b.grass,
+                1...3 => b.dirt,
+                _ => b.stone,
+            },
+            Biome::Snow => {
+                if column.height >= self.params.snow_line() {
+                    match depth {
+                        0...2 => b.snow,
+                        _ => b.stone,
+                    }
+                } else {
+                    match depth {
+                        0 => b.snowy_dirt,
+                        1...3 => b.dirt,
+                        _ => b.stone,
+                    }
+                }
+            },
+            Biome::Desert => match depth {
+                0...4 => b.sand,
+                _ => b.stone,
+            },
+            Biome::Mesa => match depth {
+                0...1 => b.redsand,
+                2...12 => b.redstone,
+                _ => b.stone,
+            },
+        }
+    }
+
+    pub fn generate(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        let origin = pos.origin();
+        for lz in 0..CHUNK_DEPTH as i32 {
+            for lx in 0..CHUNK_WIDTH as i32 {
+                let (x, z) = (origin.x + lx, origin.z + lz);
+                let column = self.column(x, z);
+                for y in 0..cmp::max(column.height, self.params.sea_level()) + 1 {
+                    let id = self.block(column, y);
+                    if id != AIR {
+                        chunk.set(lx, y, lz, id);
+                    }
+                }
+            }
+        }
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::TerrainParams;
+    use world::{
+        ChunkPos,
+        CHUNK_HEIGHT,
+    };
+    use super::{
+        Terrain,
+        TerrainBlocks,
+    };
+
+    fn terrain(seed: u32) -> Terrain {
+        let blocks = TerrainBlocks {
+            stone: 1,
+            dirt: 2,
+            grass: 3,
+            snowy_dirt: 4,
+            snow: 5,
+            sand: 6,
+            redsand: 7,
+            redstone: 8,
+            water: 9,
+        };
+        Terrain::new(&TerrainParams::new(seed), blocks)
+    }
+
+    // Far enough apart to cross several biomes and hills.
+    fn coords() -> Vec<(i32, i32)> {
+        let mut coords = Vec::new();
+        for i in -8..8 {
+            coords.push((i * 97, i * -131));
+        }
+        coords
+    }
+
+    #[test]
+    fn same_seed_same_columns() {
+        let (a, b) = (terrain(7), terrain(7));
+        for (x, z) in coords() {
+            assert_eq!(a.column(x, z), b.column(x, z));
+        }
+    }
+
+    #[test]
+    fn same_seed_same_chunks() {
+        let (a, b) = (terrain(7), terrain(7));
+        for &pos in [ChunkPos::new(0, 0), ChunkPos::new(-3, 5)].iter() {
+            assert!(a.generate(pos).blocks() == b.generate(pos).blocks());
+        }
+    }
+
+    #[test]
+    fn different_seeds_different_columns() {
+        let (a, b) = (terrain(7), terrain(8));
+        assert!(coords().into_iter().any(|(x, z)| a.column(x, z) != b.column(x, z)));
+    }
+
+    #[test]
+    fn columns_stay_in_the_world() {
+        let a = terrain(7);
+        for (x, z) in coords() {
+            let height = a.column(x, z).height;
+            assert!(height >= 1 && height < CHUNK_HEIGHT as i32);
+        }
+    }
+}
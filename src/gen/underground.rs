@@ -0,0 +1,317 @@
+use rand::Rng;
+
+use block::Registry;
+use config::UndergroundParams;
+use gen::noise::{
+    self,
+    Perlin,
+};
+use gen::terrain::Terrain;
+use world::{
+    BlockId,
+    BlockPos,
+    Chunk,
+    ChunkPos,
+    AIR,
+    CHUNK_WIDTH,
+    CHUNK_DEPTH,
+    CHUNK_HEIGHT,
+};
+use errors::Result;
+
+// Salt for per-chunk ore rngs, so they do not repeat the terrain noise seeds.
+const ORE_SALT: u32 = 0x6f7265;
+
+#[derive(Debug, Clone)]
+pub struct Ore {
+    block: BlockId,
+    alt: Option<BlockId>,
+    host: BlockId,
+    min_y: i32,
+    max_y: i32,
+    veins: f32,
+    size: u32,
+}
+
+impl Ore {
+    pub fn new(block: BlockId,
+               alt: Option<BlockId>,
+               host: BlockId,
+               min_y: i32,
+               max_y: i32,
+               veins: f32,
+               size: u32) -> Ore
+    {
+        Ore {
+            block: block,
+            alt: alt,
+            host: host,
+            min_y: min_y,
+            max_y: max_y,
+            veins: veins,
+            size: size,
+        }
+    }
+
+    pub fn block(&self) -> BlockId {
+        self.block
+    }
+
+    pub fn alt(&self) -> Option<BlockId> {
+        self.alt
+    }
+
+    // The only block a vein of this ore replaces.
+    pub fn host(&self) -> BlockId {
+        self.host
+    }
+}
+
+// Carves caves out of generated terrain and seeds ore veins into what is left.
+pub struct Underground {
+    seed: u32,
+    params: UndergroundParams,
+    ores: Vec<Ore>,
+    lava: BlockId,
+    tunnels_a: Perlin,
+    tunnels_b: Perlin,
+}
+
+impl Underground {
+    pub fn new(seed: u32, params: &UndergroundParams, registry: &Registry)
+               -> Result<Underground>
+    {
+        let mut ores = Vec::new();
+        for ore in params.ores().iter() {
+            let alt = match ore.alt() {
+                Some(name) => Some(try!(registry.require(name))),
+                None => None,
+            };
+            ores.push(Ore::new(try!(registry.require(ore.block())),
+                               alt,
+                               try!(registry.require(ore.host())),
+                               ore.min_y(),
+                               ore.max_y(),
+                               ore.veins(),
+                               ore.size()));
+        }
+        Ok(Underground::with_ores(seed, params, ores, try!(registry.require("lava"))))
+    }
+
+    pub fn with_ores(seed: u32, params: &UndergroundParams, ores: Vec<Ore>, lava: BlockId)
+                     -> Underground
+    {
+        Underground {
+            seed: seed,
+            params: params.clone(),
+            ores: ores,
+            lava: lava,
+            tunnels_a: Perlin::new(seed, 4),
+            tunnels_b: Perlin::new(seed, 5),
+        }
+    }
+
+    pub fn ores(&self) -> &[Ore] {
+        &self.ores
+    }
+
+    // Caves are where the zero sets of two noise fields cross, which gives long
+    // winding tunnels rather than blobs.
+    pub fn is_cave(&self, x: i32, y: i32, z: i32) -> bool {
+        if y < 2 {
+            return false;
+        }
+        let s = self.params.cave_scale() as f64;
+        let (fx, fy, fz) = (x as f64 * s, y as f64 * s * 1.5, z as f64 * s);
+        let t = self.params.cave_threshold() as f64;
+        self.tunnels_a.fbm3(fx, fy, fz, 2).abs() < t &&
+            self.tunnels_b.fbm3(fx, fy, fz, 2).abs() < t
+    }
+
+    // Hollows out the caves, filling those low enough with lava.
+    pub fn carve(&self, chunk: &mut Chunk, terrain: &Terrain) {
+        let origin = chunk.origin();
+        let margin = self.params.cave_margin();
+        let lava_level = self.params.lava_level();
+        for lz in 0..CHUNK_DEPTH as i32 {
+            for lx in 0..CHUNK_WIDTH as i32 {
+                let (x, z) = (origin.x + lx, origin.z + lz);
+                let top = terrain.column(x, z).height - margin;
+                for y in 0..top {
+                    if self.is_cave(x, y, z) {
+                        chunk.set(lx, y, lz, if y <= lava_level { self.lava } else { AIR });
+                    }
+                }
+            }
+        }
+    }
+
+    // The positions of every vein of ore `index` started in chunk `pos`. Veins may
+    // reach into neighbouring chunks.
+    pub fn veins(&self, pos: ChunkPos, index: usize) -> Vec<Vec<BlockPos>> {
+        let ore = &self.ores[index];
+        let chunk_seed = noise::hash3(self.seed, pos.x, index as i32, pos.z);
+        let mut rng = noise::rng(chunk_seed, ORE_SALT);
+
+        let whole = ore.veins.floor();
+        let mut count = whole as u32;
+        if rng.gen::<f32>() < ore.veins - whole {
+            count += 1;
+        }
+
+        let origin = pos.origin();
+        let mut veins = Vec::new();
+        for _ in 0..count {
+            let mut p = BlockPos::new(origin.x + rng.gen_range(0, CHUNK_WIDTH as i32),
+                                      rng.gen_range(ore.min_y, ore.max_y + 1),
+                                      origin.z + rng.gen_range(0, CHUNK_DEPTH as i32));
+            let mut vein = Vec::with_capacity(ore.size as usize);
+            for _ in 0..ore.size {
+                vein.push(p);
+                // Steps out of the ore's band are not taken, so veins keep to it.
+                p = match rng.gen_range(0, 6) {
+                    0 => p.offset(1, 0, 0),
+                    1 => p.offset(-1, 0, 0),
+                    2 if p.y < ore.max_y => p.offset(0, 1, 0),
+                    3 if p.y > ore.min_y => p.offset(0, -1, 0),
+                    4 => p.offset(0, 0, 1),
+                    5 => p.offset(0, 0, -1),
+                    _ => p,
+                };
+            }
+            veins.push(vein);
+        }
+        veins
+    }
+
+    // Places the parts of every vein that fall inside `chunk`, including veins
+    // started in neighbouring chunks, so the result does not depend on which
+    // chunks were generated first.
+    pub fn place_ores(&self, chunk: &mut Chunk) {
+        let pos = chunk.position();
+        let origin = chunk.origin();
+        for index in 0..self.ores.len() {
+            let ore = &self.ores[index];
+            for dx in -1..2 {
+                for dz in -1..2 {
+                    let from = ChunkPos::new(pos.x + dx, pos.z + dz);
+                    for vein in self.veins(from, index) {
+                        for p in vein {
+                            let (lx, lz) = (p.x - origin.x, p.z - origin.z);
+                            if lx < 0 || lz < 0 || lx >= CHUNK_WIDTH as i32 ||
+                                lz >= CHUNK_DEPTH as i32 || p.y < 0 ||
+                                p.y >= CHUNK_HEIGHT as i32
+                            {
+                                continue;
+                            }
+                            if chunk.get(lx, p.y, lz) != ore.host {
+                                continue;
+                            }
+                            let flip = noise::hash3(self.seed, p.x, p.y, p.z) & 1 == 1;
+                            let id = match ore.alt {
+                                Some(alt) if flip => alt,
+                                _ => ore.block,
+                            };
+                            chunk.set(lx, p.y, lz, id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::UndergroundParams;
+    use world::{
+        BlockId,
+        Chunk,
+        ChunkPos,
+        CHUNK_WIDTH,
+        CHUNK_DEPTH,
+    };
+    use super::{
+        Ore,
+        Underground,
+    };
+
+    const STONE: BlockId = 1;
+    const COAL: BlockId = 2;
+    const GOLD: BlockId = 3;
+    const LAVA: BlockId = 4;
+    const TOP: i32 = 80;
+
+    fn underground(seed: u32) -> Underground {
+        let ores = vec![Ore::new(COAL, None, STONE, 8, 64, 10.0, 12),
+                        Ore::new(GOLD, None, STONE, 4, 16, 1.5, 6)];
+        Underground::with_ores(seed, &UndergroundParams::new(), ores, LAVA)
+    }
+
+    // A chunk of stone up to `TOP` with the ores placed in it.
+    fn ores(underground: &Underground, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        for y in 0..TOP {
+            for z in 0..CHUNK_DEPTH as i32 {
+                for x in 0..CHUNK_WIDTH as i32 {
+                    chunk.set(x, y, z, STONE);
+                }
+            }
+        }
+        underground.place_ores(&mut chunk);
+        chunk
+    }
+
+    fn count(chunk: &Chunk, id: BlockId) -> usize {
+        chunk.blocks().iter().filter(|&&b| b == id).count()
+    }
+
+    #[test]
+    fn same_seed_same_ore_counts() {
+        let (a, b) = (underground(42), underground(42));
+        for &pos in [ChunkPos::new(0, 0), ChunkPos::new(4, -2)].iter() {
+            let (a, b) = (ores(&a, pos), ores(&b, pos));
+            assert!(count(&a, COAL) > 0);
+            assert_eq!(count(&a, COAL), count(&b, COAL));
+            assert_eq!(count(&a, GOLD), count(&b, GOLD));
+            assert!(a.blocks() == b.blocks());
+        }
+    }
+
+    #[test]
+    fn ores_stay_in_their_bands() {
+        let u = underground(42);
+        for cx in -2..3 {
+            for cz in -2..3 {
+                let chunk = ores(&u, ChunkPos::new(cx, cz));
+                for y in 0..TOP {
+                    for z in 0..CHUNK_DEPTH as i32 {
+                        for x in 0..CHUNK_WIDTH as i32 {
+                            match chunk.get(x, y, z) {
+                                COAL => assert!(y >= 8 && y <= 64),
+                                GOLD => assert!(y >= 4 && y <= 16),
+                                _ => {},
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_height_band() {
+        let band = vec![Ore::new(COAL, None, STONE, 20, 20, 4.0, 8)];
+        let u = Underground::with_ores(7, &UndergroundParams::new(), band, LAVA);
+        let chunk = ores(&u, ChunkPos::new(0, 0));
+        for y in 0..TOP {
+            if y != 20 {
+                for z in 0..CHUNK_DEPTH as i32 {
+                    for x in 0..CHUNK_WIDTH as i32 {
+                        assert!(chunk.get(x, y, z) != COAL);
+                    }
+                }
+            }
+        }
+    }
+}
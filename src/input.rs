@@ -1,9 +1,13 @@
 use std::collections::hash_map::{
     HashMap,
 };
+use std::collections::hash_set::{
+    HashSet,
+};
 
 use glutin::{
     Event,
+    MouseScrollDelta,
 };
 use glium::backend::glutin_backend::{
     GlutinFacade,
@@ -13,6 +17,7 @@ use glium::backend::glutin_backend::{
 
 pub use glutin::{
     ElementState,
+    MouseButton,
     VirtualKeyCode as Key,
 };
 
@@ -22,8 +27,18 @@ use errors::{
 };
 use cursor::Cursor;
 
+// Scrolling by pixels, as touchpads do, counts one line per this many.
+const PIXELS_PER_LINE: f32 = 16.0;
+
 pub struct Input<'a> {
     keys: HashMap<Key, ElementState>,
+    // Keys pressed since the last update, ignoring key repeat.
+    taps: HashSet<Key>,
+    buttons: HashMap<MouseButton, ElementState>,
+    // Buttons pressed since the last update.
+    clicks: HashSet<MouseButton>,
+    // Lines scrolled since the last update, positive away from the user.
+    scroll: f32,
     cursor: Cursor<'a>,
     winsize: (i32, i32),
     should_close: bool,
@@ -37,6 +52,10 @@ impl<'a> Input<'a> {
         let (x, y) = (x as i32, y as i32);
         Ok(Input {
             keys: HashMap::new(),
+            taps: HashSet::new(),
+            buttons: HashMap::new(),
+            clicks: HashSet::new(),
+            scroll: 0.0,
             cursor: try!(Cursor::new(winref, x / 2, y / 2)),
             winsize: (x as i32, y as i32),
             should_close: false,
@@ -44,6 +63,9 @@ impl<'a> Input<'a> {
     }
 
     pub fn update(&mut self, events: PollEventsIter) {
+        self.taps.clear();
+        self.clicks.clear();
+        self.scroll = 0.0;
         for event in events {
             if is_event_should_close(&event) {
                 self.should_close = true;
@@ -51,8 +73,23 @@ impl<'a> Input<'a> {
 
             match event {
                 Event::KeyboardInput(state, _, Some(key)) => {
+                    if state == ElementState::Pressed && !self.is_pressed(key) {
+                        self.taps.insert(key);
+                    }
                     self.keys.insert(key, state);
                 },
+                Event::MouseInput(state, button) => {
+                    if state == ElementState::Pressed {
+                        self.clicks.insert(button);
+                    }
+                    self.buttons.insert(button, state);
+                },
+                Event::MouseWheel(MouseScrollDelta::LineDelta(_, y)) => {
+                    self.scroll += y;
+                },
+                Event::MouseWheel(MouseScrollDelta::PixelDelta(_, y)) => {
+                    self.scroll += y / PIXELS_PER_LINE;
+                },
                 Event::MouseMoved((xpos, ypos)) => {
                     self.cursor.update(xpos, ypos);
                 },
@@ -78,6 +115,28 @@ impl<'a> Input<'a> {
         }
     }
 
+    // True only on the update the key went down.
+    pub fn is_tapped(&self, key: Key) -> bool {
+        self.taps.contains(&key)
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        if let Some(state) = self.buttons.get(&button) {
+            *state == ElementState::Pressed
+        } else {
+            false
+        }
+    }
+
+    // True only on the update the button went down.
+    pub fn is_clicked(&self, button: MouseButton) -> bool {
+        self.clicks.contains(&button)
+    }
+
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
     pub fn cursor(&self) -> &Cursor {
         &self.cursor
     }
@@ -0,0 +1,367 @@
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use block::Registry;
+use world::BlockId;
+use errors::{
+    Result,
+    Error,
+};
+
+pub type ItemId = u16;
+
+// Tool tiers, weakest first. Tier 0 is the bare hand.
+pub const TIERS: [&'static str; 5] = ["bronze", "iron", "silver", "gold", "diamond"];
+
+// 1 for the first of `TIERS`, 2 for the next and so on.
+pub fn tier(name: &str) -> Option<u8> {
+    TIERS.iter().position(|&t| t == name).map(|i| i as u8 + 1)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Tool {
+    Pick,
+    Axe,
+    Shovel,
+    Hoe,
+    Hammer,
+    Flail,
+    Sword,
+}
+
+impl Tool {
+    pub fn from_name(name: &str) -> Option<Tool> {
+        match name {
+            "pick" => Some(Tool::Pick),
+            "axe" => Some(Tool::Axe),
+            "shovel" => Some(Tool::Shovel),
+            "hoe" => Some(Tool::Hoe),
+            "hammer" => Some(Tool::Hammer),
+            "flail" => Some(Tool::Flail),
+            "sword" => Some(Tool::Sword),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemType {
+    name: String,
+    // Image in the items directory, without the extension. Block items are
+    // drawn with their tiles instead.
+    icon: Option<String>,
+    block: Option<BlockId>,
+    stack: u32,
+    durability: Option<u32>,
+    tool: Option<Tool>,
+    tier: u8,
+    speed: f32,
+    food: u32,
+}
+
+impl ItemType {
+    // An item without an icon that stacks to 64 and does nothing else.
+    pub fn new(name: &str) -> ItemType {
+        ItemType {
+            name: String::from(name),
+            icon: None,
+            block: None,
+            stack: 64,
+            durability: None,
+            tool: None,
+            tier: 0,
+            speed: 1.0,
+            food: 0,
+        }
+    }
+
+    // The item for a block that is not declared in the items file.
+    fn for_block(name: &str, block: BlockId) -> ItemType {
+        ItemType {
+            block: Some(block),
+            ..ItemType::new(name)
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_ref().map(|s| &s[..])
+    }
+
+    // The block placed when this item is used on the world.
+    pub fn block(&self) -> Option<BlockId> {
+        self.block
+    }
+
+    // Most items one slot can hold.
+    pub fn stack(&self) -> u32 {
+        self.stack
+    }
+
+    // Uses before the item breaks. `None` never wears out.
+    pub fn durability(&self) -> Option<u32> {
+        self.durability
+    }
+
+    pub fn tool(&self) -> Option<Tool> {
+        self.tool
+    }
+
+    // 1 for bronze up to 5 for diamond, 0 for anything that is not a tool.
+    pub fn tier(&self) -> u8 {
+        self.tier
+    }
+
+    // How many times faster than by hand the item breaks blocks it is a tool
+    // for.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // Hunger restored by eating the item, 0 if it is not food.
+    pub fn food(&self) -> u32 {
+        self.food
+    }
+}
+
+// For building items in tests.
+#[cfg(test)]
+impl ItemType {
+    pub fn with_tool(self, tool: Tool, tier: u8, speed: f32) -> ItemType {
+        ItemType {
+            tool: Some(tool),
+            tier: tier,
+            speed: speed,
+            ..self
+        }
+    }
+
+    pub fn with_stack(self, stack: u32) -> ItemType {
+        ItemType {
+            stack: stack,
+            ..self
+        }
+    }
+
+    // Items that wear out don't stack.
+    pub fn with_durability(self, durability: u32) -> ItemType {
+        ItemType {
+            stack: 1,
+            durability: Some(durability),
+            ..self
+        }
+    }
+}
+
+// Item types in the order they are declared, followed by an item for every
+// block that is not declared. Ids are not stable between runs, so items are
+// saved by name.
+pub struct Items {
+    items: Vec<ItemType>,
+    names: HashMap<String, ItemId>,
+}
+
+impl Items {
+    // Icons are checked against the images in `icons`, and every block's drop
+    // must name an item.
+    pub fn load<P, Q>(path: P, blocks: &Registry, icons: Q) -> Result<Items>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let entries = match table.remove("items") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => {
+                let desc = "`items` must be an array of tables";
+                return Err(config::error_at(path, &src, 0, desc));
+            },
+            None => Vec::new(),
+        };
+
+        let mut items = Items::with_types(Vec::new());
+
+        for (i, entry) in entries.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "items", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_item = match TomlItemType::decode(&mut decoder) {
+                Ok(item) => item,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let item = match toml_item.into_item_type(blocks, icons.as_ref()) {
+                Ok(item) => item,
+                Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+            };
+            if items.names.contains_key(&item.name) {
+                let desc = format!("duplicate item `{}`", item.name);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            items.push(item);
+        }
+
+        for id in 1..blocks.len() {
+            let id = id as BlockId;
+            let name = blocks.name(id);
+            if !items.names.contains_key(name) {
+                items.push(ItemType::for_block(name, id));
+            }
+        }
+
+        for id in 1..blocks.len() {
+            if let Some(drop) = blocks.get(id as BlockId).and_then(|b| b.drop()) {
+                if !items.names.contains_key(drop) {
+                    return Err(Error::with_detail("item error",
+                                                  format!("block `{}` drops `{}`, which is \
+                                                           not an item",
+                                                          blocks.name(id as BlockId), drop)));
+                }
+            }
+        }
+
+        Ok(items)
+    }
+
+    // Items declared in code rather than loaded, in id order.
+    pub fn with_types(types: Vec<ItemType>) -> Items {
+        let mut items = Items {
+            items: Vec::new(),
+            names: HashMap::new(),
+        };
+        for item in types {
+            items.push(item);
+        }
+        items
+    }
+
+    fn push(&mut self, item: ItemType) {
+        let id = self.items.len() as ItemId;
+        self.names.insert(item.name.clone(), id);
+        self.items.push(item);
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn id(&self, name: &str) -> Option<ItemId> {
+        self.names.get(name).cloned()
+    }
+
+    // Like `id`, for items the game cannot run without.
+    pub fn require(&self, name: &str) -> Result<ItemId> {
+        self.id(name).ok_or(Error::with_detail("item error",
+                                               format!("no item named `{}`", name)))
+    }
+
+    pub fn get(&self, id: ItemId) -> &ItemType {
+        &self.items[id as usize]
+    }
+
+    // The item of the same name as `block`.
+    pub fn of_block(&self, blocks: &Registry, block: BlockId) -> Option<ItemId> {
+        self.id(blocks.name(block))
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlItemType {
+    name: String,
+    icon: Option<String>,
+    stack: Option<u32>,
+    durability: Option<u32>,
+    tool: Option<String>,
+    tier: Option<String>,
+    speed: Option<f32>,
+    food: Option<u32>,
+}
+
+impl TomlItemType {
+    fn into_item_type(self, blocks: &Registry, icons: &Path)
+                      -> ::std::result::Result<ItemType, String>
+    {
+        let name = self.name;
+        let block = blocks.id(&name);
+        let icon = match (self.icon, block) {
+            (Some(ref icon), _) if icon.is_empty() => None,
+            (Some(icon), _) => Some(icon),
+            (None, Some(_)) => None,
+            (None, None) => Some(name.clone()),
+        };
+        if let Some(ref icon) = icon {
+            if !icons.join(format!("{}.png", icon)).exists() {
+                return Err(format!("item `{}` uses missing icon `{}`", name, icon));
+            }
+        }
+
+        let tool = match self.tool {
+            Some(ref tool) => match Tool::from_name(tool) {
+                Some(t) => Some(t),
+                None => return Err(format!("item `{}` has unknown tool `{}`, expected `pick`, \
+                                            `axe`, `shovel`, `hoe`, `hammer`, `flail` or \
+                                            `sword`", name, tool)),
+            },
+            None => None,
+        };
+        let tier = match self.tier {
+            Some(ref t) => match tier(t) {
+                Some(t) => t,
+                None => return Err(format!("item `{}` has unknown tier `{}`, expected one of \
+                                            {}", name, t, TIERS.join(", "))),
+            },
+            None => 0,
+        };
+        if tool.is_some() != (tier > 0) {
+            return Err(format!("item `{}` needs both `tool` and `tier`, or neither", name));
+        }
+
+        // Anything that wears out cannot be stacked.
+        let stack = match (self.stack, self.durability) {
+            (Some(s), _) if s == 0 => {
+                return Err(format!("item `{}` has a stack size of 0", name));
+            },
+            (Some(s), Some(_)) if s > 1 => {
+                return Err(format!("item `{}` has durability, so cannot stack", name));
+            },
+            (Some(s), _) => s,
+            (None, Some(_)) => 1,
+            (None, None) => 64,
+        };
+        let speed = self.speed.unwrap_or(1.0);
+        if speed <= 0.0 {
+            return Err(format!("item `{}` has a speed that is not positive", name));
+        }
+        if self.durability == Some(0) {
+            return Err(format!("item `{}` has a durability of 0", name));
+        }
+
+        Ok(ItemType {
+            name: name,
+            icon: icon,
+            block: block,
+            stack: stack,
+            durability: self.durability,
+            tool: tool,
+            tier: tier,
+            speed: speed,
+            food: self.food.unwrap_or(0),
+        })
+    }
+}
@@ -0,0 +1,382 @@
+pub mod item;
+
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::{
+    self,
+    File,
+};
+
+use toml::{
+    self,
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use errors::Result;
+
+pub use self::item::{
+    Items,
+    ItemId,
+    ItemType,
+    Tool,
+};
+
+// The first slots of every inventory, picked from with the number keys.
+pub const HOTBAR: usize = 9;
+
+// Some number of one item in a slot.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Stack {
+    pub item: ItemId,
+    pub count: u32,
+    // Uses taken out of an item with durability.
+    pub wear: u32,
+}
+
+impl Stack {
+    pub fn new(item: ItemId, count: u32) -> Stack {
+        Stack {
+            item: item,
+            count: count,
+            wear: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    slots: Vec<Option<Stack>>,
+    // Index into the hotbar.
+    selected: usize,
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Inventory {
+        assert!(size >= HOTBAR);
+        Inventory {
+            slots: vec![None; size],
+            selected: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn slot(&self, i: usize) -> Option<&Stack> {
+        self.slots[i].as_ref()
+    }
+
+    pub fn set_slot(&mut self, i: usize, stack: Option<Stack>) {
+        self.slots[i] = stack.and_then(|s| if s.count > 0 { Some(s) } else { None });
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, i: usize) {
+        if i < HOTBAR {
+            self.selected = i;
+        }
+    }
+
+    // Moves the selection `steps` slots along the hotbar, wrapping at the ends.
+    pub fn scroll(&mut self, steps: i32) {
+        let n = HOTBAR as i32;
+        self.selected = (((self.selected as i32 + steps) % n + n) % n) as usize;
+    }
+
+    // The stack in the selected hotbar slot.
+    pub fn held(&self) -> Option<&Stack> {
+        self.slot(self.selected)
+    }
+
+    // How many of `item` are held across every slot.
+    pub fn count(&self, item: ItemId) -> u32 {
+        self.slots.iter()
+            .filter_map(|s| s.as_ref())
+            .filter(|s| s.item == item)
+            .fold(0, |n, s| n + s.count)
+    }
+
+    // Adds `count` of `item`, topping up stacks already held before filling
+    // empty slots, hotbar first. Returns how many did not fit.
+    pub fn add(&mut self, items: &Items, item: ItemId, count: u32) -> u32 {
+        let max = items.get(item).stack();
+        let mut left = count;
+        for slot in self.slots.iter_mut() {
+            if left == 0 {
+                break;
+            }
+            if let Some(ref mut stack) = *slot {
+                if stack.item == item && stack.wear == 0 && stack.count < max {
+                    let n = if max - stack.count < left { max - stack.count } else { left };
+                    stack.count += n;
+                    left -= n;
+                }
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if left == 0 {
+                break;
+            }
+            if slot.is_none() {
+                let n = if max < left { max } else { left };
+                *slot = Some(Stack::new(item, n));
+                left -= n;
+            }
+        }
+        left
+    }
+
+    // Whether every one of `stacks` could be added without any left over.
+    pub fn fits(&self, items: &Items, stacks: &[Stack]) -> bool {
+        let mut copy = self.clone();
+        stacks.iter().all(|s| copy.add(items, s.item, s.count) == 0)
+    }
+
+    // Takes one of the held item, returning which item it was.
+    pub fn take_held(&mut self) -> Option<ItemId> {
+        let selected = self.selected;
+        self.take(selected)
+    }
+
+    // Takes one item out of slot `i`.
+    pub fn take(&mut self, i: usize) -> Option<ItemId> {
+        let (item, empty) = match self.slots[i] {
+            Some(ref mut stack) => {
+                stack.count -= 1;
+                (stack.item, stack.count == 0)
+            },
+            None => return None,
+        };
+        if empty {
+            self.slots[i] = None;
+        }
+        Some(item)
+    }
+
+    // Wears the held item by one use. Returns true if it broke.
+    pub fn wear_held(&mut self, items: &Items) -> bool {
+        let selected = self.selected;
+        let broke = match self.slots[selected] {
+            Some(ref mut stack) => match items.get(stack.item).durability() {
+                Some(durability) => {
+                    stack.wear += 1;
+                    stack.wear >= durability
+                },
+                None => false,
+            },
+            None => false,
+        };
+        if broke {
+            self.slots[selected] = None;
+        }
+        broke
+    }
+
+    // Reads an inventory written by `save`. Items are stored by name, so slots
+    // holding items that no longer exist are an error, as are stacks bigger
+    // than their item's or more worn than it lasts.
+    pub fn load<P>(path: P, items: &Items, size: usize) -> Result<Inventory>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let table = try!(config::parse(&src, path));
+        let mut decoder = Decoder::new(Value::Table(table));
+        let saved = match TomlInventory::decode(&mut decoder) {
+            Ok(saved) => saved,
+            Err(e) => return Err(config::error_at(path, &src, 0, e)),
+        };
+
+        let mut inventory = Inventory::new(size);
+        inventory.select(saved.selected);
+        for (i, slot) in saved.slots.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "slots", i);
+            let item = match items.id(&slot.item) {
+                Some(item) => item,
+                None => {
+                    let desc = format!("no item named `{}`", slot.item);
+                    return Err(config::error_at(path, &src, offset, desc));
+                },
+            };
+            if slot.slot >= size {
+                let desc = format!("slot {} is past the end of the inventory", slot.slot);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            if inventory.slots[slot.slot].is_some() {
+                let desc = format!("slot {} is saved more than once", slot.slot);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            let item_type = items.get(item);
+            if slot.count == 0 || slot.count > item_type.stack() {
+                let desc = format!("slot {} holds {} `{}`, which stacks from 1 to {}",
+                                   slot.slot, slot.count, slot.item, item_type.stack());
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            let worn = match item_type.durability() {
+                Some(durability) => slot.wear >= durability,
+                None => slot.wear > 0,
+            };
+            if worn {
+                let desc = format!("slot {} holds `{}` worn by {}, more than it lasts",
+                                   slot.slot, slot.item, slot.wear);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            inventory.set_slot(slot.slot, Some(Stack {
+                item: item,
+                count: slot.count,
+                wear: slot.wear,
+            }));
+        }
+        Ok(inventory)
+    }
+
+    // Writes to a temporary file first so a failed save leaves the last one
+    // intact.
+    pub fn save<P>(&self, path: P, items: &Items) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let saved = TomlInventory {
+            selected: self.selected,
+            slots: self.slots.iter()
+                .enumerate()
+                .filter_map(|(i, s)| s.as_ref().map(|s| (i, s)))
+                .map(|(i, s)| TomlSlot {
+                    slot: i,
+                    item: String::from(items.get(s.item).name()),
+                    count: s.count,
+                    wear: s.wear,
+                })
+                .collect(),
+        };
+
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(toml::encode_str(&saved).as_bytes()));
+        }
+        try!(fs::rename(&tmp, path));
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+struct TomlInventory {
+    selected: usize,
+    slots: Vec<TomlSlot>,
+}
+
+#[derive(Debug, Clone, RustcEncodable, RustcDecodable)]
+struct TomlSlot {
+    slot: usize,
+    item: String,
+    count: u32,
+    wear: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    use super::{
+        Inventory,
+        Items,
+        ItemId,
+        ItemType,
+        Stack,
+        HOTBAR,
+    };
+
+    const DIRT: ItemId = 0;
+    const PICK: ItemId = 1;
+
+    fn items() -> Items {
+        Items::with_types(vec![
+            ItemType::new("dirt").with_stack(64),
+            ItemType::new("iron_pick").with_durability(100),
+        ])
+    }
+
+    fn saved(name: &str, src: &str) -> ::std::path::PathBuf {
+        let path = env::temp_dir().join(format!("cube-inventory-{}.toml", name));
+        File::create(&path).unwrap().write_all(src.as_bytes()).unwrap();
+        path
+    }
+
+    #[test]
+    fn stacks_fill_up_first() {
+        let items = items();
+        let mut inventory = Inventory::new(HOTBAR);
+        assert_eq!(inventory.add(&items, DIRT, 70), 0);
+        assert_eq!(inventory.slot(0), Some(&Stack::new(DIRT, 64)));
+        assert_eq!(inventory.slot(1), Some(&Stack::new(DIRT, 6)));
+        assert_eq!(inventory.add(&items, DIRT, 10), 0);
+        assert_eq!(inventory.slot(1), Some(&Stack::new(DIRT, 16)));
+        assert_eq!(inventory.count(DIRT), 80);
+    }
+
+    #[test]
+    fn full_inventories_say_what_is_left() {
+        let items = items();
+        let mut inventory = Inventory::new(HOTBAR);
+        assert_eq!(inventory.add(&items, PICK, HOTBAR as u32 - 1), 0);
+        assert!(inventory.fits(&items, &[Stack::new(DIRT, 64)]));
+        assert!(!inventory.fits(&items, &[Stack::new(DIRT, 65)]));
+        assert!(!inventory.fits(&items, &[Stack::new(DIRT, 1), Stack::new(PICK, 1)]));
+        assert_eq!(inventory.add(&items, DIRT, 100), 36);
+        assert_eq!(inventory.count(DIRT), 64);
+    }
+
+    #[test]
+    fn round_trip() {
+        let items = items();
+        let mut inventory = Inventory::new(HOTBAR + 3);
+        inventory.add(&items, DIRT, 100);
+        inventory.set_slot(HOTBAR + 2, Some(Stack { item: PICK, count: 1, wear: 42 }));
+        inventory.select(4);
+
+        let path = env::temp_dir().join("cube-inventory-round-trip.toml");
+        inventory.save(&path, &items).unwrap();
+        let loaded = Inventory::load(&path, &items, HOTBAR + 3).unwrap();
+        assert_eq!(loaded.selected(), 4);
+        for i in 0..inventory.len() {
+            assert_eq!(loaded.slot(i), inventory.slot(i));
+        }
+    }
+
+    #[test]
+    fn bad_saves_are_rejected() {
+        let items = items();
+        let bad = [
+            ("unknown", 0, "gold", 1, 0),
+            ("past-end", HOTBAR, "dirt", 1, 0),
+            ("overfull", 0, "dirt", 65, 0),
+            ("empty", 0, "dirt", 0, 0),
+            ("worn-out", 0, "iron_pick", 1, 100),
+            ("worn-dirt", 0, "dirt", 1, 1),
+        ];
+        for &(name, slot, item, count, wear) in bad.iter() {
+            let src = format!("selected = 0\n[[slots]]\nslot = {}\nitem = \"{}\"\n\
+                               count = {}\nwear = {}\n", slot, item, count, wear);
+            let path = saved(name, &src);
+            assert!(Inventory::load(&path, &items, HOTBAR).is_err(), "{} loaded", name);
+        }
+
+        let twice = "selected = 0\n\
+                     [[slots]]\nslot = 0\nitem = \"dirt\"\ncount = 1\nwear = 0\n\
+                     [[slots]]\nslot = 0\nitem = \"dirt\"\ncount = 1\nwear = 0\n";
+        assert!(Inventory::load(&saved("twice", twice), &items, HOTBAR).is_err());
+    }
+}
@@ -18,6 +18,10 @@ extern crate term;
 use std::fs::File;
 use std::io::prelude::*;
 use std::env;
+use std::path::Path;
+use std::collections::hash_map::{
+    HashMap,
+};
 
 use num::One;
 use gel::{
@@ -36,6 +40,7 @@ use glium::{
     DepthTest,
 };
 use glium::texture::SrgbTexture2d;
+use image::Rgba;
 use glium::uniforms::{
     SamplerWrapFunction,
     MinifySamplerFilter,
@@ -50,19 +55,102 @@ use glutin::{
 };
 
 use cube::Cube;
+use debug_draw::DebugDraw;
 use resource::shader::{
     Manager,
 };
+use resource::atlas::{
+    self,
+    AtlasBuilder,
+};
 use config::Config;
 use process::Process;
 use camera::Camera;
 use input::{
     Input,
     Key,
+    MouseButton,
+};
+use delta::{
+    Delta,
+    FixedStep,
 };
-use delta::Delta;
 use player::Player;
 use light::Light;
+use sky::{
+    Clock,
+    Bodies,
+    Skybox,
+    Clouds,
+};
+use world::{
+    World,
+    ChunkPos,
+    BlockPos,
+    BlockId,
+    Blocks,
+    BlocksMut,
+    Face,
+    AIR,
+};
+use world::ray;
+use world::shape;
+use world::mesh::Shape;
+use world::tick::RandomTicks;
+use farm::{
+    Farming,
+    MOST_HARVEST,
+};
+use fluid::Fluids;
+use rail::{
+    Rails,
+    Minecart,
+};
+use world::region::RegionStore;
+use gen::Generator;
+use gen::noise;
+use gen::structure::Structures;
+use block::Registry;
+use crafting::{
+    Recipes,
+    Smelting,
+    Oven,
+    GRID,
+};
+use crafting::smelting;
+use mining::{
+    Mining,
+    Dig,
+};
+use mob::{
+    Mobs,
+    MobTypes,
+    Surroundings,
+};
+use mob::render::ArrowRenderer;
+use model::{
+    Models,
+    ModelRenderer,
+};
+use model::transform;
+use model::animation::{
+    Clips,
+    ClipSet,
+    Motion,
+};
+use particle::{
+    Particles,
+    Presets,
+    Emitter,
+    ParticleRenderer,
+};
+use inventory::{
+    Inventory,
+    Items,
+    Stack,
+    Tool,
+    HOTBAR,
+};
 use errors::{
     Result,
     Error,
@@ -80,6 +168,20 @@ mod player;
 mod cursor;
 mod light;
 mod file;
+mod world;
+mod block;
+mod gen;
+mod debug_draw;
+mod farm;
+mod fluid;
+mod rail;
+mod inventory;
+mod mining;
+mod crafting;
+mod mob;
+mod model;
+mod sky;
+mod particle;
 
 fn main() {
     let process = Process::new(execute);
@@ -101,18 +203,65 @@ fn execute() -> Result<()> {
         try!(target.finish());
     }
 
-    let player_params = config.player();
-    let diffuse_map_path = config.paths().assets().join(player_params.diffuse_map());
-    let diffuse_map_img = try!(image::open(diffuse_map_path));
-    let diffuse_map = try!(SrgbTexture2d::new(&display, diffuse_map_img));
-    let specular_map_path = config.paths().assets().join(player_params.specular_map());
-    let specular_map_img = try!(image::open(specular_map_path));
-    let specular_map = try!(SrgbTexture2d::new(&display, specular_map_img));
-    let mut player: Player = try!(Player::new(&display,
-                                              diffuse_map,
-                                              specular_map,
-                                              player_params.shine(),
-                                              player_params));
+    let mut player = Player::new(config.player());
+
+    let characters_path = config.paths().assets().join("characters");
+    let models_path = Path::new(&config_path).with_file_name("models.toml");
+    let models = try!(Models::load(&models_path, &characters_path));
+    let model_renderer = try!(ModelRenderer::new(&display, &models, &characters_path));
+    let player_model = try!(models.require(config.player().model()));
+    let clips_path = Path::new(&config_path).with_file_name("animations.toml");
+    let clips = try!(Clips::load(&clips_path));
+    let player_clips = try!(ClipSet::for_model(models.get(player_model), &clips));
+
+    let atlas_params = config.atlas();
+    let tiles_path = config.paths().assets().join(atlas_params.tiles());
+    let mut atlas_builder = AtlasBuilder::new(atlas_params.padding());
+    try!(atlas_builder.add_dir(&tiles_path, "_specular"));
+    let atlas = try!(atlas_builder.build());
+
+    let blocks_path = Path::new(&config_path).with_file_name("blocks.toml");
+    let registry = try!(Registry::load(&blocks_path, &atlas, &tiles_path));
+
+    let atlas_specular = try!(atlas.companion(&tiles_path,
+                                              "_specular",
+                                              &registry.specular_tiles(),
+                                              Rgba([0, 0, 0, 255])));
+    if let Some(dump) = atlas_params.dump() {
+        try!(atlas.save(dump, "atlas"));
+        try!(atlas_specular.save(dump.join("atlas_specular.png")));
+    }
+    let world_diffuse_map = try!(atlas.to_texture(&display));
+    let world_specular_map = try!(atlas::to_texture(&display, &atlas_specular));
+
+    let world_params = config.world();
+    let structures_path = Path::new(&config_path).with_file_name("structures.toml");
+    let structures = try!(Structures::load(config.terrain().seed(), &structures_path, &registry));
+    let generator = try!(Generator::new(config.terrain(),
+                                        config.underground(),
+                                        structures,
+                                        &registry));
+    let save_dir = world_params.save().map(|dir| Path::new(&config_path).with_file_name(dir));
+    let mut store = match save_dir {
+        Some(ref dir) => Some(try!(RegionStore::new(dir))),
+        None => None,
+    };
+    let mut world = World::new();
+    world.set_greedy(world_params.greedy());
+    let radius = world_params.radius();
+    for x in -radius..radius + 1 {
+        for z in -radius..radius + 1 {
+            let pos = ChunkPos::new(x, z);
+            let saved = match store {
+                Some(ref mut store) => try!(store.load_chunk(pos)),
+                None => None,
+            };
+            world.insert(saved.unwrap_or_else(|| generator.generate(pos)));
+        }
+    }
+    try!(world.update_meshes(&display, &registry, &atlas));
+    let spawn = Vec3::new(0.5, generator.spawn_height(0, 0) as f32 + 1.0, 0.5);
+    player.set_position(spawn);
 
     let mut shaders = Manager::new();
 
@@ -124,61 +273,488 @@ fn execute() -> Result<()> {
 
     try!(shaders.compile(&display, "cube.vert", "cube.frag"));
 
-    let light_vert_src = try!(file::load(config.paths().shaders().join("light.vert")));
-    shaders.store("light.vert", light_vert_src);
+    let billboard_vert_src = try!(file::load(config.paths().shaders().join("billboard.vert")));
+    shaders.store("billboard.vert", billboard_vert_src);
+
+    let billboard_frag_src = try!(file::load(config.paths().shaders().join("billboard.frag")));
+    shaders.store("billboard.frag", billboard_frag_src);
+
+    try!(shaders.compile(&display, "billboard.vert", "billboard.frag"));
+
+    let skybox_vert_src = try!(file::load(config.paths().shaders().join("skybox.vert")));
+    shaders.store("skybox.vert", skybox_vert_src);
+
+    let skybox_frag_src = try!(file::load(config.paths().shaders().join("skybox.frag")));
+    shaders.store("skybox.frag", skybox_frag_src);
+
+    try!(shaders.compile(&display, "skybox.vert", "skybox.frag"));
+
+    let clouds_vert_src = try!(file::load(config.paths().shaders().join("clouds.vert")));
+    shaders.store("clouds.vert", clouds_vert_src);
+
+    let clouds_frag_src = try!(file::load(config.paths().shaders().join("clouds.frag")));
+    shaders.store("clouds.frag", clouds_frag_src);
+
+    try!(shaders.compile(&display, "clouds.vert", "clouds.frag"));
+
+    let particle_vert_src = try!(file::load(config.paths().shaders().join("particle.vert")));
+    shaders.store("particle.vert", particle_vert_src);
 
-    let light_frag_src = try!(file::load(config.paths().shaders().join("light.frag")));
-    shaders.store("light.frag", light_frag_src);
+    let particle_frag_src = try!(file::load(config.paths().shaders().join("particle.frag")));
+    shaders.store("particle.frag", particle_frag_src);
 
-    try!(shaders.compile(&display, "light.vert", "light.frag"));
+    try!(shaders.compile(&display, "particle.vert", "particle.frag"));
+
+    let debug_vert_src = try!(file::load(config.paths().shaders().join("debug.vert")));
+    shaders.store("debug.vert", debug_vert_src);
+
+    let debug_frag_src = try!(file::load(config.paths().shaders().join("debug.frag")));
+    shaders.store("debug.frag", debug_frag_src);
+
+    try!(shaders.compile(&display, "debug.vert", "debug.frag"));
 
     let winref = try!(display.get_window()
                       .ok_or(Error::with_detail("window error",
                                                 "failed to get window reference")));
 
-    let mut light: Light = try!(Light::new(&display, config.light()));
+    let mut light = Light::new(config.light());
+    let mut clock = Clock::new(config.day());
+    let bodies = try!(Bodies::new(&display, config.day(), config.paths().assets()));
+    let skybox = try!(Skybox::new(&display, config.skybox(), config.paths().assets()));
+    let mut clouds = if config.sky().clouds() {
+        Some(try!(Clouds::new(&display, config.sky(), config.paths().assets())))
+    } else {
+        None
+    };
 
     let mut input = try!(Input::new(winref));
 
     let mut camera = Camera::new(config.camera());
 
+    let items_path = Path::new(&config_path).with_file_name("items.toml");
+    let items = try!(Items::load(&items_path, &registry, config.paths().assets().join("items")));
+    let seed_item = try!(items.require("seed"));
+    let wheat_item = try!(items.require("wheat"));
+
+    let slots = config.player().slots();
+    if slots < HOTBAR {
+        return Err(Error::with_detail("config error",
+                                      format!("the inventory needs at least {} slots", HOTBAR)));
+    }
+    let inventory_path = save_dir.as_ref().map(|dir| dir.join("inventory.toml"));
+    let mut inventory = match inventory_path {
+        Some(ref path) if path.exists() => try!(Inventory::load(path, &items, slots)),
+        _ => {
+            let mut inventory = Inventory::new(slots);
+            for name in config.player().items() {
+                let item = try!(items.require(name));
+                inventory.add(&items, item, items.get(item).stack());
+            }
+            inventory
+        },
+    };
+    let mut scrolled = 0.0;
+
+    let mining = Mining::new(config.mining());
+    let mut dig: Option<Dig> = None;
+
+    let recipes_path = Path::new(&config_path).with_file_name("recipes.toml");
+    let recipes = try!(Recipes::load(&recipes_path, &items));
+    let smelting_path = Path::new(&config_path).with_file_name("smelting.toml");
+    let smelting = try!(Smelting::load(&smelting_path, &items));
+    let table_block = try!(registry.require("table"));
+    let oven_block = try!(registry.require("oven"));
+    let ovens_path = save_dir.as_ref().map(|dir| dir.join("ovens.toml"));
+    let mut ovens: HashMap<BlockPos, Oven> = match ovens_path {
+        Some(ref path) if path.exists() => try!(smelting::load_ovens(path, &items)),
+        _ => HashMap::new(),
+    };
+
+    let seed = config.terrain().seed();
+    let farming = try!(Farming::new(&registry, config.ticks().growth()));
+    let mut block_ticks = FixedStep::new(1.0 / config.ticks().rate());
+    let mut random_ticks = RandomTicks::new([seed | 1, seed ^ 0x7469636b, 1, 2],
+                                            config.ticks().random());
+    let mut rng = noise::rng(seed, 0x6661726d);
+    let mut fluids = try!(Fluids::new(&registry, config.fluids()));
+
+    let rails = try!(Rails::new(&registry));
+    let mut cart: Option<Minecart> = None;
+    let mut riding = false;
+    let cart_texture_path = config.paths().assets().join(config.minecart().texture());
+    let cart_texture = try!(SrgbTexture2d::new(&display, try!(image::open(cart_texture_path))));
+    let mut cart_model = try!(Cube::new(&display));
+    cart_model.set_scale(config.minecart().scale());
+
+    let mobs_path = Path::new(&config_path).with_file_name("mobs.toml");
+    let mob_types = try!(MobTypes::load(&mobs_path));
+    // Mobs are drawn with the model of the same name.
+    let mut mob_models = Vec::with_capacity(mob_types.len());
+    let mut mob_clips = Vec::with_capacity(mob_types.len());
+    for mob_type in mob_types.iter() {
+        let id = try!(models.require(mob_type.name()));
+        mob_models.push(id);
+        mob_clips.push(try!(ClipSet::for_model(models.get(id), &clips)));
+    }
+    let mut mobs = Mobs::new(config.mobs(), seed);
+    let arrow_path = config.paths().assets().join("items").join("arrow.png");
+    let arrow_renderer = try!(ArrowRenderer::new(&display, arrow_path));
+
+    let presets_path = Path::new(&config_path).with_file_name("particles.toml");
+    let particles_dir = config.paths().assets().join("particles");
+    let presets = try!(Presets::load(&presets_path, &particles_dir));
+    let mut particle_renderer = try!(ParticleRenderer::new(&display, &presets, &particles_dir));
+    let break_preset = try!(presets.require("break"));
+    let smoke_preset = try!(presets.require("smoke"));
+    let flame_preset = try!(presets.require("flame"));
+    let mut particles = Particles::new(seed);
+    // The smoke and flames of each lit oven.
+    let mut oven_emitters: HashMap<BlockPos, [Emitter; 2]> = HashMap::new();
+
     let mut delta = Delta::new();
+    // Seconds played, and the player's walking and swinging, for animation.
+    let mut elapsed = 0.0;
+    let mut walked = player.walked();
+    let mut swung: Option<f32> = None;
 
     let player_program = try!(shaders.load("cube.vert", "cube.frag"));
-    let light_program = try!(shaders.load("light.vert", "light.frag"));
+    let billboard_program = try!(shaders.load("billboard.vert", "billboard.frag"));
+    let skybox_program = try!(shaders.load("skybox.vert", "skybox.frag"));
+    let clouds_program = try!(shaders.load("clouds.vert", "clouds.frag"));
+    let particle_program = try!(shaders.load("particle.vert", "particle.frag"));
+    let debug_program = try!(shaders.load("debug.vert", "debug.frag"));
+
+    let mut debug = DebugDraw::new();
 
     'main: loop {
         let dtime = delta.update();
+        elapsed += dtime;
+        swung = swung.map(|t| t + dtime);
 
         let events = display.poll_events();
         input.update(events);
         if input.should_close() {
+            if let Some(ref mut store) = store {
+                try!(store.save_world(&world));
+            }
+            if let Some(ref path) = inventory_path {
+                try!(inventory.save(path, &items));
+            }
+            if let Some(ref path) = ovens_path {
+                try!(smelting::save_ovens(path, &ovens, &items));
+            }
             break 'main;
         }
-        if input.is_pressed(Key::Comma) {
-            player.forward(dtime);
-        }
-        if input.is_pressed(Key::O) {
-            player.backward(dtime);
-        }
-        if input.is_pressed(Key::A) {
-            player.left(dtime);
+        if input.is_tapped(Key::F3) {
+            debug.toggle();
         }
-        if input.is_pressed(Key::E) {
-            player.right(dtime);
+        let number_keys = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+                           Key::Key6, Key::Key7, Key::Key8, Key::Key9];
+        for (i, key) in number_keys.iter().enumerate() {
+            if input.is_tapped(*key) {
+                inventory.select(i);
+            }
         }
-        if input.is_pressed(Key::Space) {
-            player.up(dtime);
+        // Scrolling up moves towards the start of the hotbar.
+        scrolled += input.scroll();
+        let steps = scrolled.trunc();
+        scrolled -= steps;
+        inventory.scroll(-steps as i32);
+        if input.is_tapped(Key::R) {
+            riding = !riding && cart.as_ref().map_or(false, |c| {
+                let d = c.position(&world, &rails) - player.position();
+                d.x * d.x + d.y * d.y + d.z * d.z < 9.0
+            });
         }
-        if input.is_pressed(Key::LControl) {
-            player.down(dtime);
+        if riding {
+            let facing = camera.heading();
+            let push = config.minecart().push() * dtime;
+            if let Some(ref mut cart) = cart {
+                if input.is_pressed(Key::Comma) {
+                    cart.push(&world, &rails, facing, push);
+                }
+                if input.is_pressed(Key::O) {
+                    cart.push(&world, &rails, facing, -push);
+                }
+            }
+        } else {
+            let start = player.position();
+            if input.is_pressed(Key::Comma) {
+                player.forward(dtime);
+            }
+            if input.is_pressed(Key::O) {
+                player.backward(dtime);
+            }
+            if input.is_pressed(Key::A) {
+                player.left(dtime);
+            }
+            if input.is_pressed(Key::E) {
+                player.right(dtime);
+            }
+            if input.is_pressed(Key::Space) {
+                player.up(dtime);
+            }
+            if input.is_pressed(Key::LControl) {
+                player.down(dtime);
+            }
+            // Go only as far as the blocks in the way allow.
+            let d = player.position() - start;
+            player.set_position(start);
+            let moved = shape::slide(&world,
+                                     &registry,
+                                     &player.bounds(),
+                                     [d.x, d.y, d.z],
+                                     &|id| id != AIR && !fluids.is_fluid(id));
+            player.set_position(start + Vec3::new(moved[0], moved[1], moved[2]));
         }
 
         let (dx, dy) = input.cursor().get_delta();
         camera.update(dx, dy);
 
+        // Blocks break after the button has been held on them long enough.
+        let breaking = input.is_button_pressed(MouseButton::Left);
+        let placing = input.is_clicked(MouseButton::Right);
+        let hoeing = input.is_clicked(MouseButton::Middle);
+        if !breaking {
+            dig = None;
+        }
+        if input.is_clicked(MouseButton::Left) || placing || hoeing {
+            swung = Some(0.0);
+        }
+        // Clicking a mob in reach, in front of any block, hits it instead.
+        let mut struck = false;
+        if input.is_clicked(MouseButton::Left) {
+            let eye = camera.eye(player.position());
+            let reach = camera.radius() + config.player().reach();
+            let block = ray::cast(&world,
+                                  eye,
+                                  player.position() - eye,
+                                  reach,
+                                  |id| id != AIR && !fluids.is_fluid(id));
+            let damage = match inventory.held().map(|s| items.get(s.item)) {
+                Some(item) if item.tool() == Some(Tool::Sword) => {
+                    (config.player().damage() as f32 * item.speed()) as u32
+                },
+                _ => config.player().damage(),
+            };
+            let reach = block.map_or(reach, |h| h.distance);
+            struck = mobs.attack(&mob_types, eye, player.position() - eye, reach, damage);
+            if struck {
+                dig = None;
+            }
+        }
+        if !struck && (breaking || placing || hoeing) {
+            let eye = camera.eye(player.position());
+            let hit = ray::cast(&world,
+                                eye,
+                                player.position() - eye,
+                                camera.radius() + config.player().reach(),
+                                |id| id != AIR && !fluids.is_fluid(id));
+            if hit.map_or(true, |h| dig.map_or(false, |d| d.pos() != h.block)) {
+                dig = None;
+            }
+            if let Some(hit) = hit {
+                let held = inventory.held().map(|s| s.item);
+                if breaking {
+                    let broken = match registry.get(world.block(hit.block)) {
+                        Some(block) => {
+                            let tool = held.map(|item| items.get(item));
+                            let time = mining.time(block, tool);
+                            let drop = if mining.drops(block, tool) { block.drop() } else { None };
+                            let worn = tool.map_or(false, |t| t.tool().is_some()) &&
+                                block.hardness() > 0.0;
+                            let mut progress = dig.unwrap_or(Dig::new(hit.block));
+                            let done = progress.advance(dtime, time);
+                            dig = Some(progress);
+                            if done { Some((drop.and_then(|d| items.id(d)), worn)) } else { None }
+                        },
+                        None => None,
+                    };
+                    if let Some((drop, worn)) = broken {
+                        // Whatever was in an oven comes out with it. Blocks
+                        // only break once everything they give fits.
+                        let mut gained: Vec<Stack> = drop.map(|d| Stack::new(d, 1))
+                            .into_iter()
+                            .collect();
+                        if let Some(oven) = ovens.get(&hit.block) {
+                            gained.extend(oven.input().into_iter()
+                                          .chain(oven.fuel())
+                                          .chain(oven.output())
+                                          .cloned());
+                        }
+                        if inventory.fits(&items, &gained) {
+                            world.set_block(hit.block, AIR);
+                            ovens.remove(&hit.block);
+                            let b = hit.block;
+                            let centre = Vec3::new(b.x as f32, b.y as f32, b.z as f32) +
+                                         Vec3::repeat(0.5);
+                            particles.emit(break_preset, centre);
+                            for stack in gained {
+                                inventory.add(&items, stack.item, stack.count);
+                            }
+                            if worn {
+                                inventory.wear_held(&items);
+                            }
+                        }
+                        dig = None;
+                    }
+                } else if hoeing {
+                    // Tilling needs a hoe in hand, which wears with use.
+                    let hoe = held.map_or(false, |item| items.get(item).tool() == Some(Tool::Hoe));
+                    if hoe && farming.hoe(&mut world, hit.block) {
+                        inventory.wear_held(&items);
+                    }
+                } else if world.block(hit.block) == table_block {
+                    // The hotbar is laid out as the crafting grid, in rows.
+                    let cells: Vec<_> = (0..GRID * GRID)
+                        .map(|i| inventory.slot(i).map(|s| s.item))
+                        .collect();
+                    // Inputs are only used up if what they make fits.
+                    if let Some(made) = recipes.find(GRID, &cells) {
+                        let mut crafted = inventory.clone();
+                        for i in 0..GRID * GRID {
+                            crafted.take(i);
+                        }
+                        if crafted.add(&items, made.item, made.count) == 0 {
+                            inventory = crafted;
+                        }
+                    }
+                } else if world.block(hit.block) == oven_block {
+                    let oven = ovens.entry(hit.block).or_insert(Oven::new());
+                    let selected = inventory.selected();
+                    match inventory.held().cloned() {
+                        Some(stack) if smelting.smelt(stack.item).is_some() => {
+                            inventory.set_slot(selected, oven.put_input(stack, &items));
+                        },
+                        Some(stack) if smelting.burn_time(stack.item).is_some() => {
+                            inventory.set_slot(selected, oven.put_fuel(stack, &items));
+                        },
+                        _ => {
+                            // Output stays in the oven until it all fits.
+                            let fits = oven.output()
+                                .map_or(false, |&out| inventory.fits(&items, &[out]));
+                            if fits {
+                                if let Some(out) = oven.take_output() {
+                                    inventory.add(&items, out.item, out.count);
+                                }
+                            }
+                        },
+                    }
+                } else if farming.is_ripe(world.block(hit.block)) &&
+                          !inventory.fits(&items, &[Stack::new(wheat_item, MOST_HARVEST.wheat),
+                                                    Stack::new(seed_item, MOST_HARVEST.seeds)]) {
+                    // Ripe crops stay standing until whatever they give fits.
+                } else if let Some(harvest) = farming.harvest(&mut world, hit.block, &mut rng) {
+                    inventory.add(&items, wheat_item, harvest.wheat);
+                    inventory.add(&items, seed_item, harvest.seeds);
+                } else if hit.face == Face::Top && held == Some(seed_item) {
+                    if farming.plant(&mut world, hit.block) {
+                        inventory.take_held();
+                    }
+                } else if rails.is_rail(world.block(hit.block)) {
+                    cart = Minecart::new(&world, &rails, hit.block).map(|mut c| {
+                        c.set_friction(config.minecart().friction());
+                        c.set_max_speed(config.minecart().max_speed());
+                        c
+                    });
+                    riding = false;
+                } else {
+                    let target = hit.block.neighbour(hit.face);
+                    let p = player.position();
+                    let inside = BlockPos::new(p.x.floor() as i32,
+                                               p.y.floor() as i32,
+                                               p.z.floor() as i32);
+                    let block = held.and_then(|item| items.get(item).block());
+                    if let Some(block) = block {
+                        let free = world.block(target) == AIR ||
+                            fluids.is_fluid(world.block(target));
+                        if target != inside && free {
+                            if rails.is_rail(block) {
+                                rails.place(&mut world, target);
+                            } else {
+                                world.set_block(target, block);
+                            }
+                            inventory.take_held();
+                        }
+                    }
+                }
+                fluids.notify(&world, hit.block);
+                fluids.notify(&world, hit.block.neighbour(hit.face));
+            }
+        }
+
+        let steps = block_ticks.advance(dtime, 4);
+        for _ in 0..steps {
+            random_ticks.tick(&mut world, |world, pos, rng| farming.random_tick(world, pos, rng));
+        }
+        fluids.update(&mut world, dtime);
+        for oven in ovens.values_mut() {
+            oven.update(&smelting, &items, dtime);
+        }
+        for (&pos, oven) in ovens.iter() {
+            if oven.is_lit() {
+                let top = Vec3::new(pos.x as f32 + 0.5, pos.y as f32 + 1.0, pos.z as f32 + 0.5);
+                let emitters = oven_emitters.entry(pos).or_insert([
+                    Emitter::new(smoke_preset, top),
+                    Emitter::new(flame_preset, top),
+                ]);
+                for emitter in emitters.iter_mut() {
+                    emitter.update(&presets, &mut particles, dtime);
+                }
+            }
+        }
+        let out: Vec<BlockPos> = oven_emitters.keys()
+            .filter(|pos| !ovens.get(pos).map_or(false, |o| o.is_lit()))
+            .cloned()
+            .collect();
+        for pos in out {
+            oven_emitters.remove(&pos);
+        }
+        particles.update(&presets, dtime);
+        let damage = {
+            // Mobs walk over anything solid that is not flat, like rails.
+            let solid = |id: BlockId| id != AIR && !fluids.is_fluid(id) &&
+                registry.get(id).map_or(false, |b| b.shape() != Shape::Flat);
+            let surroundings = Surroundings {
+                blocks: &world,
+                shapes: &registry,
+                solid: &solid,
+                terrain: generator.terrain(),
+                player: player.position(),
+                daylight: clock.daylight(),
+            };
+            mobs.update(&mob_types, &surroundings, dtime)
+        };
+        if player.hurt(damage) {
+            player.respawn(spawn);
+            riding = false;
+        }
+
+        if let Some(ref mut cart) = cart {
+            cart.update(&world, &rails, dtime);
+            let base = cart.position(&world, &rails);
+            let scale = config.minecart().scale();
+            cart_model.set_position(base + Vec3::new(0.0, scale, 0.0));
+            if riding {
+                let height = scale * 2.0 + config.player().scale();
+                player.set_position(base + Vec3::new(0.0, height, 0.0));
+            }
+        } else {
+            riding = false;
+        }
+        try!(world.update_meshes(&display, &registry, &atlas));
+
+        clock.update(dtime);
+        if let Some(ref mut clouds) = clouds {
+            clouds.update(dtime);
+        }
+        let sky = clock.sky(config.day());
+        light.set_direction(clock.light());
+        light.set_ambient(sky.ambient);
+        light.set_diffuse(sky.diffuse);
+
         let mut target = display.draw();
-        target.clear_color(0.01, 0.01, 0.01, 1.0);
+        target.clear_color(sky.clear.x, sky.clear.y, sky.clear.z, 1.0);
         target.clear_depth(1.0);
 
         let (width, height) = target.get_dimensions();
@@ -194,31 +770,201 @@ fn execute() -> Result<()> {
 
         {
             let uniforms = uniform! {
-                model: player.model(),
+                view: view,
+                projection: projection,
+            };
+            try!(skybox.draw(&mut target,
+                             skybox_program,
+                             &uniforms,
+                             sky.clear,
+                             clock.daylight()));
+            try!(bodies.draw(&mut target,
+                             billboard_program,
+                             &uniforms,
+                             camera.eye(player.position()),
+                             &clock));
+        }
+
+        {
+            let uniforms = uniform! {
+                view: view,
+                projection: projection,
+
+                light_dir: light.direction(),
+                light_color: light.color(),
+                light_ambient: light.ambient(),
+                light_diffuse: light.diffuse(),
+                light_specular: light.specular(),
+
+                shine: player.shine(),
+                cutoff: world_params.cutoff(),
+            };
+            let motion = Motion {
+                time: elapsed,
+                distance: player.walked(),
+                speed: if dtime > 0.0 { (player.walked() - walked) / dtime } else { 0.0 },
+                attack: swung,
+            };
+            walked = player.walked();
+            let placement = transform::placement(player.feet(), player.yaw());
+            let pose = player_clips.pose(&clips, models.get(player_model), &motion);
+            try!(model_renderer.draw(&mut target,
+                                     player_program,
+                                     &uniforms,
+                                     &models,
+                                     player_model,
+                                     &placement,
+                                     &pose));
+            for mob in mobs.mobs() {
+                let model = mob_models[mob.kind()];
+                let v = mob.velocity();
+                let motion = Motion {
+                    time: mob.age(),
+                    distance: mob.distance(),
+                    speed: (v.x * v.x + v.z * v.z).sqrt(),
+                    attack: mob.attacked(),
+                };
+                let placement = transform::placement(mob.position(), mob.yaw());
+                let pose = mob_clips[mob.kind()].pose(&clips, models.get(model), &motion);
+                try!(model_renderer.draw(&mut target,
+                                         player_program,
+                                         &uniforms,
+                                         &models,
+                                         model,
+                                         &placement,
+                                         &pose));
+            }
+        }
+
+        {
+            let uniforms = uniform! {
+                view: view,
+                projection: projection,
+            };
+            try!(arrow_renderer.draw(&mut target, billboard_program, &uniforms, mobs.arrows()));
+        }
+
+        if cart.is_some() {
+            let uniforms = uniform! {
+                model: cart_model.model(),
                 view: view,
                 projection: projection,
 
-                diffuse_map: player.diffuse_map(),
-                specular_map: player.specular_map(),
+                diffuse_map: cart_texture.sampled()
+                    .magnify_filter(MagnifySamplerFilter::Nearest),
+                specular_map: cart_texture.sampled()
+                    .magnify_filter(MagnifySamplerFilter::Nearest),
 
-                light_pos: light.position(),
+                light_dir: light.direction(),
                 light_color: light.color(),
                 light_ambient: light.ambient(),
                 light_diffuse: light.diffuse(),
                 light_specular: light.specular(),
 
                 shine: player.shine(),
+                cutoff: world_params.cutoff(),
+            };
+            try!(cart_model.draw(&mut target, player_program, &uniforms));
+        }
+
+        {
+            let uniforms = uniform! {
+                model: Mat4::one(),
+                view: view,
+                projection: projection,
+
+                diffuse_map: world_diffuse_map.sampled()
+                    .wrap_function(SamplerWrapFunction::Clamp)
+                    .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+                    .magnify_filter(MagnifySamplerFilter::Nearest),
+                specular_map: world_specular_map.sampled()
+                    .wrap_function(SamplerWrapFunction::Clamp)
+                    .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+                    .magnify_filter(MagnifySamplerFilter::Nearest),
+
+                light_dir: light.direction(),
+                light_color: light.color(),
+                light_ambient: light.ambient(),
+                light_diffuse: light.diffuse(),
+                light_specular: light.specular(),
+
+                shine: world_params.shine(),
             };
-            try!(player.draw(&mut target, player_program, &uniforms));
+            try!(world.draw(&mut target, player_program, &uniforms, world_params.cutoff()));
+            try!(world.draw_translucent(&display,
+                                        &mut target,
+                                        player_program,
+                                        &uniforms,
+                                        camera.eye(player.position())));
+        }
+
+        if let Some(ref clouds) = clouds {
+            let uniforms = uniform! {
+                view: view,
+                projection: projection,
+            };
+            // Lit as brightly as the ground, but never more than white.
+            let lit = sky.ambient + sky.diffuse;
+            let tint = Vec3::new(lit.x.min(1.0), lit.y.min(1.0), lit.z.min(1.0));
+            try!(clouds.draw(&mut target,
+                             clouds_program,
+                             &uniforms,
+                             camera.eye(player.position()),
+                             tint));
         }
 
         {
             let uniforms = uniform! {
-                model: light.model(),
                 view: view,
                 projection: projection,
             };
-            try!(light.draw(&mut target, light_program, &uniforms));
+            try!(particle_renderer.draw(&display,
+                                        &mut target,
+                                        particle_program,
+                                        &uniforms,
+                                        particles.particles()));
+        }
+
+        if debug.is_enabled() {
+            let p = player.position();
+            let bounds = player.bounds();
+            debug.aabb(Vec3::new(bounds.min[0], bounds.min[1], bounds.min[2]),
+                       Vec3::new(bounds.max[0], bounds.max[1], bounds.max[2]),
+                       Vec3::new(1.0, 1.0, 0.0));
+            debug.axes(p, 2.0);
+            debug.line(p, p + light.direction() * 4.0, Vec3::new(1.0, 1.0, 1.0));
+
+            for mob in mobs.mobs() {
+                let mob_type = mob_types.get(mob.kind());
+                let p = mob.position();
+                let half = mob_type.width() / 2.0;
+                debug.aabb(p - Vec3::new(half, 0.0, half),
+                           p + Vec3::new(half, mob_type.height() as f32, half),
+                           Vec3::new(1.0, 0.0, 1.0));
+            }
+            for arrow in mobs.arrows() {
+                let p = arrow.position();
+                let v = arrow.velocity();
+                let tail = 0.5 / (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+                debug.line(p - Vec3::new(v.x * tail, v.y * tail, v.z * tail),
+                           p,
+                           Vec3::new(1.0, 1.0, 1.0));
+            }
+
+            let eye = camera.eye(player.position());
+            let hit = ray::cast(&world,
+                                eye,
+                                player.position() - eye,
+                                camera.radius() + config.player().reach(),
+                                |id| id != AIR && !fluids.is_fluid(id));
+            if let Some(hit) = hit {
+                for aabb in shape::collision_boxes(&world, &registry, hit.block) {
+                    debug.aabb(Vec3::new(aabb.min[0], aabb.min[1], aabb.min[2]),
+                               Vec3::new(aabb.max[0], aabb.max[1], aabb.max[2]),
+                               Vec3::new(1.0, 0.0, 0.0));
+                }
+            }
+            try!(debug.flush(&display, &mut target, debug_program, view, projection));
         }
 
         try!(target.finish());
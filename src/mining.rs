@@ -0,0 +1,143 @@
+use block::BlockType;
+use config::MiningParams;
+use inventory::ItemType;
+use world::BlockPos;
+
+// How long blocks take to break, and whether they drop anything, depending on
+// what is held. Blocks take `seconds` per point of hardness by hand, divided by
+// the speed of a tool they list. A block with a tier drops nothing unless it is
+// broken with one of its tools of that tier or better, and takes `penalty`
+// times as long without one.
+#[derive(Debug, Copy, Clone)]
+pub struct Mining {
+    seconds: f32,
+    penalty: f32,
+}
+
+impl Mining {
+    pub fn new(params: &MiningParams) -> Mining {
+        Mining::with_times(params.seconds(), params.penalty())
+    }
+
+    pub fn with_times(seconds: f32, penalty: f32) -> Mining {
+        Mining {
+            seconds: seconds,
+            penalty: penalty,
+        }
+    }
+
+    // Whether `held` is one of the tools listed by `block`.
+    pub fn suits(&self, block: &BlockType, held: Option<&ItemType>) -> bool {
+        match held.and_then(|item| item.tool()) {
+            Some(tool) => block.tools().contains(&tool),
+            None => false,
+        }
+    }
+
+    // Whether breaking `block` with `held` yields its drop.
+    pub fn drops(&self, block: &BlockType, held: Option<&ItemType>) -> bool {
+        block.tier() == 0 ||
+            (self.suits(block, held) && held.map_or(0, |item| item.tier()) >= block.tier())
+    }
+
+    // Seconds it takes to break `block` with `held`.
+    pub fn time(&self, block: &BlockType, held: Option<&ItemType>) -> f32 {
+        let speed = match held {
+            Some(item) if self.suits(block, held) => item.speed(),
+            _ => 1.0,
+        };
+        let time = block.hardness() * self.seconds / speed;
+        if self.drops(block, held) { time } else { time * self.penalty }
+    }
+}
+
+// Progress on breaking one block, which is lost on moving to another.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Dig {
+    pos: BlockPos,
+    elapsed: f32,
+}
+
+impl Dig {
+    pub fn new(pos: BlockPos) -> Dig {
+        Dig {
+            pos: pos,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn pos(&self) -> BlockPos {
+        self.pos
+    }
+
+    // Seconds spent on the block so far.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    // Works on the block for `dt` seconds. Returns true once `time` seconds
+    // have been spent in all.
+    pub fn advance(&mut self, dt: f32, time: f32) -> bool {
+        self.elapsed += dt;
+        self.elapsed >= time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use block::BlockType;
+    use inventory::{
+        ItemType,
+        Tool,
+    };
+    use world::BlockPos;
+    use super::{
+        Dig,
+        Mining,
+    };
+
+    #[test]
+    fn break_times() {
+        let mining = Mining::with_times(1.5, 5.0);
+        let bedrock = BlockType::new("bedrock").with_hardness(0.0);
+        let dirt = BlockType::new("dirt").with_hardness(0.5).with_tools(vec![Tool::Shovel], 0);
+        let stone = BlockType::new("stone").with_tools(vec![Tool::Pick], 1);
+        let ore = BlockType::new("iron_ore").with_hardness(2.0).with_tools(vec![Tool::Pick], 2);
+
+        let wheat = ItemType::new("wheat");
+        let shovel = ItemType::new("bronze_shovel").with_tool(Tool::Shovel, 1, 4.0);
+        let bronze = ItemType::new("bronze_pick").with_tool(Tool::Pick, 1, 4.0);
+        let iron = ItemType::new("iron_pick").with_tool(Tool::Pick, 2, 6.0);
+
+        // Block, what is held, seconds to break and whether it drops.
+        let table = [
+            (&bedrock, None, 0.0, true),
+            (&dirt, None, 0.75, true),
+            (&dirt, Some(&wheat), 0.75, true),
+            (&dirt, Some(&shovel), 0.1875, true),
+            (&dirt, Some(&bronze), 0.75, true),
+            (&stone, None, 7.5, false),
+            (&stone, Some(&shovel), 7.5, false),
+            (&stone, Some(&bronze), 0.375, true),
+            (&stone, Some(&iron), 0.25, true),
+            (&ore, None, 15.0, false),
+            (&ore, Some(&bronze), 3.75, false),
+            (&ore, Some(&iron), 0.5, true),
+        ];
+        for &(block, held, time, drops) in table.iter() {
+            let name = held.map_or("hand", |h| h.name());
+            assert!((mining.time(block, held) - time).abs() < 1e-5,
+                    "{} with {} took {}", block.name(), name, mining.time(block, held));
+            assert_eq!(mining.drops(block, held), drops, "{} with {}", block.name(), name);
+        }
+    }
+
+    #[test]
+    fn digging_takes_time() {
+        let mut dig = Dig::new(BlockPos::new(1, 2, 3));
+        assert!(!dig.advance(0.25, 0.5));
+        assert!(dig.advance(0.25, 0.5));
+        assert_eq!(dig.elapsed(), 0.5);
+        assert!(Dig::new(BlockPos::new(0, 0, 0)).advance(0.0, 0.0));
+    }
+}
@@ -0,0 +1,355 @@
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use gen::terrain::Biome;
+use world::MAX_LIGHT;
+use errors::Result;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Behaviour {
+    // Walks to random places nearby.
+    Wander,
+    // Wanders, and runs from the player on seeing them.
+    Flee,
+    // Wanders, and walks at the player on seeing them.
+    Chase,
+    // Chases, and shoots arrows at the player from within range.
+    Ranged,
+}
+
+impl Behaviour {
+    pub fn from_name(name: &str) -> Option<Behaviour> {
+        match name {
+            "wander" => Some(Behaviour::Wander),
+            "flee" => Some(Behaviour::Flee),
+            "chase" => Some(Behaviour::Chase),
+            "ranged" => Some(Behaviour::Ranged),
+            _ => None,
+        }
+    }
+}
+
+// How a ranged mob shoots.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ranged {
+    // Furthest the mob shoots from.
+    pub range: f32,
+    // Seconds between shots.
+    pub cooldown: f32,
+    pub arrow_speed: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MobType {
+    name: String,
+    behaviour: Behaviour,
+    ranged: Option<Ranged>,
+    speed: f32,
+    width: f32,
+    height: i32,
+    health: u32,
+    damage: u32,
+    sight: f32,
+    biomes: Vec<Biome>,
+    light: (u8, u8),
+    chance: f32,
+    cap: usize,
+}
+
+impl MobType {
+    // A mob that spawns nowhere. It is 0.6 wide and a block tall, sees 16
+    // blocks and does 2 damage, which are the defaults for loaded mobs too.
+    pub fn new(name: &str, behaviour: Behaviour, speed: f32, health: u32) -> MobType {
+        MobType {
+            name: String::from(name),
+            behaviour: behaviour,
+            ranged: None,
+            speed: speed,
+            width: 0.6,
+            height: 1,
+            health: health,
+            damage: 2,
+            sight: 16.0,
+            biomes: Vec::new(),
+            light: (0, MAX_LIGHT),
+            chance: 1.0,
+            cap: 4,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn behaviour(&self) -> Behaviour {
+        self.behaviour
+    }
+
+    // Set for `Ranged` mobs only.
+    pub fn ranged(&self) -> Option<Ranged> {
+        self.ranged
+    }
+
+    // Blocks per second.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    // Whole blocks the mob needs to stand in.
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn health(&self) -> u32 {
+        self.health
+    }
+
+    // Health taken off the player by each swing or arrow.
+    pub fn damage(&self) -> u32 {
+        self.damage
+    }
+
+    // How far away the mob notices the player.
+    pub fn sight(&self) -> f32 {
+        self.sight
+    }
+
+    // Biomes the mob spawns in.
+    pub fn biomes(&self) -> &[Biome] {
+        &self.biomes
+    }
+
+    // Lowest and highest light levels the mob spawns in.
+    pub fn light(&self) -> (u8, u8) {
+        self.light
+    }
+
+    // Likelihood of a suitable spawn attempt choosing this mob.
+    pub fn chance(&self) -> f32 {
+        self.chance
+    }
+
+    // Most of the mob there may be at once.
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn can_spawn(&self, biome: Biome, light: u8) -> bool {
+        self.biomes.contains(&biome) && light >= self.light.0 && light <= self.light.1
+    }
+}
+
+// For building mobs in tests.
+#[cfg(test)]
+impl MobType {
+    pub fn with_ranged(self, ranged: Ranged) -> MobType {
+        MobType {
+            ranged: Some(ranged),
+            ..self
+        }
+    }
+
+    pub fn with_height(self, height: i32) -> MobType {
+        MobType {
+            height: height,
+            ..self
+        }
+    }
+
+    pub fn with_spawning(self, biomes: Vec<Biome>, light: (u8, u8)) -> MobType {
+        MobType {
+            biomes: biomes,
+            light: light,
+            ..self
+        }
+    }
+}
+
+// Mob types in the order they are declared.
+pub struct MobTypes {
+    types: Vec<MobType>,
+}
+
+impl MobTypes {
+    // Mob types declared in code rather than loaded, in kind order.
+    pub fn with_types(types: Vec<MobType>) -> MobTypes {
+        MobTypes {
+            types: types,
+        }
+    }
+
+    pub fn load<P>(path: P) -> Result<MobTypes>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let entries = match table.remove("mobs") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => {
+                let desc = "`mobs` must be an array of tables";
+                return Err(config::error_at(path, &src, 0, desc));
+            },
+            None => Vec::new(),
+        };
+
+        let mut types: Vec<MobType> = Vec::new();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "mobs", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_mob = match TomlMobType::decode(&mut decoder) {
+                Ok(mob) => mob,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let mob = match toml_mob.into_mob_type() {
+                Ok(mob) => mob,
+                Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+            };
+            if types.iter().any(|t| t.name == mob.name) {
+                let desc = format!("duplicate mob `{}`", mob.name);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            types.push(mob);
+        }
+
+        Ok(MobTypes::with_types(types))
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn get(&self, kind: usize) -> &MobType {
+        &self.types[kind]
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.types.iter().position(|t| t.name == name)
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<MobType> {
+        self.types.iter()
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlMobType {
+    name: String,
+    behaviour: String,
+    speed: f32,
+    width: Option<f32>,
+    height: Option<i32>,
+    health: u32,
+    damage: Option<u32>,
+    sight: Option<f32>,
+    biomes: Vec<String>,
+    light: Option<Vec<u8>>,
+    chance: Option<f32>,
+    cap: Option<usize>,
+    range: Option<f32>,
+    cooldown: Option<f32>,
+    arrow_speed: Option<f32>,
+}
+
+impl TomlMobType {
+    fn into_mob_type(self) -> ::std::result::Result<MobType, String> {
+        let name = self.name;
+        let behaviour = match Behaviour::from_name(&self.behaviour) {
+            Some(b) => b,
+            None => return Err(format!("mob `{}` has unknown behaviour `{}`, expected \
+                                        `wander`, `flee`, `chase` or `ranged`",
+                                       name, self.behaviour)),
+        };
+
+        let ranged = match (behaviour, self.range, self.cooldown, self.arrow_speed) {
+            (Behaviour::Ranged, Some(range), Some(cooldown), Some(arrow_speed)) => {
+                if range <= 0.0 || cooldown <= 0.0 || arrow_speed <= 0.0 {
+                    return Err(format!("mob `{}` needs a positive `range`, `cooldown` and \
+                                        `arrow_speed`", name));
+                }
+                Some(Ranged {
+                    range: range,
+                    cooldown: cooldown,
+                    arrow_speed: arrow_speed,
+                })
+            },
+            (Behaviour::Ranged, _, _, _) => {
+                return Err(format!("ranged mob `{}` needs `range`, `cooldown` and \
+                                    `arrow_speed`", name));
+            },
+            (_, None, None, None) => None,
+            _ => {
+                return Err(format!("only ranged mobs take `range`, `cooldown` and \
+                                    `arrow_speed`, but `{}` is not ranged", name));
+            },
+        };
+
+        let mut biomes = Vec::with_capacity(self.biomes.len());
+        for biome in self.biomes.iter() {
+            match Biome::from_name(biome) {
+                Some(b) => biomes.push(b),
+                None => return Err(format!("mob `{}` has unknown biome `{}`", name, biome)),
+            }
+        }
+
+        let defaults = MobType::new(&name, behaviour, self.speed, self.health);
+        let light = match self.light {
+            Some(ref light) if light.len() == 2 && light[0] <= light[1] &&
+                light[1] <= MAX_LIGHT => (light[0], light[1]),
+            Some(_) => {
+                return Err(format!("mob `{}` needs `light` to be a lowest and highest level \
+                                    from 0 to {}", name, MAX_LIGHT));
+            },
+            None => defaults.light,
+        };
+
+        let height = self.height.unwrap_or(defaults.height);
+        let width = self.width.unwrap_or(defaults.width);
+        if self.speed <= 0.0 || height < 1 || width <= 0.0 || width > 1.0 {
+            return Err(format!("mob `{}` needs a positive `speed` and `height`, and a `width` \
+                                of at most 1", name));
+        }
+        if self.health == 0 {
+            return Err(format!("mob `{}` has a health of 0", name));
+        }
+        let sight = self.sight.unwrap_or(defaults.sight);
+        if sight <= 0.0 {
+            return Err(format!("mob `{}` has a `sight` that is not positive", name));
+        }
+        let chance = self.chance.unwrap_or(defaults.chance);
+        if chance < 0.0 {
+            return Err(format!("mob `{}` has a negative `chance`", name));
+        }
+
+        Ok(MobType {
+            ranged: ranged,
+            width: width,
+            height: height,
+            damage: self.damage.unwrap_or(defaults.damage),
+            sight: sight,
+            biomes: biomes,
+            light: light,
+            chance: chance,
+            cap: self.cap.unwrap_or(defaults.cap),
+            ..defaults
+        })
+    }
+}
@@ -0,0 +1,816 @@
+pub mod kind;
+pub mod path;
+pub mod render;
+
+use rand::{
+    Rng,
+    XorShiftRng,
+};
+use gel::Vec3;
+
+use config::MobsParams;
+use delta::FixedStep;
+use gen::noise;
+use gen::terrain::Terrain;
+use world::{
+    Blocks,
+    BlockId,
+    BlockPos,
+    CHUNK_HEIGHT,
+    MAX_LIGHT,
+};
+use world::ray;
+use world::mesh::BlockTypes;
+use world::shape::{
+    self,
+    Aabb,
+};
+
+pub use self::kind::{
+    MobType,
+    MobTypes,
+    Behaviour,
+    Ranged,
+};
+
+// Downward acceleration of falling mobs and arrows, in blocks per second squared.
+const GRAVITY: f32 = 20.0;
+// Furthest a wandering mob heads for, along each axis.
+const WANDER: i32 = 8;
+// Seconds a mob idles between wanders, at least and at most.
+const IDLE: (f32, f32) = (2.0, 6.0);
+// Seconds between a mob that sees the player finding a new path.
+const REPATH: f32 = 0.5;
+// Seconds before an arrow is removed.
+const ARROW_LIFE: f32 = 10.0;
+// Arrows passing this close to the player's centre hit them.
+const ARROW_HIT: f32 = 0.8;
+// How close to the player's centre chasing mobs swing at them, and how often.
+const REACH: f32 = 1.5;
+const SWING: f32 = 1.0;
+
+fn length(v: Vec3) -> f32 {
+    (v.x * v.x + v.y * v.y + v.z * v.z).sqrt()
+}
+
+fn cell_of(p: Vec3) -> BlockPos {
+    BlockPos::new(p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32)
+}
+
+// The middle of the floor of `cell`.
+fn floor_of(cell: BlockPos) -> Vec3 {
+    Vec3::new(cell.x as f32 + 0.5, cell.y as f32, cell.z as f32 + 0.5)
+}
+
+#[derive(Debug, Clone)]
+pub struct Mob {
+    kind: usize,
+    // The middle of the mob's feet.
+    position: Vec3,
+    // How the mob moved in the last step.
+    velocity: Vec3,
+    // Radians about the vertical, 0 facing +z.
+    yaw: f32,
+    health: u32,
+    // Seconds since the mob spawned.
+    age: f32,
+    // Blocks walked in all.
+    distance: f32,
+    // Seconds since the mob last attacked, if it has.
+    attacked: Option<f32>,
+    // Cells left to walk through, the next one last.
+    path: Vec<BlockPos>,
+    // Seconds until the mob next decides where to go.
+    wait: f32,
+    // Seconds until a ranged mob can shoot again.
+    cooldown: f32,
+}
+
+impl Mob {
+    pub fn new(kind: usize, mob_type: &MobType, position: Vec3) -> Mob {
+        Mob {
+            kind: kind,
+            position: position,
+            velocity: Vec3::new(0.0, 0.0, 0.0),
+            yaw: 0.0,
+            health: mob_type.health(),
+            age: 0.0,
+            distance: 0.0,
+            attacked: None,
+            path: Vec::new(),
+            wait: 0.0,
+            cooldown: 0.0,
+        }
+    }
+
+    pub fn kind(&self) -> usize {
+        self.kind
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn health(&self) -> u32 {
+        self.health
+    }
+
+    pub fn age(&self) -> f32 {
+        self.age
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn attacked(&self) -> Option<f32> {
+        self.attacked
+    }
+
+    // The cell the mob's feet are in.
+    pub fn cell(&self) -> BlockPos {
+        cell_of(self.position)
+    }
+
+    fn eye(&self, mob_type: &MobType) -> Vec3 {
+        self.position + Vec3::new(0.0, mob_type.height() as f32 - 0.5, 0.0)
+    }
+
+    fn face(&mut self, d: Vec3) {
+        if d.x != 0.0 || d.z != 0.0 {
+            self.yaw = d.x.atan2(d.z);
+        }
+    }
+
+    // Sets out for `goal`, or as close to it as can be reached.
+    fn head_for<B, F>(&mut self,
+                      blocks: &B,
+                      solid: &F,
+                      height: i32,
+                      goal: BlockPos,
+                      search: usize)
+        where B: Blocks,
+              F: Fn(BlockId) -> bool,
+    {
+        self.path = path::find(blocks, solid, self.cell(), goal, height, search)
+            .unwrap_or(Vec::new());
+        self.path.reverse();
+    }
+
+    // The box the mob collides with blocks in.
+    pub fn bounds(&self, mob_type: &MobType) -> Aabb {
+        let p = self.position;
+        Aabb::standing([p.x, p.y, p.z], mob_type.width(), mob_type.height() as f32)
+    }
+
+    // Walks along the path, and falls if there is nothing underfoot. A mob
+    // that walks into a block's collision, like the top of a fence, stops
+    // and gives up on its path.
+    fn walk<B, T, F>(&mut self, blocks: &B, shapes: &T, solid: &F, mob_type: &MobType, dt: f32)
+        where B: Blocks,
+              T: BlockTypes,
+              F: Fn(BlockId) -> bool,
+    {
+        let start = self.position;
+        let cell = self.cell();
+        // A mob that has hopped up a step is held up by it until it is over.
+        let standing = self.position.y == cell.y as f32 &&
+            (solid(blocks.block(cell.offset(0, -1, 0))) ||
+             self.path.last().map_or(false, |next| next.y == cell.y));
+
+        if !standing {
+            // Fall at the speed reached after the previous step, landing on
+            // the first solid block passed.
+            let mut y = self.position.y + (self.velocity.y - GRAVITY * dt) * dt;
+            let mut c = cell.y;
+            while c as f32 > y && c > 0 {
+                if solid(blocks.block(BlockPos::new(cell.x, c - 1, cell.z))) {
+                    y = c as f32;
+                    break;
+                }
+                c -= 1;
+            }
+            self.position.y = if y < 0.0 { 0.0 } else { y };
+        } else if let Some(&next) = self.path.last() {
+            // Give up on paths the world has changed under.
+            if !path::can_stand(blocks, solid, next, mob_type.height()) {
+                self.path.clear();
+            } else {
+                let target = floor_of(next);
+                let d = Vec3::new(target.x - self.position.x, 0.0, target.z - self.position.z);
+                let distance = length(d);
+                let step = mob_type.speed() * dt;
+                self.face(d);
+                if distance <= step {
+                    self.position.x = target.x;
+                    self.position.z = target.z;
+                    self.path.pop();
+                } else {
+                    self.position.x += d.x / distance * step;
+                    self.position.z += d.z / distance * step;
+                }
+                // Mobs hop straight up a step, and fall down a drop once
+                // they are over it.
+                if next.y > cell.y {
+                    self.position.y = next.y as f32;
+                }
+                if shape::collides(blocks, shapes, &self.bounds(mob_type), solid) {
+                    self.position = start;
+                    self.path.clear();
+                }
+            }
+        }
+
+        let moved = self.position - start;
+        self.velocity = Vec3::new(moved.x / dt, moved.y / dt, moved.z / dt);
+        self.distance += (moved.x * moved.x + moved.z * moved.z).sqrt();
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Arrow {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    stuck: bool,
+    // Health taken off the player if it hits them.
+    damage: u32,
+}
+
+impl Arrow {
+    pub fn new(position: Vec3, velocity: Vec3, damage: u32) -> Arrow {
+        Arrow {
+            position: position,
+            velocity: velocity,
+            age: 0.0,
+            stuck: false,
+            damage: damage,
+        }
+    }
+
+    // The tip of the arrow.
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn damage(&self) -> u32 {
+        self.damage
+    }
+
+    // Whether the arrow has stopped in a block.
+    pub fn is_stuck(&self) -> bool {
+        self.stuck
+    }
+
+    // Moves the arrow on. Stuck arrows keep their velocity, for the way they
+    // point. Returns true if it passes through `target`.
+    fn fly<B, F>(&mut self, blocks: &B, solid: &F, target: Vec3, dt: f32) -> bool
+        where B: Blocks,
+              F: Fn(BlockId) -> bool,
+    {
+        self.age += dt;
+        if self.stuck {
+            return false;
+        }
+        self.velocity.y -= GRAVITY * dt;
+        let d = Vec3::new(self.velocity.x * dt, self.velocity.y * dt, self.velocity.z * dt);
+        let distance = length(d);
+        if let Some(hit) = ray::cast(blocks, self.position, d, distance, |id| solid(id)) {
+            let s = hit.distance / distance;
+            self.position = self.position + Vec3::new(d.x * s, d.y * s, d.z * s);
+            self.stuck = true;
+            return false;
+        }
+        self.position = self.position + d;
+        length(target - self.position) < ARROW_HIT
+    }
+}
+
+// What mobs need to know about the world around them.
+pub struct Surroundings<'a, B: 'a, T: 'a, F: 'a> {
+    pub blocks: &'a B,
+    // What the blocks collide with.
+    pub shapes: &'a T,
+    // Which blocks can be stood on and block sight.
+    pub solid: &'a F,
+    pub terrain: &'a Terrain,
+    // The middle of the player.
+    pub player: Vec3,
+    // From 0 at night to 1 by day, which dims the sky light mobs spawn in.
+    pub daylight: f32,
+}
+
+// What every mob thinking in one step shares.
+struct Step {
+    // The cell the player can be reached in.
+    goal: BlockPos,
+    search: usize,
+    dt: f32,
+}
+
+// Every mob and arrow in the world, simulated in fixed steps so behaviour
+// does not depend on the frame rate. Nothing here draws, so the simulation
+// can run on its own.
+pub struct Mobs {
+    step: FixedStep,
+    spawning: FixedStep,
+    attempts: u32,
+    radius: i32,
+    near: f32,
+    despawn: f32,
+    search: usize,
+    rng: XorShiftRng,
+    mobs: Vec<Mob>,
+    arrows: Vec<Arrow>,
+}
+
+impl Mobs {
+    pub fn new(params: &MobsParams, seed: u32) -> Mobs {
+        Mobs {
+            step: FixedStep::new(1.0 / params.rate()),
+            spawning: FixedStep::new(params.interval()),
+            attempts: params.attempts(),
+            radius: params.radius(),
+            near: params.near(),
+            despawn: params.despawn(),
+            search: params.search(),
+            rng: noise::rng(seed, 0x6d6f6273),
+            mobs: Vec::new(),
+            arrows: Vec::new(),
+        }
+    }
+
+    pub fn mobs(&self) -> &[Mob] {
+        &self.mobs
+    }
+
+    pub fn arrows(&self) -> &[Arrow] {
+        &self.arrows
+    }
+
+    // Runs the steps due after `dt` more seconds. Returns the health the
+    // mobs took off the player.
+    pub fn update<B, T, F>(&mut self,
+                           types: &MobTypes,
+                           world: &Surroundings<B, T, F>,
+                           dt: f32) -> u32
+        where B: Blocks,
+              T: BlockTypes,
+              F: Fn(BlockId) -> bool,
+    {
+        let steps = self.step.advance(dt, 8);
+        let interval = self.step.interval();
+        let mut damage = 0;
+        for _ in 0..steps {
+            damage += self.tick(types, world, interval);
+        }
+        damage
+    }
+
+    pub fn tick<B, T, F>(&mut self, types: &MobTypes, world: &Surroundings<B, T, F>, dt: f32) -> u32
+        where B: Blocks,
+              T: BlockTypes,
+              F: Fn(BlockId) -> bool,
+    {
+        let player = world.player;
+        let despawn = self.despawn;
+        self.mobs.retain(|m| length(m.position - player) <= despawn);
+
+        for _ in 0..self.spawning.advance(dt, 1) {
+            for _ in 0..self.attempts {
+                self.spawn(types, world);
+            }
+        }
+
+        let step = Step {
+            goal: standing_cell(world.blocks, world.solid, player),
+            search: self.search,
+            dt: dt,
+        };
+        let mut damage = 0;
+        for mob in self.mobs.iter_mut() {
+            let mob_type = types.get(mob.kind);
+            damage += think(mob, mob_type, world, &step, &mut self.rng, &mut self.arrows);
+            mob.walk(world.blocks, world.shapes, world.solid, mob_type, dt);
+        }
+
+        // Arrows that reach the player are spent.
+        for arrow in self.arrows.iter_mut() {
+            if arrow.fly(world.blocks, world.solid, player, dt) {
+                arrow.age = ARROW_LIFE;
+                damage += arrow.damage;
+            }
+        }
+        self.arrows.retain(|a| a.age < ARROW_LIFE);
+        damage
+    }
+
+    // Hits the nearest mob that the ray from `origin` along `direction`
+    // passes through within `reach`, taking `damage` off its health. Mobs
+    // left with none die. Returns whether any mob was hit.
+    pub fn attack(&mut self,
+                  types: &MobTypes,
+                  origin: Vec3,
+                  direction: Vec3,
+                  reach: f32,
+                  damage: u32) -> bool
+    {
+        let len = length(direction);
+        if len == 0.0 {
+            return false;
+        }
+        let o = [origin.x, origin.y, origin.z];
+        let d = [direction.x / len, direction.y / len, direction.z / len];
+        let mut nearest: Option<(usize, f32)> = None;
+        for (i, mob) in self.mobs.iter().enumerate() {
+            if let Some(t) = mob.bounds(types.get(mob.kind)).ray(o, d) {
+                if t <= reach && nearest.map_or(true, |(_, n)| t < n) {
+                    nearest = Some((i, t));
+                }
+            }
+        }
+        let i = match nearest {
+            Some((i, _)) => i,
+            None => return false,
+        };
+        self.mobs[i].health = self.mobs[i].health.saturating_sub(damage);
+        if self.mobs[i].health == 0 {
+            self.mobs.remove(i);
+        }
+        true
+    }
+
+    // Tries to spawn a mob in a random column around the player, on a cell
+    // whose biome and light suit it. Cells open to the sky are as light as
+    // the time of day makes them, and covered ones are dark.
+    fn spawn<B, T, F>(&mut self, types: &MobTypes, world: &Surroundings<B, T, F>)
+        where B: Blocks,
+              T: BlockTypes,
+              F: Fn(BlockId) -> bool,
+    {
+        let (blocks, solid, player) = (world.blocks, world.solid, world.player);
+        let x = player.x.floor() as i32 + self.rng.gen_range(-self.radius, self.radius + 1);
+        let z = player.z.floor() as i32 + self.rng.gen_range(-self.radius, self.radius + 1);
+        let (dx, dz) = (x as f32 + 0.5 - player.x, z as f32 + 0.5 - player.z);
+        if (dx * dx + dz * dz).sqrt() < self.near {
+            return;
+        }
+
+        // Any cell in the column a mob could stand in, with its sky light.
+        let mut cells = Vec::new();
+        let mut light = (MAX_LIGHT as f32 * world.daylight).round() as u8;
+        for y in (1..CHUNK_HEIGHT as i32).rev() {
+            let pos = BlockPos::new(x, y, z);
+            if solid(blocks.block(pos)) {
+                light = 0;
+            } else if path::can_stand(blocks, solid, pos, 1) {
+                cells.push((pos, light));
+            }
+        }
+        if cells.is_empty() {
+            return;
+        }
+        let (pos, light) = cells[self.rng.gen_range(0, cells.len())];
+
+        let biome = world.terrain.biome(x, z);
+        let candidates: Vec<usize> = (0..types.len())
+            .filter(|&kind| {
+                let mob_type = types.get(kind);
+                mob_type.can_spawn(biome, light) &&
+                    path::is_clear(blocks, solid, pos, mob_type.height()) &&
+                    self.mobs.iter().filter(|m| m.kind == kind).count() < mob_type.cap()
+            })
+            .collect();
+        let total = candidates.iter().fold(0.0, |sum, &kind| sum + types.get(kind).chance());
+        if total <= 0.0 {
+            return;
+        }
+        let mut pick = self.rng.gen::<f32>() * total;
+        for &kind in candidates.iter() {
+            let mob_type = types.get(kind);
+            pick -= mob_type.chance();
+            if pick < 0.0 {
+                self.mobs.push(Mob::new(kind, mob_type, floor_of(pos)));
+                return;
+            }
+        }
+    }
+}
+
+// The cell below `p` that something could stand in, for pathing towards.
+fn standing_cell<B, F>(blocks: &B, solid: &F, p: Vec3) -> BlockPos
+    where B: Blocks,
+          F: Fn(BlockId) -> bool,
+{
+    let cell = cell_of(p);
+    for dy in 0..path::MAX_DROP + 1 {
+        let below = cell.offset(0, -dy, 0);
+        if path::can_stand(blocks, solid, below, 1) {
+            return below;
+        }
+    }
+    cell
+}
+
+// Decides where `mob` goes next, and attacks if it can. Returns the health
+// its swing takes off the player; arrows it shoots hit later.
+fn think<B, T, F, R>(mob: &mut Mob,
+                     mob_type: &MobType,
+                     world: &Surroundings<B, T, F>,
+                     step: &Step,
+                     rng: &mut R,
+                     arrows: &mut Vec<Arrow>) -> u32
+    where B: Blocks,
+          F: Fn(BlockId) -> bool,
+          R: Rng,
+{
+    let (blocks, solid, player) = (world.blocks, world.solid, world.player);
+    let (goal, search, dt) = (step.goal, step.search, step.dt);
+    mob.wait -= dt;
+    mob.cooldown -= dt;
+    mob.age += dt;
+    mob.attacked = mob.attacked.map(|t| t + dt);
+
+    let eye = mob.eye(mob_type);
+    let to_player = player - eye;
+    let distance = length(to_player);
+    let sees = distance <= mob_type.sight() &&
+        ray::cast(blocks, eye, to_player, distance, |id| solid(id)).is_none();
+    let height = mob_type.height();
+
+    let mut damage = 0;
+    match (mob_type.behaviour(), mob_type.ranged()) {
+        (Behaviour::Ranged, Some(ranged)) if sees && distance <= ranged.range => {
+            mob.path.clear();
+            mob.face(to_player);
+            if mob.cooldown <= 0.0 {
+                mob.cooldown = ranged.cooldown;
+                mob.attacked = Some(0.0);
+                arrows.push(aim(eye, player, ranged.arrow_speed, mob_type.damage()));
+            }
+        },
+        (Behaviour::Chase, _) | (Behaviour::Ranged, _) if sees => {
+            if distance <= REACH && mob.cooldown <= 0.0 {
+                mob.cooldown = SWING;
+                mob.attacked = Some(0.0);
+                damage = mob_type.damage();
+            }
+            if mob.wait <= 0.0 {
+                mob.wait = REPATH;
+                mob.head_for(blocks, solid, height, goal, search);
+            }
+        },
+        (Behaviour::Flee, _) if sees => {
+            if mob.wait <= 0.0 {
+                mob.wait = REPATH;
+                let away = Vec3::new(-to_player.x, 0.0, -to_player.z);
+                let len = length(away);
+                let away = if len > 0.0 {
+                    Vec3::new(away.x / len, 0.0, away.z / len)
+                } else {
+                    Vec3::new(1.0, 0.0, 0.0)
+                };
+                let cell = mob.cell();
+                let target = cell.offset((away.x * WANDER as f32) as i32,
+                                         0,
+                                         (away.z * WANDER as f32) as i32);
+                mob.head_for(blocks, solid, height, target, search);
+            }
+        },
+        _ => {
+            if mob.wait <= 0.0 && mob.path.is_empty() {
+                mob.wait = rng.gen_range(IDLE.0, IDLE.1);
+                let target = mob.cell().offset(rng.gen_range(-WANDER, WANDER + 1),
+                                               0,
+                                               rng.gen_range(-WANDER, WANDER + 1));
+                mob.head_for(blocks, solid, height, target, search);
+            }
+        },
+    }
+    damage
+}
+
+// An arrow from `from` that drops onto `target` when fired at `speed`.
+fn aim(from: Vec3, target: Vec3, speed: f32, damage: u32) -> Arrow {
+    let d = target - from;
+    let distance = length(d);
+    // Aim above the target by as far as the arrow will drop on the way.
+    let time = distance / speed;
+    let lift = 0.5 * GRAVITY * time * time;
+    let aimed = Vec3::new(d.x, d.y + lift, d.z);
+    let s = speed / length(aimed);
+    Arrow::new(from, Vec3::new(aimed.x * s, aimed.y * s, aimed.z * s), damage)
+}
+
+#[cfg(test)]
+mod tests {
+    use gel::Vec3;
+
+    use config::{
+        MobsParams,
+        TerrainParams,
+    };
+    use gen::terrain::{
+        Biome,
+        Terrain,
+        TerrainBlocks,
+    };
+    use world::{
+        BlockId,
+        BlockPos,
+        BlocksMut,
+        Grid,
+        AIR,
+        MAX_LIGHT,
+    };
+    use world::mesh::Uniform;
+    use super::{
+        length,
+        Behaviour,
+        Mob,
+        Mobs,
+        MobType,
+        MobTypes,
+        Ranged,
+        Surroundings,
+    };
+
+    const STONE: BlockId = 1;
+
+    fn solid(id: BlockId) -> bool {
+        id != AIR
+    }
+
+    // A stone floor at y 0 with nothing above it.
+    fn floor(size: i32) -> Grid {
+        let mut grid = Grid::new(size, 8, size);
+        for z in 0..size {
+            for x in 0..size {
+                grid.set_block(BlockPos::new(x, 0, z), STONE);
+            }
+        }
+        grid
+    }
+
+    fn terrain() -> Terrain {
+        let blocks = TerrainBlocks {
+            stone: 1,
+            dirt: 2,
+            grass: 3,
+            snowy_dirt: 4,
+            snow: 5,
+            sand: 6,
+            redsand: 7,
+            redstone: 8,
+            water: 9,
+        };
+        Terrain::new(&TerrainParams::new(7), blocks)
+    }
+
+    // Runs `mobs` for `seconds` with the player standing at `player`,
+    // returning the damage done to them.
+    fn run(mobs: &mut Mobs, types: &MobTypes, grid: &Grid, player: Vec3, seconds: f32) -> u32 {
+        let terrain = terrain();
+        let world = Surroundings {
+            blocks: grid,
+            shapes: &Uniform(0),
+            solid: &solid,
+            terrain: &terrain,
+            player: player,
+            daylight: 1.0,
+        };
+        let mut damage = 0;
+        for _ in 0..(seconds * 100.0) as u32 {
+            damage += mobs.tick(types, &world, 0.01);
+        }
+        damage
+    }
+
+    fn place(mobs: &mut Mobs, types: &MobTypes, kind: usize, position: Vec3) {
+        mobs.mobs.push(Mob::new(kind, types.get(kind), position));
+    }
+
+    #[test]
+    fn chasers_reach_and_hit_the_player() {
+        let types = MobTypes::with_types(vec![MobType::new("zombie", Behaviour::Chase, 2.0, 10)]);
+        let mut mobs = Mobs::new(&MobsParams::new(), 1);
+        place(&mut mobs, &types, 0, Vec3::new(2.5, 1.0, 2.5));
+        let player = Vec3::new(10.5, 1.5, 2.5);
+        let damage = run(&mut mobs, &types, &floor(16), player, 6.0);
+        assert!(length(mobs.mobs()[0].position() - Vec3::new(10.5, 1.0, 2.5)) < 1.0);
+        assert!(damage >= 2);
+        assert_eq!(damage % 2, 0);
+    }
+
+    #[test]
+    fn fleeing_mobs_run_away() {
+        let types = MobTypes::with_types(vec![MobType::new("fox", Behaviour::Flee, 2.0, 10)]);
+        let mut mobs = Mobs::new(&MobsParams::new(), 1);
+        place(&mut mobs, &types, 0, Vec3::new(10.5, 1.0, 10.5));
+        let player = Vec3::new(7.5, 1.5, 10.5);
+        let damage = run(&mut mobs, &types, &floor(24), player, 3.0);
+        assert!(mobs.mobs()[0].position().x > 13.0);
+        assert_eq!(damage, 0);
+    }
+
+    #[test]
+    fn arrows_hit_the_player() {
+        let ranged = Ranged {
+            range: 12.0,
+            cooldown: 10.0,
+            arrow_speed: 16.0,
+        };
+        let skeleton = MobType::new("skeleton", Behaviour::Ranged, 2.0, 10)
+            .with_height(2)
+            .with_ranged(ranged);
+        let types = MobTypes::with_types(vec![skeleton]);
+        let mut mobs = Mobs::new(&MobsParams::new(), 1);
+        place(&mut mobs, &types, 0, Vec3::new(2.5, 1.0, 2.5));
+        let player = Vec3::new(10.5, 1.5, 2.5);
+
+        // Shooting from range, rather than walking up.
+        assert_eq!(run(&mut mobs, &types, &floor(16), player, 0.05), 0);
+        assert_eq!(mobs.arrows().len(), 1);
+        assert_eq!(run(&mut mobs, &types, &floor(16), player, 1.0), 2);
+        assert!(mobs.arrows().is_empty());
+        assert_eq!(mobs.mobs()[0].position(), Vec3::new(2.5, 1.0, 2.5));
+    }
+
+    #[test]
+    fn walls_block_sight() {
+        let types = MobTypes::with_types(vec![MobType::new("zombie", Behaviour::Chase, 2.0, 10)]);
+        let mut grid = floor(16);
+        for y in 1..4 {
+            for z in 0..16 {
+                grid.set_block(BlockPos::new(6, y, z), STONE);
+            }
+        }
+        let mut mobs = Mobs::new(&MobsParams::new(), 1);
+        place(&mut mobs, &types, 0, Vec3::new(2.5, 1.0, 2.5));
+        let damage = run(&mut mobs, &types, &grid, Vec3::new(10.5, 1.5, 2.5), 4.0);
+        assert_eq!(damage, 0);
+        assert!(mobs.mobs()[0].position().x < 6.0);
+    }
+
+    #[test]
+    fn attacks_kill_mobs() {
+        let types = MobTypes::with_types(vec![MobType::new("boar", Behaviour::Wander, 1.0, 4)]);
+        let mut mobs = Mobs::new(&MobsParams::new(), 1);
+        place(&mut mobs, &types, 0, Vec3::new(5.5, 1.0, 5.5));
+        place(&mut mobs, &types, 0, Vec3::new(5.5, 1.0, 8.5));
+        let eye = Vec3::new(5.5, 1.5, 0.0);
+
+        // Missing, or out of reach.
+        assert!(!mobs.attack(&types, eye, Vec3::new(0.0, 0.0, -1.0), 10.0, 3));
+        assert!(!mobs.attack(&types, eye, Vec3::new(0.0, 0.0, 1.0), 4.0, 3));
+
+        // The nearer mob is hit first.
+        assert!(mobs.attack(&types, eye, Vec3::new(0.0, 0.0, 2.0), 10.0, 3));
+        assert_eq!(mobs.mobs()[0].health(), 1);
+        assert_eq!(mobs.mobs()[1].health(), 4);
+        assert!(mobs.attack(&types, eye, Vec3::new(0.0, 0.0, 2.0), 10.0, 3));
+        assert_eq!(mobs.mobs().len(), 1);
+        assert_eq!(mobs.mobs()[0].position().z, 8.5);
+    }
+
+    #[test]
+    fn night_mobs_spawn_in_the_dark() {
+        let biomes = vec![Biome::Grassland, Biome::Snow, Biome::Desert, Biome::Mesa];
+        let zombie = MobType::new("zombie", Behaviour::Chase, 1.0, 10)
+            .with_spawning(biomes, (0, 7));
+        let types = MobTypes::with_types(vec![zombie]);
+        let grid = floor(80);
+        let terrain = terrain();
+        let spawned = |daylight: f32| {
+            let world = Surroundings {
+                blocks: &grid,
+                shapes: &Uniform(0),
+                solid: &solid,
+                terrain: &terrain,
+                player: Vec3::new(40.5, 1.5, 40.5),
+                daylight: daylight,
+            };
+            let mut mobs = Mobs::new(&MobsParams::new(), 1);
+            for _ in 0..200 {
+                mobs.spawn(&types, &world);
+            }
+            mobs.mobs().len()
+        };
+        assert_eq!(spawned(1.0), 0);
+        assert_eq!(spawned(0.5), 0);
+        assert!(spawned(0.0) > 0);
+        assert!(spawned(7.0 / MAX_LIGHT as f32) > 0);
+    }
+}
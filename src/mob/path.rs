@@ -0,0 +1,159 @@
+use std::cmp::Ordering;
+use std::collections::binary_heap::{
+    BinaryHeap,
+};
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use world::{
+    Blocks,
+    BlockId,
+    BlockPos,
+    Face,
+    CHUNK_HEIGHT,
+};
+
+const HORIZONTAL: [Face; 4] = [Face::Left, Face::Right, Face::Back, Face::Front];
+
+// Furthest a mob will walk off a ledge.
+pub const MAX_DROP: i32 = 3;
+
+// Whether a mob `height` blocks tall fits in the cells from `pos` up.
+pub fn is_clear<B, F>(blocks: &B, solid: &F, pos: BlockPos, height: i32) -> bool
+    where B: Blocks,
+          F: Fn(BlockId) -> bool,
+{
+    pos.y > 0 && pos.y + height <= CHUNK_HEIGHT as i32 &&
+        (0..height).all(|dy| !solid(blocks.block(pos.offset(0, dy, 0))))
+}
+
+// Whether a mob `height` blocks tall can stand with its feet in `pos`.
+pub fn can_stand<B, F>(blocks: &B, solid: &F, pos: BlockPos, height: i32) -> bool
+    where B: Blocks,
+          F: Fn(BlockId) -> bool,
+{
+    solid(blocks.block(pos.neighbour(Face::Bottom))) && is_clear(blocks, solid, pos, height)
+}
+
+// Where a mob standing at `pos` can get to in one move: a step to the side,
+// up onto a block, or down off a ledge of at most `MAX_DROP`.
+pub fn moves<B, F>(blocks: &B, solid: &F, pos: BlockPos, height: i32) -> Vec<BlockPos>
+    where B: Blocks,
+          F: Fn(BlockId) -> bool,
+{
+    let mut moves = Vec::with_capacity(4);
+    let headroom = is_clear(blocks, solid, pos, height + 1);
+    for &face in HORIZONTAL.iter() {
+        let side = pos.neighbour(face);
+        if can_stand(blocks, solid, side, height) {
+            moves.push(side);
+        } else if headroom && can_stand(blocks, solid, side.offset(0, 1, 0), height) {
+            moves.push(side.offset(0, 1, 0));
+        } else if is_clear(blocks, solid, side, height) {
+            for drop in 1..MAX_DROP + 1 {
+                let below = side.offset(0, -drop, 0);
+                if !is_clear(blocks, solid, below, 1) {
+                    break;
+                }
+                if can_stand(blocks, solid, below, height) {
+                    moves.push(below);
+                    break;
+                }
+            }
+        }
+    }
+    moves
+}
+
+fn distance(a: BlockPos, b: BlockPos) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs()
+}
+
+// An open node, ordered so the heap pops the lowest estimate first and breaks
+// ties by position, which keeps the search deterministic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Node {
+    estimate: i32,
+    pos: BlockPos,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        match other.estimate.cmp(&self.estimate) {
+            Ordering::Equal => {
+                (other.pos.x, other.pos.y, other.pos.z).cmp(&(self.pos.x, self.pos.y, self.pos.z))
+            },
+            order => order,
+        }
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// A* from `start` towards `goal`, looking at no more than `limit` cells. Moves
+// cost one plus any change in height. The path runs from the cell after
+// `start` to `goal` or, if `goal` cannot be reached, to the cell nearest it.
+// `None` if there is nowhere better to go than `start`.
+pub fn find<B, F>(blocks: &B,
+                  solid: &F,
+                  start: BlockPos,
+                  goal: BlockPos,
+                  height: i32,
+                  limit: usize) -> Option<Vec<BlockPos>>
+    where B: Blocks,
+          F: Fn(BlockId) -> bool,
+{
+    let mut open = BinaryHeap::new();
+    let mut cost: HashMap<BlockPos, i32> = HashMap::new();
+    let mut from: HashMap<BlockPos, BlockPos> = HashMap::new();
+    let mut best = start;
+
+    open.push(Node { estimate: distance(start, goal), pos: start });
+    cost.insert(start, 0);
+
+    let mut expanded = 0;
+    while let Some(node) = open.pop() {
+        let pos = node.pos;
+        if pos == goal {
+            best = goal;
+            break;
+        }
+        if distance(pos, goal) < distance(best, goal) {
+            best = pos;
+        }
+        expanded += 1;
+        if expanded > limit {
+            break;
+        }
+
+        let g = cost[&pos];
+        for next in moves(blocks, solid, pos, height) {
+            let g_next = g + 1 + (next.y - pos.y).abs();
+            if cost.get(&next).map_or(true, |&c| g_next < c) {
+                cost.insert(next, g_next);
+                from.insert(next, pos);
+                open.push(Node { estimate: g_next + distance(next, goal), pos: next });
+            }
+        }
+    }
+
+    if best == start {
+        return None;
+    }
+    let mut path = vec![best];
+    let mut pos = best;
+    while let Some(&prev) = from.get(&pos) {
+        if prev == start {
+            break;
+        }
+        path.push(prev);
+        pos = prev;
+    }
+    path.reverse();
+    Some(path)
+}
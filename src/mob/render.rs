@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+    BlendingFunction,
+    LinearBlendingFactor,
+};
+use glium::texture::SrgbTexture2d;
+use glium::uniforms::{
+    Uniforms,
+    UniformValue,
+    AsUniformValue,
+    Sampler,
+    MinifySamplerFilter,
+    MagnifySamplerFilter,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use image;
+
+use errors::Result;
+use super::Arrow;
+
+#[derive(Copy, Clone)]
+struct Corner {
+    corner: [f32; 2],
+}
+implement_vertex!(Corner, corner);
+
+// A square facing the camera, as two triangles.
+const QUAD: [Corner; 6] = [
+    Corner { corner: [-1.0, -1.0] },
+    Corner { corner: [ 1.0, -1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [-1.0,  1.0] },
+    Corner { corner: [-1.0, -1.0] },
+];
+
+// Half the length of an arrow, in blocks.
+const SIZE: f32 = 0.3;
+
+// Arrows, drawn with the billboard program as sprites always facing the
+// camera.
+pub struct ArrowRenderer {
+    vtxbuf: VertexBuffer<Corner>,
+    texture: SrgbTexture2d,
+}
+
+impl ArrowRenderer {
+    pub fn new<F, P>(facade: &F, texture: P) -> Result<ArrowRenderer>
+        where F: Facade,
+              P: AsRef<Path>,
+    {
+        let image = try!(image::open(texture));
+        Ok(ArrowRenderer {
+            vtxbuf: try!(VertexBuffer::new(facade, &QUAD)),
+            texture: try!(SrgbTexture2d::new(facade, image)),
+        })
+    }
+
+    // `uniforms` supply the view and projection.
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U,
+                      arrows: &[Arrow]) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLess,
+            depth_write: false,
+            blending_function: Some(BlendingFunction::Addition {
+                source: LinearBlendingFactor::SourceAlpha,
+                destination: LinearBlendingFactor::OneMinusSourceAlpha,
+            }),
+            ..Default::default()
+        };
+        for arrow in arrows.iter() {
+            // The position is the tip, so draw the sprite behind it.
+            let (p, v) = (arrow.position(), arrow.velocity());
+            let speed = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+            let back = if speed > 0.0 { SIZE / speed } else { 0.0 };
+            let arrow_uniforms = ArrowUniforms {
+                uniforms: uniforms,
+                centre: [p.x - v.x * back, p.y - v.y * back, p.z - v.z * back],
+                size: SIZE,
+                tint: [1.0, 1.0, 1.0, 1.0],
+                sprite: self.texture.sampled()
+                    .minify_filter(MinifySamplerFilter::Nearest)
+                    .magnify_filter(MagnifySamplerFilter::Nearest),
+            };
+            try!(surface.draw(&self.vtxbuf,
+                              NoIndices(PrimitiveType::TrianglesList),
+                              program,
+                              &arrow_uniforms,
+                              draw_params));
+        }
+        Ok(())
+    }
+}
+
+struct ArrowUniforms<'a, U: 'a> {
+    uniforms: &'a U,
+    centre: [f32; 3],
+    size: f32,
+    tint: [f32; 4],
+    sprite: Sampler<'a, SrgbTexture2d>,
+}
+
+impl<'a, U> Uniforms for ArrowUniforms<'a, U>
+    where U: Uniforms
+{
+    fn visit_values<'b, F>(&'b self, mut output: F)
+        where F: FnMut(&str, UniformValue<'b>)
+    {
+        self.uniforms.visit_values(&mut output);
+        output("centre", self.centre.as_uniform_value());
+        output("size", self.size.as_uniform_value());
+        output("tint", self.tint.as_uniform_value());
+        output("sprite", self.sprite.as_uniform_value());
+    }
+}
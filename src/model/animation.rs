@@ -0,0 +1,545 @@
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use errors::{
+    Result,
+    Error,
+};
+use super::Model;
+use super::transform::Quat;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    // Interpolates the rotations' components, which eases in and out a little.
+    Linear,
+    // Turns at a constant speed between keys.
+    Slerp,
+}
+
+impl Interpolation {
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        match name {
+            "linear" => Some(Interpolation::Linear),
+            "slerp" => Some(Interpolation::Slerp),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Key {
+    pub time: f32,
+    pub rotation: Quat,
+}
+
+// How one part turns over a clip.
+#[derive(Debug, Clone)]
+pub struct Curve {
+    part: String,
+    interpolation: Interpolation,
+    // In order of time, with at least one.
+    keys: Vec<Key>,
+}
+
+impl Curve {
+    // Needs at least one key, in order of time from 0.
+    pub fn new(part: &str, interpolation: Interpolation, keys: Vec<Key>)
+               -> ::std::result::Result<Curve, String>
+    {
+        if keys.is_empty() {
+            return Err(format!("curve for `{}` has no keys", part));
+        }
+        if keys[0].time < 0.0 || keys.windows(2).any(|pair| pair[0].time >= pair[1].time) {
+            return Err(format!("curve for `{}` needs its keys in order of time, from 0", part));
+        }
+        Ok(Curve {
+            part: part.to_string(),
+            interpolation: interpolation,
+            keys: keys,
+        })
+    }
+
+    pub fn part(&self) -> &str {
+        &self.part
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    // The rotation at `time`, holding the first and last keys outside them.
+    pub fn sample(&self, time: f32) -> Quat {
+        let keys = &self.keys;
+        if time <= keys[0].time {
+            return keys[0].rotation;
+        }
+        for pair in keys.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if time < b.time {
+                let t = (time - a.time) / (b.time - a.time);
+                return match self.interpolation {
+                    Interpolation::Linear => a.rotation.nlerp(&b.rotation, t),
+                    Interpolation::Slerp => a.rotation.slerp(&b.rotation, t),
+                };
+            }
+        }
+        keys[keys.len() - 1].rotation
+    }
+}
+
+// Curves for some of the parts of a model, over `length` seconds. Clips go by
+// part name, so one clip can drive every model with parts of those names.
+#[derive(Debug, Clone)]
+pub struct Clip {
+    name: String,
+    length: f32,
+    looping: bool,
+    stride: Option<f32>,
+    curves: Vec<Curve>,
+}
+
+impl Clip {
+    // Needs a positive `length` and `stride`, at most one curve for each part,
+    // and no keys past the end.
+    pub fn new(name: &str,
+               length: f32,
+               looping: bool,
+               stride: Option<f32>,
+               curves: Vec<Curve>) -> ::std::result::Result<Clip, String>
+    {
+        if length <= 0.0 {
+            return Err(format!("clip `{}` has a length that is not positive", name));
+        }
+        if stride.map_or(false, |s| s <= 0.0) {
+            return Err(format!("clip `{}` has a stride that is not positive", name));
+        }
+        for (i, curve) in curves.iter().enumerate() {
+            if curves[..i].iter().any(|c| c.part == curve.part) {
+                return Err(format!("clip `{}` has two curves for part `{}`", name, curve.part));
+            }
+            if curve.keys.iter().any(|k| k.time > length) {
+                return Err(format!("clip `{}` has keys for `{}` past its length",
+                                   name, curve.part));
+            }
+        }
+        Ok(Clip {
+            name: name.to_string(),
+            length: length,
+            looping: looping,
+            stride: stride,
+            curves: curves,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    // Whether the clip starts over once it ends, rather than holding its
+    // last pose.
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    // Blocks walked over one play of the clip, for walks.
+    pub fn stride(&self) -> Option<f32> {
+        self.stride
+    }
+
+    pub fn curves(&self) -> &[Curve] {
+        &self.curves
+    }
+
+    // Time into the clip `time` seconds after it started.
+    pub fn local_time(&self, time: f32) -> f32 {
+        if self.looping {
+            let t = time % self.length;
+            if t < 0.0 { t + self.length } else { t }
+        } else if time > self.length {
+            self.length
+        } else {
+            time
+        }
+    }
+
+    // The rotation of each part of `model` at `time`, or `None` for parts
+    // the clip does not move.
+    pub fn sample(&self, model: &Model, time: f32) -> Vec<Option<Quat>> {
+        let t = self.local_time(time);
+        model.parts().iter()
+            .map(|p| self.curves.iter().find(|c| c.part == p.name()).map(|c| c.sample(t)))
+            .collect()
+    }
+}
+
+pub struct Clips {
+    clips: Vec<Clip>,
+}
+
+impl Clips {
+    pub fn with_clips(clips: Vec<Clip>) -> Clips {
+        Clips {
+            clips: clips,
+        }
+    }
+
+    pub fn load<P>(path: P) -> Result<Clips>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let entries = match table.remove("clips") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => {
+                let desc = "`clips` must be an array of tables";
+                return Err(config::error_at(path, &src, 0, desc));
+            },
+            None => Vec::new(),
+        };
+
+        let mut clips: Vec<Clip> = Vec::new();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "clips", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_clip = match TomlClip::decode(&mut decoder) {
+                Ok(clip) => clip,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let clip = match toml_clip.into_clip() {
+                Ok(clip) => clip,
+                Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+            };
+            if clips.iter().any(|c| c.name == clip.name) {
+                let desc = format!("duplicate clip `{}`", clip.name);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            clips.push(clip);
+        }
+
+        Ok(Clips::with_clips(clips))
+    }
+
+    pub fn get(&self, id: usize) -> &Clip {
+        &self.clips[id]
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|c| c.name == name)
+    }
+
+    // Like `id`, for clips the game cannot run without.
+    pub fn require(&self, name: &str) -> Result<usize> {
+        self.id(name).ok_or(Error::with_detail("animation error",
+                                               format!("no clip named `{}`", name)))
+    }
+}
+
+// How a character is moving, which picks the clips it plays and how much of
+// each.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Motion {
+    // Seconds the character has been around.
+    pub time: f32,
+    // Blocks walked in all.
+    pub distance: f32,
+    // Blocks per second.
+    pub speed: f32,
+    // Seconds since the character last attacked, if it has.
+    pub attack: Option<f32>,
+}
+
+// The clips a model plays.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClipSet {
+    pub idle: Option<usize>,
+    pub walk: Option<usize>,
+    pub attack: Option<usize>,
+}
+
+impl ClipSet {
+    // The clips `model` names. Walks must have a stride.
+    pub fn for_model(model: &Model, clips: &Clips) -> Result<ClipSet> {
+        let find = |name: Option<&str>| match name {
+            Some(name) => clips.require(name).map(Some),
+            None => Ok(None),
+        };
+        let set = ClipSet {
+            idle: try!(find(model.idle())),
+            walk: try!(find(model.walk())),
+            attack: try!(find(model.attack())),
+        };
+        if let Some(walk) = set.walk {
+            if clips.get(walk).stride().is_none() {
+                return Err(Error::with_detail("animation error",
+                                              format!("model `{}` walks with clip `{}`, which \
+                                                       has no stride",
+                                                      model.name(), clips.get(walk).name())));
+            }
+        }
+        Ok(set)
+    }
+
+    // The turn of each part of `model` for `motion`, for `Model::boxes`. The
+    // idle clip blends into the walk as the character speeds up to the pace
+    // the walk is made for, and the walk keeps step with the distance walked.
+    // An attack takes over the parts it moves while it plays.
+    pub fn pose(&self, clips: &Clips, model: &Model, motion: &Motion) -> Vec<Quat> {
+        let mut pose = vec![Quat::identity(); model.parts().len()];
+        if let Some(idle) = self.idle {
+            for (q, turn) in pose.iter_mut().zip(clips.get(idle).sample(model, motion.time)) {
+                if let Some(turn) = turn {
+                    *q = turn;
+                }
+            }
+        }
+
+        if let Some(walk) = self.walk {
+            let walk = clips.get(walk);
+            let stride = walk.stride().unwrap_or(1.0);
+            let pace = stride / walk.length();
+            let weight = (motion.speed / pace).max(0.0).min(1.0);
+            if weight > 0.0 {
+                let time = motion.distance / stride * walk.length();
+                for (q, turn) in pose.iter_mut().zip(walk.sample(model, time)) {
+                    if let Some(turn) = turn {
+                        *q = q.slerp(&turn, weight);
+                    }
+                }
+            }
+        }
+
+        if let (Some(attack), Some(since)) = (self.attack, motion.attack) {
+            let attack = clips.get(attack);
+            if since < attack.length() {
+                for (q, turn) in pose.iter_mut().zip(attack.sample(model, since)) {
+                    if let Some(turn) = turn {
+                        *q = turn;
+                    }
+                }
+            }
+        }
+        pose
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlClip {
+    name: String,
+    length: f32,
+    looping: Option<bool>,
+    stride: Option<f32>,
+    curves: Vec<TomlCurve>,
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlCurve {
+    part: String,
+    interpolation: Option<String>,
+    // [time, x, y, z], with the rotation in degrees as for model parts.
+    keys: Vec<Vec<f32>>,
+}
+
+impl TomlClip {
+    fn into_clip(self) -> ::std::result::Result<Clip, String> {
+        let name = self.name;
+        let mut curves: Vec<Curve> = Vec::with_capacity(self.curves.len());
+        for curve in self.curves.into_iter() {
+            let interpolation = match curve.interpolation {
+                Some(ref i) => match Interpolation::from_name(i) {
+                    Some(i) => i,
+                    None => return Err(format!("clip `{}` has unknown interpolation `{}`, \
+                                                expected `linear` or `slerp`", name, i)),
+                },
+                None => Interpolation::Slerp,
+            };
+
+            let mut keys: Vec<Key> = Vec::with_capacity(curve.keys.len());
+            for key in curve.keys.iter() {
+                if key.len() != 4 {
+                    return Err(format!("clip `{}` has a key for `{}` that is not \
+                                        [time, x, y, z]", name, curve.part));
+                }
+                keys.push(Key {
+                    time: key[0],
+                    rotation: Quat::from_degrees([key[1], key[2], key[3]]),
+                });
+            }
+            match Curve::new(&curve.part, interpolation, keys) {
+                Ok(c) => curves.push(c),
+                Err(desc) => return Err(format!("in clip `{}`, {}", name, desc)),
+            }
+        }
+
+        Clip::new(&name, self.length, self.looping.unwrap_or(true), self.stride, curves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        Model,
+        Part,
+    };
+    use super::super::transform::Quat;
+    use super::{
+        Clip,
+        Clips,
+        ClipSet,
+        Curve,
+        Interpolation,
+        Key,
+        Motion,
+    };
+
+    fn part(name: &str) -> Part {
+        Part {
+            name: name.to_string(),
+            parent: None,
+            texture: 0,
+            pivot: [0.0; 3],
+            from: [0.0; 3],
+            size: [1.0; 3],
+            rotation: Quat::identity(),
+        }
+    }
+
+    fn model() -> Model {
+        Model {
+            name: "test".to_string(),
+            dir: "test".to_string(),
+            scale: 1.0 / 16.0,
+            textures: vec!["skin".to_string()],
+            parts: vec![part("arm"), part("leg")],
+            idle: Some("idle".to_string()),
+            walk: Some("walk".to_string()),
+            attack: Some("attack".to_string()),
+        }
+    }
+
+    fn turn(degrees: f32) -> Quat {
+        Quat::from_degrees([0.0, degrees, 0.0])
+    }
+
+    // Degrees turned about y.
+    fn angle(q: Quat) -> f32 {
+        2.0 * q.y.atan2(q.w).to_degrees()
+    }
+
+    fn close(a: Quat, b: Quat) -> bool {
+        a.dot(&b).abs() > 1.0 - 1e-6
+    }
+
+    fn swing(interpolation: Interpolation) -> Curve {
+        Curve::new("arm", interpolation, vec![
+            Key { time: 0.0, rotation: turn(0.0) },
+            Key { time: 1.0, rotation: turn(90.0) },
+        ]).unwrap()
+    }
+
+    fn hold(part: &str, degrees: f32) -> Curve {
+        let key = Key { time: 0.0, rotation: turn(degrees) };
+        Curve::new(part, Interpolation::Slerp, vec![key]).unwrap()
+    }
+
+    #[test]
+    fn curves_hold_their_ends() {
+        let curve = swing(Interpolation::Slerp);
+        for &(time, degrees) in [(-1.0, 0.0), (0.0, 0.0), (1.0, 90.0), (2.0, 90.0)].iter() {
+            assert!(close(curve.sample(time), turn(degrees)), "at {}", time);
+        }
+    }
+
+    #[test]
+    fn curves_interpolate() {
+        let slerp = swing(Interpolation::Slerp);
+        let linear = swing(Interpolation::Linear);
+        assert!(close(slerp.sample(0.5), turn(45.0)));
+        assert!(close(linear.sample(0.5), turn(45.0)));
+        assert!(close(slerp.sample(0.25), turn(22.5)));
+        // Linear interpolation eases in, so it has turned less a quarter of the way.
+        let eased = angle(linear.sample(0.25));
+        assert!(eased > 20.0 && eased < 22.0, "turned {}", eased);
+    }
+
+    #[test]
+    fn clips_loop_or_hold() {
+        let curves = vec![swing(Interpolation::Slerp)];
+        let looping = Clip::new("swing", 2.0, true, None, curves.clone()).unwrap();
+        assert!((looping.local_time(5.0) - 1.0).abs() < 1e-5);
+        assert!((looping.local_time(-0.5) - 1.5).abs() < 1e-5);
+        let held = Clip::new("swing", 2.0, false, None, curves).unwrap();
+        assert_eq!(held.local_time(5.0), 2.0);
+        assert_eq!(held.local_time(0.5), 0.5);
+
+        let pose = held.sample(&model(), 5.0);
+        assert!(close(pose[0].unwrap(), turn(90.0)));
+        assert!(pose[1].is_none());
+    }
+
+    #[test]
+    fn bad_clips_are_rejected() {
+        let key = |time| Key { time: time, rotation: Quat::identity() };
+        assert!(Curve::new("arm", Interpolation::Slerp, Vec::new()).is_err());
+        assert!(Curve::new("arm", Interpolation::Slerp, vec![key(-1.0)]).is_err());
+        assert!(Curve::new("arm", Interpolation::Slerp, vec![key(1.0), key(0.5)]).is_err());
+
+        assert!(Clip::new("swing", 0.0, true, None, Vec::new()).is_err());
+        assert!(Clip::new("swing", 1.0, true, Some(0.0), Vec::new()).is_err());
+        assert!(Clip::new("swing", 0.5, true, None, vec![swing(Interpolation::Slerp)]).is_err());
+        let twice = vec![hold("arm", 0.0), hold("arm", 90.0)];
+        assert!(Clip::new("swing", 1.0, true, None, twice).is_err());
+    }
+
+    #[test]
+    fn walks_blend_with_speed() {
+        let model = model();
+        let clips = Clips::with_clips(vec![
+            Clip::new("idle", 1.0, true, None, vec![hold("arm", 0.0), hold("leg", 0.0)]).unwrap(),
+            // Two blocks a second.
+            Clip::new("walk", 1.0, true, Some(2.0), vec![hold("arm", 90.0)]).unwrap(),
+            Clip::new("attack", 0.5, false, None, vec![hold("arm", -90.0)]).unwrap(),
+        ]);
+        let set = ClipSet::for_model(&model, &clips).unwrap();
+        let motion = |speed, attack| Motion {
+            time: 0.0,
+            distance: 0.0,
+            speed: speed,
+            attack: attack,
+        };
+
+        let cases = [
+            (0.0, None, 0.0),
+            (1.0, None, 45.0),
+            (2.0, None, 90.0),
+            (4.0, None, 90.0),
+            (1.0, Some(0.2), -90.0),
+            (1.0, Some(0.6), 45.0),
+        ];
+        for &(speed, attack, degrees) in cases.iter() {
+            let pose = set.pose(&clips, &model, &motion(speed, attack));
+            assert!(close(pose[0], turn(degrees)),
+                    "at speed {} and attack {:?}, turned {}", speed, attack, angle(pose[0]));
+            assert!(close(pose[1], Quat::identity()));
+        }
+    }
+}
@@ -0,0 +1,333 @@
+pub mod transform;
+pub mod render;
+pub mod animation;
+
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+
+use config;
+use errors::{
+    Result,
+    Error,
+};
+use self::transform::{
+    Matrix,
+    Quat,
+    multiply,
+    translation,
+    scaling,
+};
+
+pub use self::render::ModelRenderer;
+
+// One box of a model. It turns about `pivot`, which is relative to its
+// parent's pivot, and its box spans `from` to `from + size` around the pivot.
+#[derive(Debug, Clone)]
+pub struct Part {
+    name: String,
+    parent: Option<usize>,
+    texture: usize,
+    pivot: [f32; 3],
+    from: [f32; 3],
+    size: [f32; 3],
+    rotation: Quat,
+}
+
+impl Part {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Always before this part in the model.
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    // Index into the model's textures.
+    pub fn texture(&self) -> usize {
+        self.texture
+    }
+
+    pub fn pivot(&self) -> [f32; 3] {
+        self.pivot
+    }
+
+    pub fn from(&self) -> [f32; 3] {
+        self.from
+    }
+
+    pub fn size(&self) -> [f32; 3] {
+        self.size
+    }
+
+    // How the part is turned at rest.
+    pub fn rotation(&self) -> Quat {
+        self.rotation
+    }
+}
+
+// A character built from boxes, facing +z with its feet at the origin. Sizes
+// are in units of `scale` blocks.
+#[derive(Debug, Clone)]
+pub struct Model {
+    name: String,
+    dir: String,
+    scale: f32,
+    textures: Vec<String>,
+    parts: Vec<Part>,
+    idle: Option<String>,
+    walk: Option<String>,
+    attack: Option<String>,
+}
+
+impl Model {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Directory in the characters directory the textures are in.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    // Images in `dir`, without the extension.
+    pub fn textures(&self) -> &[String] {
+        &self.textures
+    }
+
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    // Names of the clips played standing, walking and attacking.
+    pub fn idle(&self) -> Option<&str> {
+        self.idle.as_ref().map(|s| &s[..])
+    }
+
+    pub fn walk(&self) -> Option<&str> {
+        self.walk.as_ref().map(|s| &s[..])
+    }
+
+    pub fn attack(&self) -> Option<&str> {
+        self.attack.as_ref().map(|s| &s[..])
+    }
+
+    pub fn part(&self, name: &str) -> Option<usize> {
+        self.parts.iter().position(|p| p.name == name)
+    }
+
+    // Where each part's box is drawn, as a transform of the [-1, 1] cube.
+    // `placement` puts the model in the world, and `turns` holds a rotation
+    // for each part on top of its rest rotation, or is empty for none.
+    pub fn boxes(&self, placement: &Matrix, turns: &[Quat]) -> Vec<Matrix> {
+        let placement = multiply(placement, &scaling(self.scale, self.scale, self.scale));
+        let mut frames: Vec<Matrix> = Vec::with_capacity(self.parts.len());
+        let mut boxes = Vec::with_capacity(self.parts.len());
+        for (i, part) in self.parts.iter().enumerate() {
+            let parent = match part.parent {
+                Some(p) => frames[p],
+                None => placement,
+            };
+            let p = part.pivot;
+            let rotation = match turns.get(i) {
+                Some(&turn) => part.rotation * turn,
+                None => part.rotation,
+            };
+            let frame = multiply(&multiply(&parent, &translation(p[0], p[1], p[2])),
+                                 &rotation.to_matrix());
+
+            let (f, s) = (part.from, part.size);
+            let centre = translation(f[0] + s[0] / 2.0, f[1] + s[1] / 2.0, f[2] + s[2] / 2.0);
+            let half = scaling(s[0] / 2.0, s[1] / 2.0, s[2] / 2.0);
+            boxes.push(multiply(&multiply(&frame, &centre), &half));
+            frames.push(frame);
+        }
+        boxes
+    }
+}
+
+pub struct Models {
+    models: Vec<Model>,
+}
+
+impl Models {
+    // Textures are checked against the images in `characters`.
+    pub fn load<P, Q>(path: P, characters: Q) -> Result<Models>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let entries = match table.remove("models") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => {
+                let desc = "`models` must be an array of tables";
+                return Err(config::error_at(path, &src, 0, desc));
+            },
+            None => Vec::new(),
+        };
+
+        let mut models: Vec<Model> = Vec::new();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "models", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_model = match TomlModel::decode(&mut decoder) {
+                Ok(model) => model,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let model = match toml_model.into_model(characters.as_ref()) {
+                Ok(model) => model,
+                Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+            };
+            if models.iter().any(|m| m.name == model.name) {
+                let desc = format!("duplicate model `{}`", model.name);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            models.push(model);
+        }
+
+        Ok(Models {
+            models: models,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn get(&self, id: usize) -> &Model {
+        &self.models[id]
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.models.iter().position(|m| m.name == name)
+    }
+
+    // Like `id`, for models the game cannot run without.
+    pub fn require(&self, name: &str) -> Result<usize> {
+        self.id(name).ok_or(Error::with_detail("model error",
+                                               format!("no model named `{}`", name)))
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlModel {
+    name: String,
+    dir: Option<String>,
+    scale: Option<f32>,
+    idle: Option<String>,
+    walk: Option<String>,
+    attack: Option<String>,
+    parts: Vec<TomlPart>,
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlPart {
+    name: String,
+    parent: Option<String>,
+    texture: String,
+    pivot: Option<Vec<f32>>,
+    from: Vec<f32>,
+    size: Vec<f32>,
+    rotation: Option<Vec<f32>>,
+}
+
+fn triple(v: &[f32], part: &str, field: &str) -> ::std::result::Result<[f32; 3], String> {
+    if v.len() == 3 {
+        Ok([v[0], v[1], v[2]])
+    } else {
+        Err(format!("part `{}` needs `{}` to be [x, y, z]", part, field))
+    }
+}
+
+impl TomlModel {
+    fn into_model(self, characters: &Path) -> ::std::result::Result<Model, String> {
+        let name = self.name;
+        let dir = self.dir.unwrap_or(name.clone());
+        let scale = self.scale.unwrap_or(1.0 / 16.0);
+        if scale <= 0.0 {
+            return Err(format!("model `{}` has a scale that is not positive", name));
+        }
+        if self.parts.is_empty() {
+            return Err(format!("model `{}` has no parts", name));
+        }
+
+        let mut textures: Vec<String> = Vec::new();
+        let mut parts: Vec<Part> = Vec::with_capacity(self.parts.len());
+        for part in self.parts.into_iter() {
+            if parts.iter().any(|p| p.name == part.name) {
+                return Err(format!("model `{}` has two parts named `{}`", name, part.name));
+            }
+            let parent = match part.parent {
+                Some(ref parent) => match parts.iter().position(|p| &p.name == parent) {
+                    Some(i) => Some(i),
+                    None => return Err(format!("part `{}` of model `{}` has parent `{}`, which \
+                                                is not an earlier part",
+                                               part.name, name, parent)),
+                },
+                None => None,
+            };
+            let pivot = try!(triple(&part.pivot.clone().unwrap_or(vec![0.0; 3]),
+                                    &part.name,
+                                    "pivot"));
+            let from = try!(triple(&part.from, &part.name, "from"));
+            let size = try!(triple(&part.size, &part.name, "size"));
+            let rotation = try!(triple(&part.rotation.clone().unwrap_or(vec![0.0; 3]),
+                                       &part.name,
+                                       "rotation"));
+            if size.iter().any(|&s| s <= 0.0) {
+                return Err(format!("part `{}` of model `{}` has a size that is not positive",
+                                   part.name, name));
+            }
+
+            let texture = match textures.iter().position(|t| t == &part.texture) {
+                Some(i) => i,
+                None => {
+                    let file = characters.join(&dir).join(format!("{}.png", part.texture));
+                    if !file.exists() {
+                        return Err(format!("model `{}` uses missing texture `{}`",
+                                           name, file.display()));
+                    }
+                    textures.push(part.texture.clone());
+                    textures.len() - 1
+                },
+            };
+
+            parts.push(Part {
+                name: part.name,
+                parent: parent,
+                texture: texture,
+                pivot: pivot,
+                from: from,
+                size: size,
+                rotation: Quat::from_degrees(rotation),
+            });
+        }
+
+        Ok(Model {
+            name: name,
+            dir: dir,
+            scale: scale,
+            textures: textures,
+            parts: parts,
+            idle: self.idle,
+            walk: self.walk,
+            attack: self.attack,
+        })
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::Path;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+};
+use glium::texture::SrgbTexture2d;
+use glium::uniforms::{
+    Uniforms,
+    UniformValue,
+    AsUniformValue,
+    Sampler,
+    MinifySamplerFilter,
+    MagnifySamplerFilter,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use image;
+
+use cube::{
+    self,
+    Vertex,
+};
+use errors::Result;
+use super::Models;
+use super::transform::{
+    Matrix,
+    Quat,
+};
+
+// Draws models with the cube program, one cube per part. Every face of a part
+// shows its whole texture.
+pub struct ModelRenderer {
+    vtxbuf: VertexBuffer<Vertex>,
+    // The textures of each model, in the order the model lists them.
+    textures: Vec<Vec<SrgbTexture2d>>,
+}
+
+impl ModelRenderer {
+    pub fn new<F, P>(facade: &F, models: &Models, characters: P) -> Result<ModelRenderer>
+        where F: Facade,
+              P: AsRef<Path>,
+    {
+        let ref vertices = cube::vertices();
+        let vtxbuf = try!(VertexBuffer::new(facade, vertices));
+
+        let mut textures = Vec::with_capacity(models.len());
+        for id in 0..models.len() {
+            let model = models.get(id);
+            let dir = characters.as_ref().join(model.dir());
+            let mut loaded = Vec::with_capacity(model.textures().len());
+            for name in model.textures() {
+                let img = try!(image::open(dir.join(format!("{}.png", name))));
+                loaded.push(try!(SrgbTexture2d::new(facade, img)));
+            }
+            textures.push(loaded);
+        }
+
+        Ok(ModelRenderer {
+            vtxbuf: vtxbuf,
+            textures: textures,
+        })
+    }
+
+    // Draws model `id` of `models`, placed and posed as for `Model::boxes`.
+    // `uniforms` supply everything but the model matrix and the maps.
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U,
+                      models: &Models,
+                      id: usize,
+                      placement: &Matrix,
+                      turns: &[Quat]) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let model = models.get(id);
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: true,
+            ..Default::default()
+        };
+        let boxes = model.boxes(placement, turns);
+        for (part, matrix) in model.parts().iter().zip(boxes.into_iter()) {
+            let texture = self.textures[id][part.texture()].sampled()
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Nearest);
+            let part_uniforms = PartUniforms {
+                uniforms: uniforms,
+                model: matrix,
+                texture: texture,
+            };
+            try!(surface.draw(&self.vtxbuf,
+                              NoIndices(PrimitiveType::TrianglesList),
+                              program,
+                              &part_uniforms,
+                              draw_params));
+        }
+        Ok(())
+    }
+}
+
+// Adds one part's matrix and texture to the uniforms shared by every part.
+// The texture doubles as the specular map, as for the minecart.
+struct PartUniforms<'a, U: 'a> {
+    uniforms: &'a U,
+    model: Matrix,
+    texture: Sampler<'a, SrgbTexture2d>,
+}
+
+impl<'a, U> Uniforms for PartUniforms<'a, U>
+    where U: Uniforms
+{
+    fn visit_values<'b, F>(&'b self, mut output: F)
+        where F: FnMut(&str, UniformValue<'b>)
+    {
+        self.uniforms.visit_values(&mut output);
+        output("model", self.model.as_uniform_value());
+        output("diffuse_map", self.texture.as_uniform_value());
+        output("specular_map", self.texture.as_uniform_value());
+    }
+}
@@ -0,0 +1,155 @@
+use std::ops::Mul;
+
+use gel::Vec3;
+
+// Column-major, as uniforms take them: `m[column][row]`.
+pub type Matrix = [[f32; 4]; 4];
+
+pub const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+pub fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for c in 0..4 {
+        for r in 0..4 {
+            m[c][r] = (0..4).fold(0.0, |sum, k| sum + a[k][r] * b[c][k]);
+        }
+    }
+    m
+}
+
+pub fn translation(x: f32, y: f32, z: f32) -> Matrix {
+    let mut m = IDENTITY;
+    m[3] = [x, y, z, 1.0];
+    m
+}
+
+pub fn scaling(x: f32, y: f32, z: f32) -> Matrix {
+    let mut m = IDENTITY;
+    m[0][0] = x;
+    m[1][1] = y;
+    m[2][2] = z;
+    m
+}
+
+// Turns `angle` radians about the vertical, taking +z towards +x.
+pub fn rotation_y(angle: f32) -> Matrix {
+    let (s, c) = angle.sin_cos();
+    let mut m = IDENTITY;
+    m[0] = [c, 0.0, -s, 0.0];
+    m[2] = [s, 0.0, c, 0.0];
+    m
+}
+
+// Stands a model at `position`, turned `yaw` radians about the vertical.
+pub fn placement(position: Vec3, yaw: f32) -> Matrix {
+    multiply(&translation(position.x, position.y, position.z), &rotation_y(yaw))
+}
+
+// A rotation, kept unit length.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quat {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quat {
+    pub fn identity() -> Quat {
+        Quat { w: 1.0, x: 0.0, y: 0.0, z: 0.0 }
+    }
+
+    // Turns `angle` radians about the unit `axis`.
+    pub fn from_axis_angle(axis: [f32; 3], angle: f32) -> Quat {
+        let (s, c) = (angle * 0.5).sin_cos();
+        Quat { w: c, x: axis[0] * s, y: axis[1] * s, z: axis[2] * s }
+    }
+
+    // Turns about x, then y, then z, by the given degrees.
+    pub fn from_degrees(angles: [f32; 3]) -> Quat {
+        let x = Quat::from_axis_angle([1.0, 0.0, 0.0], angles[0].to_radians());
+        let y = Quat::from_axis_angle([0.0, 1.0, 0.0], angles[1].to_radians());
+        let z = Quat::from_axis_angle([0.0, 0.0, 1.0], angles[2].to_radians());
+        z * y * x
+    }
+
+    pub fn dot(&self, b: &Quat) -> f32 {
+        self.w * b.w + self.x * b.x + self.y * b.y + self.z * b.z
+    }
+
+    pub fn normalize(&self) -> Quat {
+        let len = self.dot(self).sqrt();
+        Quat { w: self.w / len, x: self.x / len, y: self.y / len, z: self.z / len }
+    }
+
+    // Part way from this rotation to `b`, interpolating the components and
+    // renormalising. Cheap, but the speed of turning varies along the way.
+    pub fn nlerp(&self, b: &Quat, t: f32) -> Quat {
+        let b = self.nearest(b);
+        Quat {
+            w: self.w + (b.w - self.w) * t,
+            x: self.x + (b.x - self.x) * t,
+            y: self.y + (b.y - self.y) * t,
+            z: self.z + (b.z - self.z) * t,
+        }.normalize()
+    }
+
+    // Part way from this rotation to `b`, turning at a constant speed.
+    pub fn slerp(&self, b: &Quat, t: f32) -> Quat {
+        let b = self.nearest(b);
+        let cos = self.dot(&b);
+        // Nearly the same rotation, where the angle is too small to divide by.
+        if cos > 0.9995 {
+            return self.nlerp(&b, t);
+        }
+        let angle = cos.acos();
+        let sin = angle.sin();
+        let (s0, s1) = (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin);
+        Quat {
+            w: self.w * s0 + b.w * s1,
+            x: self.x * s0 + b.x * s1,
+            y: self.y * s0 + b.y * s1,
+            z: self.z * s0 + b.z * s1,
+        }
+    }
+
+    // `b`, or its negation, which is the same rotation, whichever is closer
+    // to this one, so interpolation takes the short way round.
+    fn nearest(&self, b: &Quat) -> Quat {
+        if self.dot(b) < 0.0 {
+            Quat { w: -b.w, x: -b.x, y: -b.y, z: -b.z }
+        } else {
+            *b
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix {
+        let Quat { w, x, y, z } = *self;
+        [
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + w * z), 2.0 * (x * z - w * y), 0.0],
+            [2.0 * (x * y - w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + w * x), 0.0],
+            [2.0 * (x * z + w * y), 2.0 * (y * z - w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    }
+}
+
+// Turning by `a * b` turns by `b`, then by `a`.
+impl Mul for Quat {
+    type Output = Quat;
+
+    fn mul(self, b: Quat) -> Quat {
+        let a = self;
+        Quat {
+            w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+            x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        }
+    }
+}
@@ -0,0 +1,362 @@
+pub mod preset;
+pub mod render;
+
+use std::mem;
+use std::f32::consts::PI;
+
+use rand::{
+    Rng,
+    XorShiftRng,
+};
+use gel::Vec3;
+
+use gen::noise;
+
+pub use self::preset::{
+    Preset,
+    Presets,
+    Emission,
+};
+pub use self::render::ParticleRenderer;
+
+// Particles past this many are not spawned, so a busy scene can't grind the
+// game to a halt.
+const MAX_PARTICLES: usize = 4096;
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    life: f32,
+    gravity: f32,
+    size: (f32, f32),
+    color: ([f32; 4], [f32; 4]),
+    texture: usize,
+}
+
+impl Particle {
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    // Seconds since the particle appeared.
+    pub fn age(&self) -> f32 {
+        self.age
+    }
+
+    // Seconds the particle lasts in all.
+    pub fn life(&self) -> f32 {
+        self.life
+    }
+
+    // Index into the presets' textures.
+    pub fn texture(&self) -> usize {
+        self.texture
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.age < self.life
+    }
+
+    // How far through its life the particle is, from 0 to 1.
+    fn progress(&self) -> f32 {
+        (self.age / self.life).min(1.0)
+    }
+
+    // Half the particle's width, for its age.
+    pub fn size(&self) -> f32 {
+        lerp(self.size.0, self.size.1, self.progress())
+    }
+
+    // The particle's RGBA, for its age.
+    pub fn color(&self) -> [f32; 4] {
+        let t = self.progress();
+        let (a, b) = self.color;
+        [lerp(a[0], b[0], t), lerp(a[1], b[1], t), lerp(a[2], b[2], t), lerp(a[3], b[3], t)]
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.velocity.y -= self.gravity * dt;
+        self.position = self.position + self.velocity * dt;
+        self.age += dt;
+    }
+}
+
+// Gives off particles of one preset from a point.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    preset: usize,
+    position: Vec3,
+    // Seconds since the emitter started.
+    age: f32,
+    // Particles due but not yet given off, as a fraction of one.
+    owed: f32,
+    done: bool,
+}
+
+impl Emitter {
+    pub fn new(preset: usize, position: Vec3) -> Emitter {
+        Emitter {
+            preset: preset,
+            position: position,
+            age: 0.0,
+            owed: 0.0,
+            done: false,
+        }
+    }
+
+    pub fn preset(&self) -> usize {
+        self.preset
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, v: Vec3) {
+        self.position = v;
+    }
+
+    // Whether the emitter has given off all it ever will. Continuous emitters
+    // without a duration never finish.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // Gives off into `particles` whatever is due over the next `dt` seconds.
+    pub fn update(&mut self, presets: &Presets, particles: &mut Particles, dt: f32) {
+        if self.done {
+            return;
+        }
+        let preset = presets.get(self.preset);
+        let count = match preset.emission() {
+            Emission::Burst(count) => {
+                self.done = true;
+                count
+            },
+            Emission::Continuous { rate, duration } => {
+                // Only the part of this step within the duration counts.
+                let active = match duration {
+                    Some(d) if self.age + dt >= d => {
+                        self.done = true;
+                        (d - self.age).max(0.0)
+                    },
+                    _ => dt,
+                };
+                self.owed += rate * active;
+                let count = self.owed.floor();
+                self.owed -= count;
+                count as u32
+            },
+        };
+        self.age += dt;
+        for _ in 0..count {
+            particles.spawn(preset, self.position);
+        }
+    }
+}
+
+// Every live particle, and the emitters nothing else keeps hold of. The
+// simulation is plain arithmetic, with nothing to do with drawing.
+pub struct Particles {
+    particles: Vec<Particle>,
+    emitters: Vec<Emitter>,
+    rng: XorShiftRng,
+}
+
+impl Particles {
+    pub fn new(seed: u32) -> Particles {
+        Particles {
+            particles: Vec::new(),
+            emitters: Vec::new(),
+            rng: noise::rng(seed, 0x70617274),
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn emitters(&self) -> &[Emitter] {
+        &self.emitters
+    }
+
+    // Keeps `emitter` going until it is done, which for a continuous one
+    // without a duration is forever.
+    pub fn add(&mut self, emitter: Emitter) {
+        self.emitters.push(emitter);
+    }
+
+    // Gives off a one-off burst, or a continuous preset's particles until its
+    // duration runs out, at `position`.
+    pub fn emit(&mut self, preset: usize, position: Vec3) {
+        self.add(Emitter::new(preset, position));
+    }
+
+    // One particle of `preset` at `position`, given its own life, speed and
+    // heading from the preset's ranges.
+    pub fn spawn(&mut self, preset: &Preset, position: Vec3) {
+        if self.particles.len() >= MAX_PARTICLES {
+            return;
+        }
+        let rng = &mut self.rng;
+        let (life, speed) = (preset.life(), preset.speed());
+        let life = lerp(life.0, life.1, rng.gen::<f32>());
+        let speed = lerp(speed.0, speed.1, rng.gen::<f32>());
+        let heading = stray(rng, preset.direction(), preset.spread());
+        let j = preset.jitter();
+        let offset = if j > 0.0 {
+            Vec3::new(rng.gen_range(-j, j), rng.gen_range(-j, j), rng.gen_range(-j, j))
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        };
+        self.particles.push(Particle {
+            position: position + offset,
+            velocity: heading * speed,
+            age: 0.0,
+            life: life,
+            gravity: preset.gravity(),
+            size: preset.size(),
+            color: preset.color(),
+            texture: preset.texture(),
+        });
+    }
+
+    // Runs the emitters, moves every particle on by `dt` seconds, and drops
+    // the dead ones and the finished emitters.
+    pub fn update(&mut self, presets: &Presets, dt: f32) {
+        // Taken out while they run, as they give off into `self`.
+        let mut emitters = mem::replace(&mut self.emitters, Vec::new());
+        for emitter in emitters.iter_mut() {
+            emitter.update(presets, self, dt);
+        }
+        emitters.retain(|e| !e.is_done());
+        self.emitters = emitters;
+
+        for particle in self.particles.iter_mut() {
+            particle.update(dt);
+        }
+        self.particles.retain(|p| p.is_alive());
+    }
+}
+
+// A unit heading within `spread` radians of the unit `direction`, spread
+// evenly over that part of the sphere.
+fn stray<R>(rng: &mut R, direction: Vec3, spread: f32) -> Vec3
+    where R: Rng
+{
+    let cos = 1.0 - rng.gen::<f32>() * (1.0 - spread.cos());
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let (s, c) = (rng.gen::<f32>() * 2.0 * PI).sin_cos();
+
+    // Any two axes square to `direction` and each other.
+    let other = if direction.x.abs() < 0.9 {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    let u = direction.cross(&other).normalize();
+    let v = direction.cross(&u);
+    direction * cos + u * (sin * c) + v * (sin * s)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{
+        self,
+        File,
+    };
+    use std::io::prelude::*;
+
+    use gel::Vec3;
+
+    use super::{
+        Emitter,
+        Particles,
+        Presets,
+    };
+
+    // Loads one small white preset with the given lines of particles.toml,
+    // and a stand-in for its texture.
+    fn presets(name: &str, lines: &[&str]) -> Presets {
+        let dir = env::temp_dir().join(format!("cube-particles-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        File::create(dir.join("spark.png")).unwrap();
+        let path = dir.join("particles.toml");
+        let src = format!("[[presets]]\nname = \"{}\"\ntexture = \"spark\"\nsize = [0.1]\n\
+                           color = [[1.0, 1.0, 1.0, 1.0]]\n{}\n",
+                          name, lines.join("\n"));
+        File::create(&path).unwrap().write_all(src.as_bytes()).unwrap();
+        let presets = Presets::load(&path, &dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        presets
+    }
+
+    fn origin() -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn bursts_spawn_at_once() {
+        let presets = presets("burst", &["burst = 5", "life = [10.0]"]);
+        let mut particles = Particles::new(1);
+        particles.emit(0, origin());
+        particles.update(&presets, 0.25);
+        assert_eq!(particles.particles().len(), 5);
+        assert!(particles.emitters().is_empty());
+        particles.update(&presets, 0.25);
+        assert_eq!(particles.particles().len(), 5);
+    }
+
+    #[test]
+    fn continuous_emitters_keep_their_rate() {
+        let presets = presets("smoke", &["rate = 8.0", "duration = 1.0", "life = [10.0]"]);
+        let mut particles = Particles::new(1);
+        let mut emitter = Emitter::new(0, origin());
+        for &(count, done) in [(2, false), (4, false), (6, false), (8, true), (8, true)].iter() {
+            emitter.update(&presets, &mut particles, 0.25);
+            assert_eq!(particles.particles().len(), count);
+            assert_eq!(emitter.is_done(), done);
+        }
+    }
+
+    #[test]
+    fn particles_die_of_old_age() {
+        let presets = presets("spark", &["burst = 3", "life = [1.0]"]);
+        let mut particles = Particles::new(1);
+        particles.emit(0, origin());
+        particles.update(&presets, 0.5);
+        assert_eq!(particles.particles().len(), 3);
+        assert!(particles.particles().iter().all(|p| p.age() == 0.5));
+        particles.update(&presets, 0.5);
+        assert!(particles.particles().is_empty());
+    }
+
+    #[test]
+    fn particles_fall() {
+        let presets = presets("ball", &["burst = 1", "life = [10.0]", "speed = [4.0]",
+                                        "spread = 0.0", "gravity = 2.0"]);
+        let mut particles = Particles::new(1);
+        particles.spawn(presets.get(0), Vec3::new(1.0, 2.0, 3.0));
+
+        // Speeds change before positions, each half second.
+        for &(y, vy) in [(3.5, 3.0), (4.5, 2.0), (5.0, 1.0), (5.0, 0.0)].iter() {
+            particles.update(&presets, 0.5);
+            let particle = particles.particles()[0];
+            let (p, v) = (particle.position(), particle.velocity());
+            assert_eq!((p.x, p.y, p.z), (1.0, y, 3.0));
+            assert_eq!((v.x, v.y, v.z), (0.0, vy, 0.0));
+        }
+    }
+}
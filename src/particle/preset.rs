@@ -0,0 +1,291 @@
+use std::path::Path;
+use std::io::prelude::*;
+use std::fs::File;
+
+use toml::{
+    Decoder,
+    Value,
+};
+use rustc_serialize::Decodable;
+use gel::Vec3;
+
+use config;
+use errors::{
+    Result,
+    Error,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Emission {
+    // All at once, after which the emitter is done.
+    Burst(u32),
+    // `rate` a second, for `duration` seconds or until the emitter is dropped.
+    Continuous {
+        rate: f32,
+        duration: Option<f32>,
+    },
+}
+
+// How an emitter gives off particles, and how they behave.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    name: String,
+    texture: usize,
+    emission: Emission,
+    life: (f32, f32),
+    speed: (f32, f32),
+    direction: Vec3,
+    spread: f32,
+    jitter: f32,
+    gravity: f32,
+    size: (f32, f32),
+    color: ([f32; 4], [f32; 4]),
+}
+
+impl Preset {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Index into the presets' textures.
+    pub fn texture(&self) -> usize {
+        self.texture
+    }
+
+    pub fn emission(&self) -> Emission {
+        self.emission
+    }
+
+    // Seconds a particle lasts, at least and at most.
+    pub fn life(&self) -> (f32, f32) {
+        self.life
+    }
+
+    // Blocks per second particles set off at, at least and at most.
+    pub fn speed(&self) -> (f32, f32) {
+        self.speed
+    }
+
+    // The unit direction particles set off in, give or take `spread`.
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    // Radians particles stray from `direction`, up to pi for any way at all.
+    pub fn spread(&self) -> f32 {
+        self.spread
+    }
+
+    // Blocks from the emitter particles appear within, along each axis.
+    pub fn jitter(&self) -> f32 {
+        self.jitter
+    }
+
+    // Downward acceleration, in blocks per second squared. Negative rises.
+    pub fn gravity(&self) -> f32 {
+        self.gravity
+    }
+
+    // Half the width of a particle when it appears and when it dies.
+    pub fn size(&self) -> (f32, f32) {
+        self.size
+    }
+
+    // RGBA of a particle when it appears and when it dies.
+    pub fn color(&self) -> ([f32; 4], [f32; 4]) {
+        self.color
+    }
+}
+
+pub struct Presets {
+    textures: Vec<String>,
+    presets: Vec<Preset>,
+}
+
+impl Presets {
+    // Textures are checked against the images in `particles`.
+    pub fn load<P, Q>(path: P, particles: Q) -> Result<Presets>
+        where P: AsRef<Path>,
+              Q: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut file = try!(File::open(path));
+        let mut src = String::new();
+        try!(file.read_to_string(&mut src));
+
+        let mut table = try!(config::parse(&src, path));
+        let entries = match table.remove("presets") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => {
+                let desc = "`presets` must be an array of tables";
+                return Err(config::error_at(path, &src, 0, desc));
+            },
+            None => Vec::new(),
+        };
+
+        let mut textures: Vec<String> = Vec::new();
+        let mut presets: Vec<Preset> = Vec::new();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let offset = config::entry_offset(&src, "presets", i);
+            let mut decoder = Decoder::new(entry);
+            let toml_preset = match TomlPreset::decode(&mut decoder) {
+                Ok(preset) => preset,
+                Err(e) => return Err(config::error_at(path, &src, offset, e)),
+            };
+            let texture = match textures.iter().position(|t| t == &toml_preset.texture) {
+                Some(i) => i,
+                None => {
+                    let file = particles.as_ref().join(format!("{}.png", toml_preset.texture));
+                    if !file.exists() {
+                        let desc = format!("preset `{}` uses missing texture `{}`",
+                                           toml_preset.name, file.display());
+                        return Err(config::error_at(path, &src, offset, desc));
+                    }
+                    textures.push(toml_preset.texture.clone());
+                    textures.len() - 1
+                },
+            };
+            let preset = match toml_preset.into_preset(texture) {
+                Ok(preset) => preset,
+                Err(desc) => return Err(config::error_at(path, &src, offset, desc)),
+            };
+            if presets.iter().any(|p| p.name == preset.name) {
+                let desc = format!("duplicate preset `{}`", preset.name);
+                return Err(config::error_at(path, &src, offset, desc));
+            }
+            presets.push(preset);
+        }
+
+        Ok(Presets {
+            textures: textures,
+            presets: presets,
+        })
+    }
+
+    // Images in the particles directory, without the extension.
+    pub fn textures(&self) -> &[String] {
+        &self.textures
+    }
+
+    pub fn get(&self, id: usize) -> &Preset {
+        &self.presets[id]
+    }
+
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.presets.iter().position(|p| p.name == name)
+    }
+
+    // Like `id`, for presets the game cannot run without.
+    pub fn require(&self, name: &str) -> Result<usize> {
+        self.id(name).ok_or(Error::with_detail("particle error",
+                                               format!("no preset named `{}`", name)))
+    }
+}
+
+#[derive(Debug, Clone, RustcDecodable)]
+struct TomlPreset {
+    name: String,
+    texture: String,
+    burst: Option<u32>,
+    rate: Option<f32>,
+    duration: Option<f32>,
+    life: Vec<f32>,
+    speed: Option<Vec<f32>>,
+    direction: Option<Vec<f32>>,
+    spread: Option<f32>,
+    jitter: Option<f32>,
+    gravity: Option<f32>,
+    size: Vec<f32>,
+    color: Vec<Vec<f32>>,
+}
+
+// A range written as [least, most], or one number for both ends.
+fn range(v: &[f32], name: &str, field: &str) -> ::std::result::Result<(f32, f32), String> {
+    match v.len() {
+        1 => Ok((v[0], v[0])),
+        2 if v[0] <= v[1] => Ok((v[0], v[1])),
+        _ => Err(format!("preset `{}` needs `{}` to be [least, most]", name, field)),
+    }
+}
+
+fn rgba(v: &[f32], name: &str) -> ::std::result::Result<[f32; 4], String> {
+    if v.len() == 4 && v.iter().all(|&c| c >= 0.0 && c <= 1.0) {
+        Ok([v[0], v[1], v[2], v[3]])
+    } else {
+        Err(format!("preset `{}` needs colours to be [r, g, b, a] from 0 to 1", name))
+    }
+}
+
+impl TomlPreset {
+    fn into_preset(self, texture: usize) -> ::std::result::Result<Preset, String> {
+        let name = self.name;
+        let emission = match (self.burst, self.rate) {
+            (Some(count), None) if self.duration.is_none() => Emission::Burst(count),
+            (None, Some(rate)) if rate > 0.0 && self.duration.map_or(true, |d| d > 0.0) => {
+                Emission::Continuous {
+                    rate: rate,
+                    duration: self.duration,
+                }
+            },
+            _ => return Err(format!("preset `{}` needs either a `burst` count, or a positive \
+                                     `rate` and optional `duration`", name)),
+        };
+
+        let life = try!(range(&self.life, &name, "life"));
+        if life.0 <= 0.0 {
+            return Err(format!("preset `{}` has a life that is not positive", name));
+        }
+        let speed = try!(range(&self.speed.unwrap_or(vec![0.0]), &name, "speed"));
+        if speed.0 < 0.0 {
+            return Err(format!("preset `{}` has a negative speed", name));
+        }
+
+        let direction = match self.direction {
+            Some(ref d) if d.len() == 3 && d.iter().any(|&c| c != 0.0) => {
+                Vec3::new(d[0], d[1], d[2]).normalize()
+            },
+            Some(_) => return Err(format!("preset `{}` needs `direction` to be a non-zero \
+                                           [x, y, z]", name)),
+            None => Vec3::new(0.0, 1.0, 0.0),
+        };
+        let spread = self.spread.unwrap_or(180.0);
+        if spread < 0.0 || spread > 180.0 {
+            return Err(format!("preset `{}` needs `spread` to be from 0 to 180 degrees", name));
+        }
+        let jitter = self.jitter.unwrap_or(0.0);
+        if jitter < 0.0 {
+            return Err(format!("preset `{}` has a negative jitter", name));
+        }
+
+        let size = match self.size.len() {
+            1 => (self.size[0], self.size[0]),
+            2 => (self.size[0], self.size[1]),
+            _ => return Err(format!("preset `{}` needs `size` to be [start, end]", name)),
+        };
+        if size.0 < 0.0 || size.1 < 0.0 {
+            return Err(format!("preset `{}` has a negative size", name));
+        }
+        let color = match self.color.len() {
+            1 => {
+                let c = try!(rgba(&self.color[0], &name));
+                (c, c)
+            },
+            2 => (try!(rgba(&self.color[0], &name)), try!(rgba(&self.color[1], &name))),
+            _ => return Err(format!("preset `{}` needs `color` to be [start, end]", name)),
+        };
+
+        Ok(Preset {
+            name: name,
+            texture: texture,
+            emission: emission,
+            life: life,
+            speed: speed,
+            direction: direction,
+            spread: spread.to_radians(),
+            jitter: jitter,
+            gravity: self.gravity.unwrap_or(0.0),
+            size: size,
+            color: color,
+        })
+    }
+}
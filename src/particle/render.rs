@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+    BlendingFunction,
+    LinearBlendingFactor,
+};
+use glium::texture::SrgbTexture2d;
+use glium::uniforms::{
+    Uniforms,
+    UniformValue,
+    AsUniformValue,
+    Sampler,
+    MinifySamplerFilter,
+    MagnifySamplerFilter,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use image;
+
+use errors::{
+    Result,
+    Error,
+};
+use super::{
+    Particle,
+    Presets,
+};
+
+#[derive(Copy, Clone)]
+struct Corner {
+    corner: [f32; 2],
+}
+implement_vertex!(Corner, corner);
+
+const QUAD: [Corner; 6] = [
+    Corner { corner: [-1.0, -1.0] },
+    Corner { corner: [ 1.0, -1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [-1.0,  1.0] },
+    Corner { corner: [-1.0, -1.0] },
+];
+
+// What the particle program needs of each particle.
+#[derive(Copy, Clone)]
+struct Instance {
+    centre: [f32; 3],
+    size: f32,
+    tint: [f32; 4],
+}
+implement_vertex!(Instance, centre, size, tint);
+
+// Room for this many particles to begin with.
+const INSTANCES: usize = 256;
+
+// Draws particles with the particle program as squares facing the camera, one
+// instanced draw for each texture in use.
+pub struct ParticleRenderer {
+    quad: VertexBuffer<Corner>,
+    // In the order of the presets' textures.
+    textures: Vec<SrgbTexture2d>,
+    // Rewritten each frame with every particle, grouped by texture, and only
+    // replaced when there are more particles than it has room for.
+    instances: VertexBuffer<Instance>,
+}
+
+impl ParticleRenderer {
+    pub fn new<F, P>(facade: &F, presets: &Presets, particles: P) -> Result<ParticleRenderer>
+        where F: Facade,
+              P: AsRef<Path>,
+    {
+        let quad = try!(VertexBuffer::new(facade, &QUAD));
+        let mut textures = Vec::with_capacity(presets.textures().len());
+        for name in presets.textures() {
+            let img = try!(image::open(particles.as_ref().join(format!("{}.png", name))));
+            textures.push(try!(SrgbTexture2d::new(facade, img)));
+        }
+
+        Ok(ParticleRenderer {
+            quad: quad,
+            textures: textures,
+            instances: try!(VertexBuffer::empty_dynamic(facade, INSTANCES)),
+        })
+    }
+
+    // `uniforms` supply the view and projection. Particles are blended
+    // without being sorted, and hide nothing drawn after them, so they are
+    // best drawn last.
+    pub fn draw<F, S, U>(&mut self,
+                          facade: &F,
+                          surface: &mut S,
+                          program: &Program,
+                          uniforms: &U,
+                          particles: &[Particle]) -> Result<()>
+        where F: Facade,
+              S: Surface,
+              U: Uniforms,
+    {
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: false,
+            blending_function: Some(BlendingFunction::Addition {
+                source: LinearBlendingFactor::SourceAlpha,
+                destination: LinearBlendingFactor::OneMinusSourceAlpha,
+            }),
+            ..Default::default()
+        };
+
+        let mut batches: Vec<Vec<Instance>> = vec![Vec::new(); self.textures.len()];
+        for particle in particles {
+            let p = particle.position();
+            batches[particle.texture()].push(Instance {
+                centre: [p.x, p.y, p.z],
+                size: particle.size(),
+                tint: particle.color(),
+            });
+        }
+
+        if particles.len() > self.instances.len() {
+            let room = particles.len().next_power_of_two();
+            self.instances = try!(VertexBuffer::empty_dynamic(facade, room));
+        }
+
+        let mut start = 0;
+        for (texture, batch) in self.textures.iter().zip(batches.iter()) {
+            if batch.is_empty() {
+                continue;
+            }
+            // Always in range, as the buffer has room for every particle.
+            let instances = self.instances.slice(start..start + batch.len()).unwrap();
+            start += batch.len();
+            instances.write(batch);
+            let per_instance = try!(instances.per_instance().map_err(|_| {
+                Error::with_detail("draw error", "instanced drawing is not supported")
+            }));
+            let sprite_uniforms = SpriteUniforms {
+                uniforms: uniforms,
+                sprite: texture.sampled()
+                    .minify_filter(MinifySamplerFilter::Linear)
+                    .magnify_filter(MagnifySamplerFilter::Nearest),
+            };
+            try!(surface.draw((&self.quad, per_instance),
+                              NoIndices(PrimitiveType::TrianglesList),
+                              program,
+                              &sprite_uniforms,
+                              draw_params));
+        }
+        Ok(())
+    }
+}
+
+struct SpriteUniforms<'a, U: 'a> {
+    uniforms: &'a U,
+    sprite: Sampler<'a, SrgbTexture2d>,
+}
+
+impl<'a, U> Uniforms for SpriteUniforms<'a, U>
+    where U: Uniforms
+{
+    fn visit_values<'b, F>(&'b self, mut output: F)
+        where F: FnMut(&str, UniformValue<'b>)
+    {
+        self.uniforms.visit_values(&mut output);
+        output("sprite", self.sprite.as_uniform_value());
+    }
+}
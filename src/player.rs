@@ -1,64 +1,75 @@
-use glium::backend::Facade;
-use glium::{
-    Surface,
-    Program,
-};
-use glium::texture::SrgbTexture2d;
-use glium::uniforms::{
-    Uniforms,
-    Sampler,
-    SamplerWrapFunction,
-    MinifySamplerFilter,
-    MagnifySamplerFilter,
-};
 use gel::{
-    Mat4,
     Vec3,
     Normalize,
     Cross,
 };
 
 use config::PlayerParams;
-use cube::Cube;
-use errors::Result;
+use world::shape::Aabb;
+
+// How wide the player is for colliding with blocks, against their scale.
+const WIDTH: f32 = 0.6;
 
 pub struct Player {
-    object: Cube,
     position: Vec3,
     direction: Vec3,
     up: Vec3,
     speed: f32,
-    diffuse_map: SrgbTexture2d,
-    specular_map: SrgbTexture2d,
+    scale: f32,
+    // Radians about the vertical of the way the player last walked, 0
+    // facing +z.
+    yaw: f32,
+    // Blocks walked in all.
+    walked: f32,
     shine: f32,
+    health: u32,
+    max_health: u32,
 }
 
 impl Player {
-    pub fn new<F>(facade: &F,
-                  diffuse: SrgbTexture2d,
-                  specular: SrgbTexture2d,
-                  shine: f32,
-                  params: &PlayerParams) -> Result<Player>
-        where F: Facade
-    {
-        let mut cube: Cube = try!(Cube::new(facade));
-        cube.set_scale(params.scale());
-        Ok(Player {
-            object: cube,
+    pub fn new(params: &PlayerParams) -> Player {
+        Player {
             position: Vec3::new(0.0, 0.0, 0.0),
             direction: Vec3::new(0.0, 0.0, -1.0),
             up: Vec3::new(0.0, 1.0, 0.0),
             speed: params.speed(),
-            diffuse_map: diffuse,
-            specular_map: specular,
-            shine: shine,
-        })
+            scale: params.scale(),
+            yaw: 0.0,
+            walked: 0.0,
+            shine: params.shine(),
+            health: params.health(),
+            max_health: params.health(),
+        }
     }
 
     pub fn position(&self) -> Vec3 {
         self.position
     }
 
+    pub fn set_position(&mut self, v: Vec3) {
+        self.position = v;
+    }
+
+    // The middle of the bottom of the player.
+    pub fn feet(&self) -> Vec3 {
+        self.position - Vec3::new(0.0, self.scale, 0.0)
+    }
+
+    // The box the player collides with blocks in, from the feet to as far
+    // above the eye as the eye is above the feet.
+    pub fn bounds(&self) -> Aabb {
+        let feet = self.feet();
+        Aabb::standing([feet.x, feet.y, feet.z], WIDTH * self.scale, 2.0 * self.scale)
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn walked(&self) -> f32 {
+        self.walked
+    }
+
     pub fn direction(&self) -> Vec3 {
         self.direction
     }
@@ -68,70 +79,61 @@ impl Player {
     }
 
     pub fn forward(&mut self, dt: f32) {
-        let velocity = self.speed * dt;
-        self.position = self.position + (self.direction * velocity);
-        self.object.set_position(self.position);
+        let heading = self.direction;
+        self.walk(heading, dt);
     }
 
     pub fn backward(&mut self, dt: f32) {
-        let velocity = self.speed * dt;
-        self.position = self.position - (self.direction * velocity);
-        self.object.set_position(self.position);
+        let heading = self.direction * -1.0;
+        self.walk(heading, dt);
     }
 
     pub fn right(&mut self, dt: f32) {
-        let velocity = self.speed * dt;
-        self.position = self.position + (self.direction.cross(&self.up).normalize() * velocity);
-        self.object.set_position(self.position);
+        let heading = self.direction.cross(&self.up).normalize();
+        self.walk(heading, dt);
     }
 
     pub fn left(&mut self, dt: f32) {
+        let heading = self.direction.cross(&self.up).normalize() * -1.0;
+        self.walk(heading, dt);
+    }
+
+    // Moves along the ground, turning to face the way moved.
+    fn walk(&mut self, heading: Vec3, dt: f32) {
         let velocity = self.speed * dt;
-        self.position = self.position - (self.direction.cross(&self.up).normalize() * velocity);
-        self.object.set_position(self.position);
+        self.position = self.position + (heading * velocity);
+        self.yaw = heading.x.atan2(heading.z);
+        self.walked += velocity;
     }
 
     pub fn up(&mut self, dt: f32) {
         let velocity = self.speed * dt;
         self.position = self.position + (self.up * velocity);
-        self.object.set_position(self.position);
     }
 
     pub fn down(&mut self, dt: f32) {
         let velocity = self.speed * dt;
         self.position = self.position - (self.up * velocity);
-        self.object.set_position(self.position);
     }
 
-    pub fn model(&self) -> Mat4 {
-        self.object.model()
-    }
-
-    pub fn diffuse_map(&self) -> Sampler<SrgbTexture2d> {
-        self.diffuse_map.sampled()
-            .wrap_function(SamplerWrapFunction::Mirror)
-            .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
-            .magnify_filter(MagnifySamplerFilter::Linear)
+    pub fn shine(&self) -> f32 {
+        self.shine
     }
 
-    pub fn specular_map(&self) -> Sampler<SrgbTexture2d> {
-        self.specular_map.sampled()
-            .wrap_function(SamplerWrapFunction::Mirror)
-            .minify_filter(MinifySamplerFilter::LinearMipmapLinear)
-            .magnify_filter(MagnifySamplerFilter::Linear)
+    pub fn health(&self) -> u32 {
+        self.health
     }
 
-    pub fn shine(&self) -> f32 {
-        self.shine
+    // Takes `damage` off the player's health. Returns true if that leaves
+    // none.
+    pub fn hurt(&mut self, damage: u32) -> bool {
+        self.health = self.health.saturating_sub(damage);
+        self.health == 0
     }
 
-    pub fn draw<S, U>(&self,
-                      surface: &mut S,
-                      program: &Program,
-                      uniforms: &U) -> Result<()>
-        where S: Surface,
-              U: Uniforms,
-    {
-        self.object.draw(surface, program, uniforms)
+    // Starts again at `position` with full health.
+    pub fn respawn(&mut self, position: Vec3) {
+        self.position = position;
+        self.health = self.max_health;
     }
 }
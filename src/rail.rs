@@ -0,0 +1,398 @@
+use gel::Vec3;
+
+use block::Registry;
+use world::{
+    Blocks,
+    BlocksMut,
+    BlockId,
+    BlockPos,
+    Face,
+};
+use errors::Result;
+
+const HORIZONTAL: [Face; 4] = [Face::Back, Face::Front, Face::Right, Face::Left];
+
+// Rail blocks, and the sides each joins.
+const RAILS: [(&'static str, Face, Face); 6] = [
+    ("rail", Face::Back, Face::Front),
+    ("rail_x", Face::Left, Face::Right),
+    ("rail_se", Face::Front, Face::Right),
+    ("rail_sw", Face::Front, Face::Left),
+    ("rail_nw", Face::Back, Face::Left),
+    ("rail_ne", Face::Back, Face::Right),
+];
+
+// Rail blocks by the two sides of the cell they join. North is `Face::Back`
+// (-z), south `Face::Front` (+z), east `Face::Right` (+x) and west `Face::Left`.
+pub struct Rails {
+    variants: Vec<(Face, Face, BlockId)>,
+}
+
+impl Rails {
+    pub fn new(registry: &Registry) -> Result<Rails> {
+        let mut ids = [0; 6];
+        for (id, &(name, _, _)) in ids.iter_mut().zip(RAILS.iter()) {
+            *id = try!(registry.require(name));
+        }
+        Ok(Rails::with_blocks(ids))
+    }
+
+    // `ids` are the blocks of the rails in `RAILS`, in order.
+    pub fn with_blocks(ids: [BlockId; 6]) -> Rails {
+        Rails {
+            variants: RAILS.iter()
+                .zip(ids.iter())
+                .map(|(&(_, a, b), &id)| (a, b, id))
+                .collect(),
+        }
+    }
+
+    pub fn is_rail(&self, id: BlockId) -> bool {
+        self.ends(id).is_some()
+    }
+
+    // The sides joined by rail `id`.
+    pub fn ends(&self, id: BlockId) -> Option<(Face, Face)> {
+        self.variants.iter().find(|v| v.2 == id).map(|v| (v.0, v.1))
+    }
+
+    // The rail joining sides `a` and `b`, if there is one.
+    pub fn block(&self, a: Face, b: Face) -> Option<BlockId> {
+        self.variants.iter()
+            .find(|v| (v.0 == a && v.1 == b) || (v.0 == b && v.1 == a))
+            .map(|v| v.2)
+    }
+
+    // Whether the rail at `pos` runs out through `face` into another rail that
+    // runs back in.
+    pub fn is_linked<W>(&self, world: &W, pos: BlockPos, face: Face) -> bool
+        where W: Blocks
+    {
+        let joins = |id: BlockId, f: Face| {
+            self.ends(id).map_or(false, |(a, b)| a == f || b == f)
+        };
+        joins(world.block(pos), face) &&
+            joins(world.block(pos.neighbour(face)), face.opposite())
+    }
+
+    // Picks the sides to join for a rail with rail neighbours on `sides`,
+    // preferring straights to corners.
+    pub fn shape_for(sides: &[Face]) -> (Face, Face) {
+        let has = |f: Face| sides.contains(&f);
+        if has(Face::Back) && has(Face::Front) {
+            (Face::Back, Face::Front)
+        } else if has(Face::Left) && has(Face::Right) {
+            (Face::Left, Face::Right)
+        } else if sides.len() >= 2 {
+            (sides[0], sides[1])
+        } else if sides.len() == 1 {
+            (sides[0], sides[0].opposite())
+        } else {
+            (Face::Back, Face::Front)
+        }
+    }
+
+    // Horizontal sides of `pos` with a rail that could join it: one with a
+    // free end, or already joined to `pos`.
+    fn candidates<W>(&self, world: &W, pos: BlockPos) -> Vec<Face>
+        where W: Blocks
+    {
+        HORIZONTAL.iter().cloned().filter(|&face| {
+            let side = pos.neighbour(face);
+            match self.ends(world.block(side)) {
+                Some((a, b)) => {
+                    a == face.opposite() || b == face.opposite() ||
+                        !self.is_linked(world, side, a) || !self.is_linked(world, side, b)
+                },
+                None => false,
+            }
+        }).collect()
+    }
+
+    // Lays a rail at `pos` joined to the rails around it, and turns neighbours
+    // with a free end towards it.
+    pub fn place<W>(&self, world: &mut W, pos: BlockPos)
+        where W: BlocksMut
+    {
+        let sides = self.candidates(world, pos);
+        let (a, b) = Rails::shape_for(&sides);
+        match self.block(a, b) {
+            Some(id) => world.set_block(pos, id),
+            None => return,
+        }
+
+        for &face in [a, b].iter() {
+            let side = pos.neighbour(face);
+            let (c, d) = match self.ends(world.block(side)) {
+                Some(ends) => ends,
+                None => continue,
+            };
+            if c == face.opposite() || d == face.opposite() {
+                continue;
+            }
+            // Keep whichever end is joined, or straighten out if neither is.
+            let keep = if self.is_linked(world, side, d) {
+                d
+            } else if self.is_linked(world, side, c) {
+                c
+            } else {
+                face
+            };
+            if let Some(id) = self.block(keep, face.opposite()) {
+                world.set_block(side, id);
+            }
+        }
+    }
+}
+
+// A cart running along rails. Inside each rail cell it moves from the middle
+// of the side it entered by, through the centre, to the middle of the side it
+// leaves by, so every cell is one block of travel whatever its shape.
+#[derive(Debug, Clone)]
+pub struct Minecart {
+    cell: BlockPos,
+    from: Face,
+    // Progress through the cell, from 0 to 1.
+    t: f32,
+    // Blocks per second, never negative. Carts reverse by swapping ends.
+    speed: f32,
+    friction: f32,
+    max_speed: f32,
+}
+
+impl Minecart {
+    pub fn new<W>(world: &W, rails: &Rails, cell: BlockPos) -> Option<Minecart>
+        where W: Blocks
+    {
+        rails.ends(world.block(cell)).map(|(a, _)| {
+            Minecart {
+                cell: cell,
+                from: a,
+                t: 0.5,
+                speed: 0.0,
+                friction: 0.5,
+                max_speed: 8.0,
+            }
+        })
+    }
+
+    pub fn cell(&self) -> BlockPos {
+        self.cell
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_friction(&mut self, friction: f32) {
+        self.friction = friction;
+    }
+
+    pub fn set_max_speed(&mut self, max_speed: f32) {
+        self.max_speed = max_speed;
+    }
+
+    // The side the cart is heading out of its cell by.
+    pub fn heading<W>(&self, world: &W, rails: &Rails) -> Option<Face>
+        where W: Blocks
+    {
+        rails.ends(world.block(self.cell)).map(|(a, b)| if a == self.from { b } else { a })
+    }
+
+    // World position of the bottom centre of the cart.
+    pub fn position<W>(&self, world: &W, rails: &Rails) -> Vec3
+        where W: Blocks
+    {
+        let centre = Vec3::new(self.cell.x as f32 + 0.5,
+                               self.cell.y as f32,
+                               self.cell.z as f32 + 0.5);
+        let edge = |face: Face| {
+            let d = face.normal();
+            centre + Vec3::new(d[0] * 0.5, 0.0, d[2] * 0.5)
+        };
+        let to = match self.heading(world, rails) {
+            Some(face) => face,
+            None => return centre,
+        };
+        if self.t < 0.5 {
+            let s = self.t * 2.0;
+            edge(self.from) * (1.0 - s) + centre * s
+        } else {
+            let s = self.t * 2.0 - 1.0;
+            centre * (1.0 - s) + edge(to) * s
+        }
+    }
+
+    // Adds speed along `direction`, turning the cart round if it points back
+    // the way the cart came.
+    pub fn push<W>(&mut self, world: &W, rails: &Rails, direction: Vec3, amount: f32)
+        where W: Blocks
+    {
+        let to = match self.heading(world, rails) {
+            Some(face) => face,
+            None => return,
+        };
+        // Direction of travel at the cart's current point in the cell.
+        let d = (if self.t < 0.5 { self.from.opposite() } else { to }).normal();
+        let along = d[0] * direction.x + d[2] * direction.z;
+        let mut velocity = self.speed + along * amount;
+        if velocity < 0.0 {
+            self.from = to;
+            self.t = 1.0 - self.t;
+            velocity = -velocity;
+        }
+        self.speed = if velocity > self.max_speed { self.max_speed } else { velocity };
+    }
+
+    pub fn update<W>(&mut self, world: &W, rails: &Rails, dt: f32)
+        where W: Blocks
+    {
+        self.speed -= self.friction * dt;
+        if self.speed < 0.0 {
+            self.speed = 0.0;
+        }
+        self.t += self.speed * dt;
+
+        while self.t >= 1.0 {
+            let to = match self.heading(world, rails) {
+                Some(face) => face,
+                // The rail is gone.
+                None => {
+                    self.speed = 0.0;
+                    self.t = 0.5;
+                    return;
+                },
+            };
+            if !rails.is_linked(world, self.cell, to) {
+                // End of the line.
+                self.t = 1.0;
+                self.speed = 0.0;
+                return;
+            }
+            self.cell = self.cell.neighbour(to);
+            self.from = to.opposite();
+            self.t -= 1.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gel::Vec3;
+
+    use world::{
+        Blocks,
+        BlockId,
+        BlockPos,
+        Face,
+        Grid,
+    };
+    use super::{
+        Minecart,
+        Rails,
+    };
+
+    const RAIL: BlockId = 1;
+    const RAIL_X: BlockId = 2;
+    const RAIL_SE: BlockId = 3;
+    const RAIL_SW: BlockId = 4;
+    const RAIL_NW: BlockId = 5;
+    const RAIL_NE: BlockId = 6;
+
+    fn rails() -> Rails {
+        Rails::with_blocks([RAIL, RAIL_X, RAIL_SE, RAIL_SW, RAIL_NW, RAIL_NE])
+    }
+
+    fn lay(rails: &Rails, grid: &mut Grid, cells: &[(i32, i32)]) {
+        for &(x, z) in cells.iter() {
+            rails.place(grid, BlockPos::new(x, 0, z));
+        }
+    }
+
+    fn at(grid: &Grid, x: i32, z: i32) -> BlockId {
+        grid.block(BlockPos::new(x, 0, z))
+    }
+
+    #[test]
+    fn lone_rail_runs_north_south() {
+        let (rails, mut grid) = (rails(), Grid::new(4, 1, 4));
+        lay(&rails, &mut grid, &[(1, 1)]);
+        assert_eq!(at(&grid, 1, 1), RAIL);
+        assert_eq!(rails.ends(RAIL), Some((Face::Back, Face::Front)));
+    }
+
+    #[test]
+    fn rails_in_a_row_are_straight() {
+        let (rails, mut grid) = (rails(), Grid::new(4, 1, 4));
+        lay(&rails, &mut grid, &[(0, 1), (1, 1), (2, 1)]);
+        assert_eq!(at(&grid, 0, 1), RAIL_X);
+        assert_eq!(at(&grid, 1, 1), RAIL_X);
+        assert_eq!(at(&grid, 2, 1), RAIL_X);
+    }
+
+    #[test]
+    fn turning_makes_a_corner() {
+        let (rails, mut grid) = (rails(), Grid::new(4, 1, 4));
+        lay(&rails, &mut grid, &[(0, 0), (1, 0), (1, 1), (1, 2)]);
+        assert_eq!(at(&grid, 0, 0), RAIL_X);
+        assert_eq!(at(&grid, 1, 0), RAIL_SW);
+        assert_eq!(at(&grid, 1, 1), RAIL);
+        assert_eq!(at(&grid, 1, 2), RAIL);
+        assert!(rails.is_linked(&grid, BlockPos::new(0, 0, 0), Face::Right));
+        assert!(rails.is_linked(&grid, BlockPos::new(1, 0, 0), Face::Front));
+        assert!(!rails.is_linked(&grid, BlockPos::new(1, 0, 0), Face::Right));
+    }
+
+    #[test]
+    fn every_pair_of_sides_has_a_rail() {
+        let rails = rails();
+        let sides = [Face::Back, Face::Front, Face::Left, Face::Right];
+        for &a in sides.iter() {
+            for &b in sides.iter() {
+                assert_eq!(rails.block(a, b).is_some(), a != b);
+            }
+        }
+        assert_eq!(rails.block(Face::Top, Face::Back), None);
+    }
+
+    #[test]
+    fn cart_follows_a_straight_into_a_corner() {
+        let (rails, mut grid) = (rails(), Grid::new(4, 1, 4));
+        lay(&rails, &mut grid, &[(0, 0), (1, 0), (1, 1)]);
+        let mut cart = Minecart::new(&grid, &rails, BlockPos::new(0, 0, 0)).unwrap();
+        cart.set_friction(0.0);
+        cart.push(&grid, &rails, Vec3::new(1.0, 0.0, 0.0), 2.0);
+        assert_eq!(cart.heading(&grid, &rails), Some(Face::Right));
+        assert_eq!(cart.speed(), 2.0);
+
+        // Half a block a step, from the middle of the first rail.
+        let mut cells = Vec::new();
+        for _ in 0..6 {
+            cart.update(&grid, &rails, 0.25);
+            cells.push(cart.cell());
+        }
+        assert_eq!(cells, vec![BlockPos::new(1, 0, 0),
+                               BlockPos::new(1, 0, 0),
+                               BlockPos::new(1, 0, 1),
+                               BlockPos::new(1, 0, 1),
+                               BlockPos::new(1, 0, 1),
+                               BlockPos::new(1, 0, 1)]);
+
+        // Stopped at the far end of the corner's neighbour.
+        assert_eq!(cart.speed(), 0.0);
+        let p = cart.position(&grid, &rails);
+        assert_eq!((p.x, p.y, p.z), (1.5, 0.0, 2.0));
+    }
+
+    #[test]
+    fn pushing_back_turns_the_cart_round() {
+        let (rails, mut grid) = (rails(), Grid::new(4, 1, 4));
+        lay(&rails, &mut grid, &[(0, 0), (1, 0), (2, 0)]);
+        let mut cart = Minecart::new(&grid, &rails, BlockPos::new(1, 0, 0)).unwrap();
+        cart.push(&grid, &rails, Vec3::new(1.0, 0.0, 0.0), 1.0);
+        assert_eq!(cart.heading(&grid, &rails), Some(Face::Right));
+        cart.push(&grid, &rails, Vec3::new(-1.0, 0.0, 0.0), 3.0);
+        assert_eq!(cart.heading(&grid, &rails), Some(Face::Left));
+        assert_eq!(cart.speed(), 2.0);
+    }
+}
@@ -0,0 +1,329 @@
+use std::path::Path;
+use std::cmp::Ordering;
+use std::fs::{
+    self,
+    File,
+};
+use std::io::prelude::*;
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use glium::backend::Facade;
+use glium::texture::SrgbTexture2d;
+use image::{
+    self,
+    DynamicImage,
+    GenericImage,
+    ImageBuffer,
+    Rgba,
+    RgbaImage,
+};
+
+use world::mesh::TileId;
+use errors::{
+    Result,
+    Error,
+};
+
+// Texture coordinates of a tile, with (u0, v0) at its bottom left.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+impl UvRect {
+    pub fn full() -> UvRect {
+        UvRect {
+            u0: 0.0,
+            v0: 0.0,
+            u1: 1.0,
+            v1: 1.0,
+        }
+    }
+
+    // As (u0, v0, u1, v1), the way vertices carry it.
+    pub fn rect(&self) -> [f32; 4] {
+        [self.u0, self.v0, self.u1, self.v1]
+    }
+}
+
+// Pixel rectangle of a tile inside the atlas image, excluding padding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct AtlasBuilder {
+    tiles: Vec<(String, RgbaImage)>,
+    padding: u32,
+}
+
+impl AtlasBuilder {
+    // `padding` pixels of each tile's edge are repeated around it, so that
+    // filtering and lower mipmap levels do not pick up neighbouring tiles.
+    pub fn new(padding: u32) -> AtlasBuilder {
+        AtlasBuilder {
+            tiles: Vec::new(),
+            padding: padding,
+        }
+    }
+
+    pub fn add<S>(&mut self, name: S, image: RgbaImage)
+        where S: Into<String>
+    {
+        self.tiles.push((name.into(), image));
+    }
+
+    // Adds every png in `dir`, named by file stem. Files ending in `skip` are ignored.
+    pub fn add_dir<P>(&mut self, dir: P, skip: &str) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let mut paths = Vec::new();
+        for entry in try!(fs::read_dir(dir)) {
+            let path = try!(entry).path();
+            if path.extension().and_then(|e| e.to_str()) == Some("png") {
+                paths.push(path);
+            }
+        }
+        // Directory order is unspecified; sort so tile ids are stable between runs.
+        paths.sort();
+
+        for path in paths {
+            let name = try!(path.file_stem()
+                            .and_then(|s| s.to_str())
+                            .ok_or(Error::with_detail("atlas error",
+                                                      format!("bad tile name: {}",
+                                                              path.display()))));
+            if !skip.is_empty() && name.ends_with(skip) {
+                continue;
+            }
+            let image = try!(image::open(&path)).to_rgba();
+            self.add(name, image);
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Atlas> {
+        if self.tiles.is_empty() {
+            return Err(Error::with_detail("atlas error", "no tiles to pack"));
+        }
+        let pad = self.padding;
+
+        // Shelf packing, tallest tiles first.
+        let mut order: Vec<usize> = (0..self.tiles.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (ha, hb) = (self.tiles[a].1.height(), self.tiles[b].1.height());
+            match hb.cmp(&ha) {
+                Ordering::Equal => self.tiles[a].0.cmp(&self.tiles[b].0),
+                ord => ord,
+            }
+        });
+
+        let mut area = 0;
+        let mut widest = 0;
+        for &(_, ref img) in self.tiles.iter() {
+            let (w, h) = (img.width() + 2 * pad, img.height() + 2 * pad);
+            area += w * h;
+            if w > widest {
+                widest = w;
+            }
+        }
+        let mut width = next_pow2((area as f32).sqrt().ceil() as u32);
+        if width < widest {
+            width = next_pow2(widest);
+        }
+
+        let mut rects = vec![PixelRect { x: 0, y: 0, width: 0, height: 0 }; self.tiles.len()];
+        let (mut x, mut y, mut shelf) = (0, 0, 0);
+        for &i in order.iter() {
+            let (w, h) = self.tiles[i].1.dimensions();
+            if x + w + 2 * pad > width {
+                x = 0;
+                y += shelf;
+                shelf = 0;
+            }
+            rects[i] = PixelRect {
+                x: x + pad,
+                y: y + pad,
+                width: w,
+                height: h,
+            };
+            x += w + 2 * pad;
+            if h + 2 * pad > shelf {
+                shelf = h + 2 * pad;
+            }
+        }
+        let height = next_pow2(y + shelf);
+
+        let mut image = ImageBuffer::new(width, height);
+        for (i, &(_, ref tile)) in self.tiles.iter().enumerate() {
+            blit_padded(&mut image, tile, rects[i], pad);
+        }
+
+        let mut names = HashMap::new();
+        let mut tile_names = Vec::new();
+        for (i, (name, _)) in self.tiles.into_iter().enumerate() {
+            names.insert(name.clone(), i);
+            tile_names.push(name);
+        }
+
+        Ok(Atlas {
+            image: image,
+            padding: pad,
+            names: names,
+            tile_names: tile_names,
+            rects: rects,
+        })
+    }
+}
+
+pub struct Atlas {
+    image: RgbaImage,
+    padding: u32,
+    names: HashMap<String, TileId>,
+    tile_names: Vec<String>,
+    rects: Vec<PixelRect>,
+}
+
+impl Atlas {
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    pub fn tile(&self, name: &str) -> Option<TileId> {
+        self.names.get(name).cloned()
+    }
+
+    pub fn name(&self, tile: TileId) -> &str {
+        &self.tile_names[tile]
+    }
+
+    pub fn pixel_rect(&self, tile: TileId) -> PixelRect {
+        self.rects[tile]
+    }
+
+    // Texture rows are flipped on upload, so v runs bottom to top.
+    pub fn uv(&self, tile: TileId) -> UvRect {
+        let r = self.rects[tile];
+        let (w, h) = (self.image.width() as f32, self.image.height() as f32);
+        UvRect {
+            u0: r.x as f32 / w,
+            v0: 1.0 - (r.y + r.height) as f32 / h,
+            u1: (r.x + r.width) as f32 / w,
+            v1: 1.0 - r.y as f32 / h,
+        }
+    }
+
+    pub fn uv_by_name(&self, name: &str) -> Option<UvRect> {
+        self.tile(name).map(|t| self.uv(t))
+    }
+
+    // An image with the same layout, filled from `<name><suffix>.png` in `dir` for
+    // every tile that has one and with `fill` elsewhere. Used for specular maps.
+    // `overrides` maps tile names to the image to use instead.
+    pub fn companion<P>(&self,
+                        dir: P,
+                        suffix: &str,
+                        overrides: &HashMap<String, String>,
+                        fill: Rgba<u8>) -> Result<RgbaImage>
+        where P: AsRef<Path>
+    {
+        let (w, h) = self.image.dimensions();
+        let mut image = ImageBuffer::from_pixel(w, h, fill);
+        for (i, name) in self.tile_names.iter().enumerate() {
+            let path = match overrides.get(name) {
+                Some(file) => dir.as_ref().join(format!("{}.png", file)),
+                None => dir.as_ref().join(format!("{}{}.png", name, suffix)),
+            };
+            if !path.exists() {
+                continue;
+            }
+            let mut tile = try!(image::open(&path)).to_rgba();
+            let r = self.rects[i];
+            if tile.dimensions() != (r.width, r.height) {
+                tile = image::imageops::resize(&tile, r.width, r.height,
+                                               image::FilterType::Triangle);
+            }
+            blit_padded(&mut image, &tile, r, self.padding);
+        }
+        Ok(image)
+    }
+
+    pub fn to_texture<F>(&self, facade: &F) -> Result<SrgbTexture2d>
+        where F: Facade
+    {
+        to_texture(facade, &self.image)
+    }
+
+    // Writes `<name>.png` and a `<name>.toml` manifest of tile rectangles into `dir`.
+    pub fn save<P>(&self, dir: P, name: &str) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let dir = dir.as_ref();
+        try!(fs::create_dir_all(dir));
+        try!(self.image.save(dir.join(format!("{}.png", name))));
+
+        let mut manifest = String::new();
+        manifest.push_str(&format!("width = {}\nheight = {}\npadding = {}\n",
+                                   self.image.width(), self.image.height(), self.padding));
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by(|&a, &b| self.tile_names[a].cmp(&self.tile_names[b]));
+        for i in order {
+            let (r, uv) = (self.rects[i], self.uv(i));
+            manifest.push_str(&format!("\n[tiles.{}]\nid = {}\n", self.tile_names[i], i));
+            manifest.push_str(&format!("x = {}\ny = {}\nwidth = {}\nheight = {}\n",
+                                       r.x, r.y, r.width, r.height));
+            manifest.push_str(&format!("u0 = {}\nv0 = {}\nu1 = {}\nv1 = {}\n",
+                                       uv.u0, uv.v0, uv.u1, uv.v1));
+        }
+
+        let mut file = try!(File::create(dir.join(format!("{}.toml", name))));
+        try!(file.write_all(manifest.as_bytes()));
+        Ok(())
+    }
+}
+
+pub fn to_texture<F>(facade: &F, image: &RgbaImage) -> Result<SrgbTexture2d>
+    where F: Facade
+{
+    let texture = try!(SrgbTexture2d::new(facade, DynamicImage::ImageRgba8(image.clone())));
+    Ok(texture)
+}
+
+// Copies `tile` to `rect`, repeating its edge pixels `pad` pixels outwards.
+fn blit_padded(dst: &mut RgbaImage, tile: &RgbaImage, rect: PixelRect, pad: u32) {
+    let (w, h) = (rect.width as i64, rect.height as i64);
+    let pad = pad as i64;
+    for ty in -pad..h + pad {
+        for tx in -pad..w + pad {
+            let sx = clamp(tx, 0, w - 1) as u32;
+            let sy = clamp(ty, 0, h - 1) as u32;
+            let dx = (rect.x as i64 + tx) as u32;
+            let dy = (rect.y as i64 + ty) as u32;
+            dst.put_pixel(dx, dy, *tile.get_pixel(sx, sy));
+        }
+    }
+}
+
+fn clamp(v: i64, lo: i64, hi: i64) -> i64 {
+    if v < lo { lo } else if v > hi { hi } else { v }
+}
+
+fn next_pow2(v: u32) -> u32 {
+    let mut p = 1;
+    while p < v {
+        p <<= 1;
+    }
+    p
+}
@@ -1,4 +1,5 @@
 pub mod shader;
+pub mod atlas;
 
 use std::hash::Hash;
 
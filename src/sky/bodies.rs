@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+    BlendingFunction,
+    LinearBlendingFactor,
+};
+use glium::texture::SrgbTexture2d;
+use glium::uniforms::{
+    Uniforms,
+    UniformValue,
+    AsUniformValue,
+    Sampler,
+    MinifySamplerFilter,
+    MagnifySamplerFilter,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use gel::Vec3;
+use image;
+
+use config::DayParams;
+use errors::Result;
+use super::Clock;
+
+#[derive(Copy, Clone)]
+struct Corner {
+    corner: [f32; 2],
+}
+implement_vertex!(Corner, corner);
+
+// A square facing the camera, as two triangles.
+const QUAD: [Corner; 6] = [
+    Corner { corner: [-1.0, -1.0] },
+    Corner { corner: [ 1.0, -1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [-1.0,  1.0] },
+    Corner { corner: [-1.0, -1.0] },
+];
+
+// The sun and moon, drawn with the billboard program as squares always facing
+// the camera. They keep their distance from the eye, so they can't be walked
+// up to, and fade out as they set.
+pub struct Bodies {
+    vtxbuf: VertexBuffer<Corner>,
+    sun: SrgbTexture2d,
+    moon: SrgbTexture2d,
+    distance: f32,
+    size: f32,
+}
+
+impl Bodies {
+    pub fn new<F, P>(facade: &F, params: &DayParams, assets: P) -> Result<Bodies>
+        where F: Facade,
+              P: AsRef<Path>,
+    {
+        let vtxbuf = try!(VertexBuffer::new(facade, &QUAD));
+        let sun = try!(image::open(assets.as_ref().join(params.sun())));
+        let moon = try!(image::open(assets.as_ref().join(params.moon())));
+
+        Ok(Bodies {
+            vtxbuf: vtxbuf,
+            sun: try!(SrgbTexture2d::new(facade, sun)),
+            moon: try!(SrgbTexture2d::new(facade, moon)),
+            distance: params.distance(),
+            size: params.size(),
+        })
+    }
+
+    // Draws the sun and moon where `clock` puts them as seen from `eye`.
+    // `uniforms` supply the view and projection. Anything already drawn in
+    // front hides them, but they hide nothing drawn after.
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U,
+                      eye: Vec3,
+                      clock: &Clock) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: false,
+            blending_function: Some(BlendingFunction::Addition {
+                source: LinearBlendingFactor::SourceAlpha,
+                destination: LinearBlendingFactor::OneMinusSourceAlpha,
+            }),
+            ..Default::default()
+        };
+        let bodies = [(&self.sun, clock.sun()), (&self.moon, clock.moon())];
+        for &(texture, direction) in bodies.iter() {
+            // Gone a little way below the horizon.
+            let alpha = ((direction.y + 0.1) * 10.0).max(0.0).min(1.0);
+            if alpha == 0.0 {
+                continue;
+            }
+            let centre = eye + direction * self.distance;
+            let body_uniforms = BodyUniforms {
+                uniforms: uniforms,
+                centre: [centre.x, centre.y, centre.z],
+                size: self.size,
+                tint: [1.0, 1.0, 1.0, alpha],
+                sprite: texture.sampled()
+                    .minify_filter(MinifySamplerFilter::Linear)
+                    .magnify_filter(MagnifySamplerFilter::Linear),
+            };
+            try!(surface.draw(&self.vtxbuf,
+                              NoIndices(PrimitiveType::TrianglesList),
+                              program,
+                              &body_uniforms,
+                              draw_params));
+        }
+        Ok(())
+    }
+}
+
+struct BodyUniforms<'a, U: 'a> {
+    uniforms: &'a U,
+    centre: [f32; 3],
+    size: f32,
+    tint: [f32; 4],
+    sprite: Sampler<'a, SrgbTexture2d>,
+}
+
+impl<'a, U> Uniforms for BodyUniforms<'a, U>
+    where U: Uniforms
+{
+    fn visit_values<'b, F>(&'b self, mut output: F)
+        where F: FnMut(&str, UniformValue<'b>)
+    {
+        self.uniforms.visit_values(&mut output);
+        output("centre", self.centre.as_uniform_value());
+        output("size", self.size.as_uniform_value());
+        output("tint", self.tint.as_uniform_value());
+        output("sprite", self.sprite.as_uniform_value());
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+    BlendingFunction,
+    LinearBlendingFactor,
+};
+use glium::texture::SrgbTexture2d;
+use glium::uniforms::{
+    Uniforms,
+    UniformValue,
+    AsUniformValue,
+    Sampler,
+    SamplerWrapFunction,
+    MinifySamplerFilter,
+    MagnifySamplerFilter,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use gel::Vec3;
+use image;
+
+use config::SkyParams;
+use errors::Result;
+
+#[derive(Copy, Clone)]
+struct Corner {
+    corner: [f32; 2],
+}
+implement_vertex!(Corner, corner);
+
+const QUAD: [Corner; 6] = [
+    Corner { corner: [-1.0, -1.0] },
+    Corner { corner: [ 1.0, -1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [ 1.0,  1.0] },
+    Corner { corner: [-1.0,  1.0] },
+    Corner { corner: [-1.0, -1.0] },
+];
+
+// A flat layer of the cloud texture, tiled across the sky and drifting with
+// the wind, drawn with the clouds program.
+pub struct Clouds {
+    vtxbuf: VertexBuffer<Corner>,
+    texture: SrgbTexture2d,
+    height: f32,
+    tile: f32,
+    alpha: f32,
+    reach: f32,
+    // Blocks per second in x and z.
+    wind: (f32, f32),
+    // How far the clouds have drifted, within one tile.
+    offset: (f32, f32),
+}
+
+impl Clouds {
+    // The texture is found in `assets`.
+    pub fn new<F, P>(facade: &F, params: &SkyParams, assets: P) -> Result<Clouds>
+        where F: Facade,
+              P: AsRef<Path>,
+    {
+        let vtxbuf = try!(VertexBuffer::new(facade, &QUAD));
+        let img = try!(image::open(assets.as_ref().join(params.cloud_texture())));
+        let (s, c) = params.cloud_wind().to_radians().sin_cos();
+        let speed = params.cloud_speed();
+
+        Ok(Clouds {
+            vtxbuf: vtxbuf,
+            texture: try!(SrgbTexture2d::new(facade, img)),
+            height: params.cloud_height(),
+            tile: params.cloud_tile(),
+            alpha: params.cloud_alpha(),
+            reach: params.cloud_distance(),
+            wind: (s * speed, c * speed),
+            offset: (0.0, 0.0),
+        })
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        // Whole tiles look the same, so they are dropped to keep the offset
+        // small enough for floats to stay precise.
+        self.offset.0 = (self.offset.0 + self.wind.0 * dt) % self.tile;
+        self.offset.1 = (self.offset.1 + self.wind.1 * dt) % self.tile;
+    }
+
+    // Draws the layer over `eye`, lit by `tint`. `uniforms` supply the view
+    // and projection. The clouds are see-through, so they are best drawn after
+    // everything solid.
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U,
+                      eye: Vec3,
+                      tint: Vec3) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: false,
+            blending_function: Some(BlendingFunction::Addition {
+                source: LinearBlendingFactor::SourceAlpha,
+                destination: LinearBlendingFactor::OneMinusSourceAlpha,
+            }),
+            ..Default::default()
+        };
+        let cloud_uniforms = CloudUniforms {
+            uniforms: uniforms,
+            eye: [eye.x, eye.y, eye.z],
+            height: self.height,
+            reach: self.reach,
+            tile: self.tile,
+            offset: [self.offset.0, self.offset.1],
+            tint: [tint.x, tint.y, tint.z],
+            alpha: self.alpha,
+            clouds: self.texture.sampled()
+                .wrap_function(SamplerWrapFunction::Repeat)
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear),
+        };
+        try!(surface.draw(&self.vtxbuf,
+                          NoIndices(PrimitiveType::TrianglesList),
+                          program,
+                          &cloud_uniforms,
+                          draw_params));
+        Ok(())
+    }
+}
+
+struct CloudUniforms<'a, U: 'a> {
+    uniforms: &'a U,
+    eye: [f32; 3],
+    height: f32,
+    reach: f32,
+    tile: f32,
+    offset: [f32; 2],
+    tint: [f32; 3],
+    alpha: f32,
+    clouds: Sampler<'a, SrgbTexture2d>,
+}
+
+impl<'a, U> Uniforms for CloudUniforms<'a, U>
+    where U: Uniforms
+{
+    fn visit_values<'b, F>(&'b self, mut output: F)
+        where F: FnMut(&str, UniformValue<'b>)
+    {
+        self.uniforms.visit_values(&mut output);
+        output("eye", self.eye.as_uniform_value());
+        output("height", self.height.as_uniform_value());
+        output("reach", self.reach.as_uniform_value());
+        output("tile", self.tile.as_uniform_value());
+        output("offset", self.offset.as_uniform_value());
+        output("tint", self.tint.as_uniform_value());
+        output("alpha", self.alpha.as_uniform_value());
+        output("clouds", self.clouds.as_uniform_value());
+    }
+}
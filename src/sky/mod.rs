@@ -0,0 +1,196 @@
+pub mod bodies;
+pub mod skybox;
+pub mod clouds;
+
+use std::f32::consts::PI;
+
+use gel::Vec3;
+
+use config::{
+    DayParams,
+    SkyColors,
+};
+
+pub use self::bodies::Bodies;
+pub use self::skybox::Skybox;
+pub use self::clouds::Clouds;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Phase {
+    Dawn,
+    Noon,
+    Dusk,
+    Night,
+}
+
+// The colours of the day change between these times, as fractions of a day from
+// midnight, and hold between keys of the same phase.
+const KEYS: [(f32, Phase); 8] = [
+    (0.0, Phase::Night),
+    (0.2, Phase::Night),
+    (0.25, Phase::Dawn),
+    (0.35, Phase::Noon),
+    (0.65, Phase::Noon),
+    (0.75, Phase::Dusk),
+    (0.8, Phase::Night),
+    (1.0, Phase::Night),
+];
+
+fn colors(params: &DayParams, phase: Phase) -> &SkyColors {
+    match phase {
+        Phase::Dawn => params.dawn(),
+        Phase::Noon => params.noon(),
+        Phase::Dusk => params.dusk(),
+        Phase::Night => params.night(),
+    }
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    a + (b - a) * t
+}
+
+// The lighting and sky colour at some time of day.
+#[derive(Debug, Copy, Clone)]
+pub struct Sky {
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub clear: Vec3,
+}
+
+// Time of day, which turns the sun and moon about the world.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    // Seconds in a day.
+    length: f32,
+    // Fraction of a day since midnight.
+    time: f32,
+    // Radians the sun's path leans from overhead.
+    tilt: f32,
+}
+
+impl Clock {
+    pub fn new(params: &DayParams) -> Clock {
+        Clock {
+            length: params.length(),
+            time: params.start() % 1.0,
+            tilt: params.tilt().to_radians(),
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time = (self.time + dt / self.length) % 1.0;
+    }
+
+    // Fraction of a day since midnight: dawn at a quarter, noon at a half.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // Towards the sun, which rises in +x at dawn and is highest at noon.
+    pub fn sun(&self) -> Vec3 {
+        let angle = (self.time - 0.25) * 2.0 * PI;
+        let (s, c) = angle.sin_cos();
+        Vec3::new(c, s * self.tilt.cos(), s * self.tilt.sin())
+    }
+
+    // Towards the moon, always opposite the sun.
+    pub fn moon(&self) -> Vec3 {
+        let sun = self.sun();
+        Vec3::new(-sun.x, -sun.y, -sun.z)
+    }
+
+    // Towards whichever of the sun and moon is up.
+    pub fn light(&self) -> Vec3 {
+        let sun = self.sun();
+        if sun.y >= 0.0 { sun } else { self.moon() }
+    }
+
+    // How much of the day's light there is, from 0 at night to 1 once the sun
+    // is up.
+    pub fn daylight(&self) -> f32 {
+        (self.sun().y * 5.0 + 0.5).max(0.0).min(1.0)
+    }
+
+    // The colours for the time of day, moving between those of `params`'
+    // dawn, noon, dusk and night.
+    pub fn sky(&self, params: &DayParams) -> Sky {
+        let mut from = params.night();
+        let mut to = from;
+        let mut t = 0.0;
+        for pair in KEYS.windows(2) {
+            let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+            if self.time >= t0 && self.time <= t1 {
+                from = colors(params, a);
+                to = colors(params, b);
+                t = (self.time - t0) / (t1 - t0);
+                break;
+            }
+        }
+        Sky {
+            ambient: lerp(from.ambient(), to.ambient(), t),
+            diffuse: lerp(from.diffuse(), to.diffuse(), t),
+            clear: lerp(from.clear(), to.clear(), t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gel::Vec3;
+
+    use config::DayParams;
+    use super::Clock;
+
+    fn clock(time: f32) -> Clock {
+        Clock {
+            length: 1200.0,
+            time: time,
+            tilt: 20f32.to_radians(),
+        }
+    }
+
+    fn near(a: Vec3, b: Vec3) -> bool {
+        (a.x - b.x).abs() < 1e-5 && (a.y - b.y).abs() < 1e-5 && (a.z - b.z).abs() < 1e-5
+    }
+
+    #[test]
+    fn the_sun_goes_round() {
+        let (s, c) = 20f32.to_radians().sin_cos();
+        let cases = [
+            (0.0, Vec3::new(0.0, -c, -s), 0.0),
+            (0.25, Vec3::new(1.0, 0.0, 0.0), 0.5),
+            (0.5, Vec3::new(0.0, c, s), 1.0),
+            (0.75, Vec3::new(-1.0, 0.0, 0.0), 0.5),
+        ];
+        for &(time, sun, daylight) in cases.iter() {
+            let clock = clock(time);
+            assert!(near(clock.sun(), sun), "sun at {}", time);
+            assert!(near(clock.moon(), Vec3::new(-sun.x, -sun.y, -sun.z)), "moon at {}", time);
+            assert!((clock.daylight() - daylight).abs() < 1e-5, "daylight at {}", time);
+        }
+    }
+
+    #[test]
+    fn the_sky_follows_the_day() {
+        let params = DayParams::new();
+        let cases = [
+            (0.0, params.night()),
+            (0.1, params.night()),
+            (0.25, params.dawn()),
+            (0.5, params.noon()),
+            (0.75, params.dusk()),
+            (0.9, params.night()),
+        ];
+        for &(time, colors) in cases.iter() {
+            let sky = clock(time).sky(&params);
+            assert!(near(sky.ambient, colors.ambient()), "ambient at {}", time);
+            assert!(near(sky.diffuse, colors.diffuse()), "diffuse at {}", time);
+            assert!(near(sky.clear, colors.clear()), "clear at {}", time);
+        }
+
+        // Half way from dawn to noon.
+        let (dawn, noon) = (params.dawn(), params.noon());
+        let sky = clock(0.3).sky(&params);
+        assert!(near(sky.clear, (dawn.clear() + noon.clear()) * 0.5));
+    }
+}
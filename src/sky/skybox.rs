@@ -0,0 +1,205 @@
+use std::cmp::min;
+use std::path::Path;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+    BlitTarget,
+};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{
+    SrgbTexture2d,
+    SrgbCubemap,
+    CubeLayer,
+};
+use glium::uniforms::{
+    Uniforms,
+    UniformValue,
+    AsUniformValue,
+    Sampler,
+    SamplerWrapFunction,
+    MinifySamplerFilter,
+    MagnifySamplerFilter,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use gel::Vec3;
+use image::{
+    self,
+    DynamicImage,
+    FilterType,
+    GenericImage,
+};
+
+use config::SkyboxParams;
+use errors::Result;
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    position: [f32; 3],
+}
+implement_vertex!(Vertex, position);
+
+// The bottom left, bottom right, top right and top left corners of each face
+// as seen from inside the box, in the order of `SkyboxParams`: right, left,
+// top, bottom, front and back. Looking up, the top of the image is towards
+// the front, and looking down towards the back.
+const FACES: [[[f32; 3]; 4]; 6] = [
+    [[1.0, -1.0, -1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [1.0, 1.0, -1.0]],
+    [[-1.0, -1.0, 1.0], [-1.0, -1.0, -1.0], [-1.0, 1.0, -1.0], [-1.0, 1.0, 1.0]],
+    [[-1.0, 1.0, -1.0], [1.0, 1.0, -1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0]],
+    [[-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, -1.0, -1.0], [-1.0, -1.0, -1.0]],
+    [[1.0, -1.0, 1.0], [-1.0, -1.0, 1.0], [-1.0, 1.0, 1.0], [1.0, 1.0, 1.0]],
+    [[-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0]],
+];
+
+// The cube map layer each face goes in.
+const LAYERS: [CubeLayer; 6] = [
+    CubeLayer::PositiveX,
+    CubeLayer::NegativeX,
+    CubeLayer::PositiveY,
+    CubeLayer::NegativeY,
+    CubeLayer::PositiveZ,
+    CubeLayer::NegativeZ,
+];
+
+// Two triangles from the corners of a face.
+const TRIANGLES: [usize; 6] = [0, 1, 2, 2, 3, 0];
+
+// A box of six images around the eye, drawn behind everything else with the
+// skybox program. The images are one cube map, looked up by the direction
+// from the eye.
+pub struct Skybox {
+    vtxbuf: VertexBuffer<Vertex>,
+    cubemap: SrgbCubemap,
+}
+
+impl Skybox {
+    // The images are found in `assets`. They are cut down to the size of the
+    // smallest, as the faces of a cube map must be the same size and square.
+    pub fn new<F, P>(facade: &F, params: &SkyboxParams, assets: P) -> Result<Skybox>
+        where F: Facade,
+              P: AsRef<Path>,
+    {
+        let names = [
+            params.right(),
+            params.left(),
+            params.top(),
+            params.bottom(),
+            params.front(),
+            params.back(),
+        ];
+        let mut images = Vec::with_capacity(names.len());
+        for name in names.iter() {
+            images.push(try!(image::open(assets.as_ref().join(name))));
+        }
+        let size = images.iter()
+            .map(|img| {
+                let (width, height) = img.dimensions();
+                min(width, height)
+            })
+            .fold(::std::u32::MAX, min);
+
+        let cubemap = try!(SrgbCubemap::empty(facade, size));
+        let target = BlitTarget {
+            left: 0,
+            bottom: 0,
+            width: size as i32,
+            height: size as i32,
+        };
+        for (i, img) in images.into_iter().enumerate() {
+            // Uploading puts the bottom row of an image first, while cube maps
+            // want the top row first and the sides mirrored. Turning the sides
+            // half way round makes up for both; the top and bottom come out
+            // right as they are.
+            let img = square(img, size);
+            let img = if i == 2 || i == 3 { img } else { img.rotate180() };
+            let face = try!(SrgbTexture2d::new(facade, img));
+            let layer = try!(SimpleFrameBuffer::new(facade,
+                                                    cubemap.main_level().image(LAYERS[i])));
+            face.as_surface().blit_whole_color_to(&layer, &target, MagnifySamplerFilter::Linear);
+        }
+
+        let vertices: Vec<Vertex> = FACES.iter()
+            .flat_map(|corners| TRIANGLES.iter().map(move |&i| Vertex { position: corners[i] }))
+            .collect();
+
+        Ok(Skybox {
+            vtxbuf: try!(VertexBuffer::new(facade, &vertices)),
+            cubemap: cubemap,
+        })
+    }
+
+    // Draws the box, which should come before everything else. `uniforms`
+    // supply the view and projection. The images fade into `tint`, the clear
+    // colour, as `daylight` goes from 1 to 0.
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U,
+                      tint: Vec3,
+                      daylight: f32) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: false,
+            ..Default::default()
+        };
+        let sky_uniforms = SkyUniforms {
+            uniforms: uniforms,
+            tint: [tint.x, tint.y, tint.z],
+            daylight: daylight,
+            sky: self.cubemap.sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear),
+        };
+        try!(surface.draw(&self.vtxbuf,
+                          NoIndices(PrimitiveType::TrianglesList),
+                          program,
+                          &sky_uniforms,
+                          draw_params));
+        Ok(())
+    }
+}
+
+// `img` as `size` pixels square. A stray row or column past `size`, as some
+// of the images have, is cropped off; anything bigger is scaled down.
+fn square(mut img: DynamicImage, size: u32) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    if width - size <= 1 && height - size <= 1 {
+        img.crop(0, 0, size, size)
+    } else {
+        img.resize_exact(size, size, FilterType::Triangle)
+    }
+}
+
+struct SkyUniforms<'a, U: 'a> {
+    uniforms: &'a U,
+    tint: [f32; 3],
+    daylight: f32,
+    sky: Sampler<'a, SrgbCubemap>,
+}
+
+impl<'a, U> Uniforms for SkyUniforms<'a, U>
+    where U: Uniforms
+{
+    fn visit_values<'b, F>(&'b self, mut output: F)
+        where F: FnMut(&str, UniformValue<'b>)
+    {
+        self.uniforms.visit_values(&mut output);
+        output("tint", self.tint.as_uniform_value());
+        output("daylight", self.daylight.as_uniform_value());
+        output("sky", self.sky.as_uniform_value());
+    }
+}
@@ -0,0 +1,249 @@
+use std::cmp::Ordering;
+
+use glium::backend::Facade;
+use glium::vertex::{
+    VertexBuffer,
+};
+use glium::{
+    Surface,
+    Program,
+    DrawParameters,
+    DepthTest,
+    BlendingFunction,
+    LinearBlendingFactor,
+};
+use glium::uniforms::{
+    Uniforms,
+};
+use glium::index::{
+    NoIndices,
+    PrimitiveType,
+};
+use gel::Vec3;
+
+use cube::Vertex;
+use world::{
+    BlockId,
+    BlockPos,
+    ChunkPos,
+    AIR,
+};
+use errors::{
+    Result,
+};
+
+pub const CHUNK_WIDTH: usize = 16;
+pub const CHUNK_DEPTH: usize = 16;
+pub const CHUNK_HEIGHT: usize = 256;
+pub const CHUNK_VOLUME: usize = CHUNK_WIDTH * CHUNK_DEPTH * CHUNK_HEIGHT;
+
+#[derive(Clone)]
+pub struct Chunk {
+    position: ChunkPos,
+    blocks: Vec<BlockId>,
+    dirty: bool,
+}
+
+impl Chunk {
+    pub fn new(position: ChunkPos) -> Chunk {
+        Chunk {
+            position: position,
+            blocks: vec![AIR; CHUNK_VOLUME],
+            dirty: true,
+        }
+    }
+
+    // `blocks` must hold CHUNK_VOLUME ids in chunk index order.
+    pub fn from_blocks(position: ChunkPos, blocks: Vec<BlockId>) -> Chunk {
+        assert_eq!(blocks.len(), CHUNK_VOLUME);
+        Chunk {
+            position: position,
+            blocks: blocks,
+            dirty: true,
+        }
+    }
+
+    pub fn position(&self) -> ChunkPos {
+        self.position
+    }
+
+    // World coordinates of the chunk's (0, 0, 0) block.
+    pub fn origin(&self) -> BlockPos {
+        self.position.origin()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn set_dirty(&mut self, dirty: bool) {
+        self.dirty = dirty;
+    }
+
+    // Coordinates are chunk-local. Anything outside the chunk reads as air.
+    pub fn get(&self, x: i32, y: i32, z: i32) -> BlockId {
+        match index(x, y, z) {
+            Some(i) => self.blocks[i],
+            None => AIR,
+        }
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, z: i32, id: BlockId) {
+        if let Some(i) = index(x, y, z) {
+            if self.blocks[i] != id {
+                self.blocks[i] = id;
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn blocks(&self) -> &[BlockId] {
+        &self.blocks
+    }
+}
+
+fn index(x: i32, y: i32, z: i32) -> Option<usize> {
+    if x < 0 || y < 0 || z < 0 {
+        return None;
+    }
+    let (x, y, z) = (x as usize, y as usize, z as usize);
+    if x >= CHUNK_WIDTH || y >= CHUNK_HEIGHT || z >= CHUNK_DEPTH {
+        return None;
+    }
+    Some((y * CHUNK_DEPTH + z) * CHUNK_WIDTH + x)
+}
+
+pub struct ChunkMesh {
+    vtxbuf: VertexBuffer<Vertex>,
+    idxbuf: NoIndices,
+}
+
+impl ChunkMesh {
+    pub fn new<F>(facade: &F, vertices: &[Vertex]) -> Result<ChunkMesh>
+        where F: Facade
+    {
+        let vtxbuf = try!(VertexBuffer::new(facade, vertices));
+        let idxbuf = NoIndices(PrimitiveType::TrianglesList);
+
+        Ok(ChunkMesh {
+            vtxbuf: vtxbuf,
+            idxbuf: idxbuf,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.vtxbuf.len()
+    }
+
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: true,
+            ..Default::default()
+        };
+        try!(surface.draw(&self.vtxbuf, self.idxbuf, program, uniforms, draw_params));
+        Ok(())
+    }
+
+    // Alpha blended over what is already drawn, without hiding what is behind.
+    pub fn draw_blended<S, U>(&self,
+                              surface: &mut S,
+                              program: &Program,
+                              uniforms: &U) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let ref draw_params = DrawParameters {
+            depth_test: DepthTest::IfLessOrEqual,
+            depth_write: false,
+            blending_function: Some(BlendingFunction::Addition {
+                source: LinearBlendingFactor::SourceAlpha,
+                destination: LinearBlendingFactor::OneMinusSourceAlpha,
+            }),
+            ..Default::default()
+        };
+        try!(surface.draw(&self.vtxbuf, self.idxbuf, program, uniforms, draw_params));
+        Ok(())
+    }
+}
+
+// Translucent faces of a chunk, kept in memory so they can be drawn back to
+// front. Blending only comes out right when farther faces are drawn first.
+pub struct SortedMesh {
+    faces: Vec<[Vertex; 6]>,
+    // The block the eye was in when the faces were last sorted.
+    sorted_from: Option<BlockPos>,
+    mesh: Option<ChunkMesh>,
+}
+
+impl SortedMesh {
+    // `vertices` must hold two triangles per face, six vertices at a time, with
+    // the first and third vertices at opposite corners.
+    pub fn new(vertices: &[Vertex]) -> SortedMesh {
+        assert_eq!(vertices.len() % 6, 0);
+        let faces = vertices.chunks(6)
+            .map(|v| [v[0], v[1], v[2], v[3], v[4], v[5]])
+            .collect();
+        SortedMesh {
+            faces: faces,
+            sorted_from: None,
+            mesh: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    // Orders the faces farthest first as seen from `eye`. The vertex buffer is
+    // only rebuilt when the eye has moved into another block.
+    pub fn sort<F>(&mut self, facade: &F, eye: Vec3) -> Result<()>
+        where F: Facade
+    {
+        let cell = BlockPos::new(eye.x.floor() as i32,
+                                 eye.y.floor() as i32,
+                                 eye.z.floor() as i32);
+        if self.mesh.is_some() && self.sorted_from == Some(cell) {
+            return Ok(());
+        }
+
+        let distance = |face: &[Vertex; 6]| {
+            let (a, b) = (face[0].position(), face[2].position());
+            let dx = (a[0] + b[0]) * 0.5 - eye.x;
+            let dy = (a[1] + b[1]) * 0.5 - eye.y;
+            let dz = (a[2] + b[2]) * 0.5 - eye.z;
+            dx * dx + dy * dy + dz * dz
+        };
+        self.faces.sort_by(|a, b| {
+            distance(b).partial_cmp(&distance(a)).unwrap_or(Ordering::Equal)
+        });
+
+        let mut vertices = Vec::with_capacity(self.faces.len() * 6);
+        for face in self.faces.iter() {
+            vertices.extend(face.iter().cloned());
+        }
+        self.mesh = Some(try!(ChunkMesh::new(facade, &vertices)));
+        self.sorted_from = Some(cell);
+        Ok(())
+    }
+
+    // Draws the faces in the order of the last `sort`.
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        match self.mesh {
+            Some(ref mesh) => mesh.draw_blended(surface, program, uniforms),
+            None => Ok(()),
+        }
+    }
+}
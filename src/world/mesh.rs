@@ -0,0 +1,395 @@
+use cube::Vertex;
+use resource::atlas::Atlas;
+use world::shape::Connected;
+use world::{
+    BlockId,
+    BlockPos,
+    Blocks,
+    Face,
+    AIR,
+};
+
+pub type TileId = usize;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Shape {
+    // A full block.
+    Cube,
+    // A single upward facing quad just above the floor of the cell, like rails.
+    Flat,
+    // Joins up with its neighbours, like fences. See `shape::Connected`.
+    Connected,
+}
+
+// What the mesher needs to know about each kind of block.
+pub trait BlockTypes {
+    // Opaque blocks hide the faces of their neighbours.
+    fn is_opaque(&self, id: BlockId) -> bool;
+    fn tile(&self, id: BlockId, face: Face) -> TileId;
+
+    // Translucent blocks are meshed separately so they can be blended.
+    fn is_translucent(&self, _: BlockId) -> bool {
+        false
+    }
+
+    // Cutout blocks are meshed separately so their transparent texels can be
+    // discarded.
+    fn is_cutout(&self, _: BlockId) -> bool {
+        false
+    }
+
+    // Blocks of one group, like the levels of a fluid, hide faces between them.
+    fn group(&self, id: BlockId) -> BlockId {
+        id
+    }
+
+    fn shape(&self, _: BlockId) -> Shape {
+        Shape::Cube
+    }
+
+    // Quarter turns applied to the block's textures.
+    fn rotation(&self, _: BlockId) -> u8 {
+        0
+    }
+
+    // Specular exponent, when the block doesn't use the one drawn with.
+    fn shine(&self, _: BlockId) -> Option<f32> {
+        None
+    }
+
+    // Opacity of translucent blocks, on top of their texels'.
+    fn alpha(&self, _: BlockId) -> f32 {
+        1.0
+    }
+
+    // Geometry of blocks with the `Connected` shape.
+    fn connected(&self, _: BlockId) -> Option<&Connected> {
+        None
+    }
+}
+
+// Every non-air block is opaque and uses the same tile on all faces.
+#[derive(Debug, Copy, Clone)]
+pub struct Uniform(pub TileId);
+
+impl BlockTypes for Uniform {
+    fn is_opaque(&self, id: BlockId) -> bool {
+        id != AIR
+    }
+
+    fn tile(&self, _: BlockId, _: Face) -> TileId {
+        self.0
+    }
+}
+
+// An axis-aligned rectangle covering one or more coplanar block faces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Quad {
+    face: Face,
+    min: [i32; 3],
+    size: [i32; 3],
+    block: BlockId,
+    tile: TileId,
+    rotation: u8,
+    // Distance moved along the normal, in sixteenths of a block.
+    lift: i32,
+}
+
+impl Quad {
+    pub fn face(&self) -> Face {
+        self.face
+    }
+
+    // The block-space box whose `face` side this quad lies on.
+    pub fn min(&self) -> [i32; 3] {
+        self.min
+    }
+
+    pub fn size(&self) -> [i32; 3] {
+        self.size
+    }
+
+    pub fn block(&self) -> BlockId {
+        self.block
+    }
+
+    pub fn tile(&self) -> TileId {
+        self.tile
+    }
+
+    pub fn rotation(&self) -> u8 {
+        self.rotation
+    }
+
+    // Extent along the face's texture (u, v) axes, in blocks.
+    pub fn extent(&self) -> (i32, i32) {
+        match self.face {
+            Face::Left | Face::Right => (self.size[2], self.size[1]),
+            Face::Back | Face::Front => (self.size[0], self.size[1]),
+            Face::Bottom | Face::Top => (self.size[0], self.size[2]),
+        }
+    }
+
+    // Corners in counter-clockwise order seen from outside, starting at texture (0, 0).
+    pub fn corners(&self) -> [[f32; 3]; 4] {
+        let n = self.face.normal();
+        let l = self.lift as f32 / 16.0;
+        let (x0, y0, z0) = (self.min[0] as f32 + n[0] * l,
+                            self.min[1] as f32 + n[1] * l,
+                            self.min[2] as f32 + n[2] * l);
+        let (x1, y1, z1) = (x0 + self.size[0] as f32,
+                            y0 + self.size[1] as f32,
+                            z0 + self.size[2] as f32);
+        box_corners(self.face, [x0, y0, z0], [x1, y1, z1])
+    }
+
+    // Two triangles. Texture coordinates repeat once per block, so a merged quad
+    // needs a repeating sampler, or a tile repeated in the shader.
+    pub fn vertices(&self) -> [Vertex; 6] {
+        let (w, h) = self.extent();
+        // A quarter turn lays the texture's u axis along the quad's v edge.
+        let (w, h) = if self.rotation % 2 == 0 { (w, h) } else { (h, w) };
+        let (w, h) = (w as f32, h as f32);
+        let tex = [[0.0, 0.0], [w, 0.0], [w, h], [0.0, h]];
+        self.vertices_with(tex)
+    }
+
+    pub fn vertices_with(&self, tex: [[f32; 2]; 4]) -> [Vertex; 6] {
+        let c = self.corners();
+        let n = self.face.normal();
+        let r = self.rotation as usize;
+        let v = |i: usize| {
+            let t = tex[(i + r) % 4];
+            Vertex::new(c[i][0], c[i][1], c[i][2], n[0], n[1], n[2], t[0], t[1])
+        };
+        [v(0), v(1), v(2), v(2), v(3), v(0)]
+    }
+}
+
+// Corners of the `face` side of the box from `lo` to `hi`, in the order of
+// `Quad::corners`.
+pub fn box_corners(face: Face, lo: [f32; 3], hi: [f32; 3]) -> [[f32; 3]; 4] {
+    let (x0, y0, z0) = (lo[0], lo[1], lo[2]);
+    let (x1, y1, z1) = (hi[0], hi[1], hi[2]);
+    match face {
+        Face::Right => [[x1, y0, z1], [x1, y0, z0], [x1, y1, z0], [x1, y1, z1]],
+        Face::Left => [[x0, y0, z0], [x0, y0, z1], [x0, y1, z1], [x0, y1, z0]],
+        Face::Front => [[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]],
+        Face::Back => [[x1, y0, z0], [x0, y0, z0], [x0, y1, z0], [x1, y1, z0]],
+        Face::Top => [[x0, y1, z1], [x1, y1, z1], [x1, y1, z0], [x0, y1, z0]],
+        Face::Bottom => [[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]],
+    }
+}
+
+// Whether the `face` of block `id` can be seen past `neighbour`.
+pub fn is_face_visible<T>(types: &T, id: BlockId, neighbour: BlockId) -> bool
+    where T: BlockTypes
+{
+    if id == AIR {
+        false
+    } else if neighbour == AIR {
+        true
+    } else if types.is_opaque(neighbour) {
+        false
+    } else {
+        // Faces between two transparent blocks of the same kind are hidden.
+        types.group(neighbour) != types.group(id)
+    }
+}
+
+// Produces the visible faces of the box of blocks starting at `min` with `size`
+// blocks along each axis. Blocks just outside the box are consulted for culling
+// but never meshed. With `greedy` set, coplanar faces of the same block and tile
+// are merged into larger quads.
+pub fn mesh<B, T>(blocks: &B, types: &T, min: BlockPos, size: [i32; 3], greedy: bool) -> Vec<Quad>
+    where B: Blocks,
+          T: BlockTypes,
+{
+    let mut quads = Vec::new();
+    let origin = [min.x, min.y, min.z];
+
+    for &face in Face::all().iter() {
+        let d = face.axis();
+        let (a, b) = ((d + 1) % 3, (d + 2) % 3);
+        let dir = face.direction();
+        let (na, nb) = (size[a], size[b]);
+        let mut mask: Vec<Option<(BlockId, TileId)>> = vec![None; (na * nb) as usize];
+
+        for s in 0..size[d] {
+            for j in 0..nb {
+                for i in 0..na {
+                    let mut cell = origin;
+                    cell[d] += s;
+                    cell[a] += i;
+                    cell[b] += j;
+                    let pos = BlockPos::new(cell[0], cell[1], cell[2]);
+                    let id = blocks.block(pos);
+                    let neighbour = blocks.block(pos.offset(dir[0], dir[1], dir[2]));
+                    let cube = types.shape(id) == Shape::Cube;
+                    mask[(i + j * na) as usize] = if cube && is_face_visible(types, id, neighbour) {
+                        Some((id, types.tile(id, face)))
+                    } else {
+                        None
+                    };
+                }
+            }
+
+            for j in 0..nb {
+                let mut i = 0;
+                while i < na {
+                    let key = match mask[(i + j * na) as usize] {
+                        Some(key) => key,
+                        None => {
+                            i += 1;
+                            continue;
+                        },
+                    };
+
+                    let mut w = 1;
+                    let mut h = 1;
+                    if greedy {
+                        while i + w < na && mask[(i + w + j * na) as usize] == Some(key) {
+                            w += 1;
+                        }
+                        'grow: while j + h < nb {
+                            for k in 0..w {
+                                if mask[(i + k + (j + h) * na) as usize] != Some(key) {
+                                    break 'grow;
+                                }
+                            }
+                            h += 1;
+                        }
+                    }
+
+                    for dj in 0..h {
+                        for di in 0..w {
+                            mask[(i + di + (j + dj) * na) as usize] = None;
+                        }
+                    }
+
+                    let mut qmin = origin;
+                    qmin[d] += s;
+                    qmin[a] += i;
+                    qmin[b] += j;
+                    let mut qsize = [1; 3];
+                    qsize[a] = w;
+                    qsize[b] = h;
+
+                    quads.push(Quad {
+                        face: face,
+                        min: qmin,
+                        size: qsize,
+                        block: key.0,
+                        tile: key.1,
+                        rotation: types.rotation(key.0),
+                        lift: 0,
+                    });
+                    i += w;
+                }
+            }
+        }
+    }
+
+    for y in 0..size[1] {
+        for z in 0..size[2] {
+            for x in 0..size[0] {
+                let pos = BlockPos::new(origin[0] + x, origin[1] + y, origin[2] + z);
+                let id = blocks.block(pos);
+                if id == AIR || types.shape(id) != Shape::Flat {
+                    continue;
+                }
+                // The top face of the cell below, lifted clear of the floor.
+                quads.push(Quad {
+                    face: Face::Top,
+                    min: [pos.x, pos.y - 1, pos.z],
+                    size: [1, 1, 1],
+                    block: id,
+                    tile: types.tile(id, Face::Top),
+                    rotation: types.rotation(id),
+                    lift: 1,
+                });
+            }
+        }
+    }
+    quads
+}
+
+pub fn vertices(quads: &[Quad]) -> Vec<Vertex> {
+    let mut vertices = Vec::with_capacity(quads.len() * 6);
+    for quad in quads.iter() {
+        vertices.extend(quad.vertices().iter().cloned());
+    }
+    vertices
+}
+
+// Vertices carry their quad's atlas tile, which the shader repeats once per
+// block, so merged quads stay whole, and the block's shine and alpha.
+pub fn atlas_vertices<T>(quads: &[Quad], types: &T, atlas: &Atlas) -> Vec<Vertex>
+    where T: BlockTypes
+{
+    let mut vertices = Vec::with_capacity(quads.len() * 6);
+    for quad in quads.iter() {
+        let tile = atlas.uv(quad.tile()).rect();
+        let shine = types.shine(quad.block()).unwrap_or(0.0);
+        let alpha = types.alpha(quad.block());
+        vertices.extend(quad.vertices().iter()
+                        .map(|v| v.with_tile(tile).with_shine(shine).with_alpha(alpha)));
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use world::{
+        BlockId,
+        BlockPos,
+        BlocksMut,
+        Grid,
+    };
+    use super::{
+        mesh,
+        Uniform,
+    };
+
+    const STONE: BlockId = 1;
+
+    fn triangles(grid: &Grid, greedy: bool) -> usize {
+        mesh(grid, &Uniform(0), BlockPos::new(0, 0, 0), grid.size(), greedy).len() * 2
+    }
+
+    #[test]
+    fn single_block() {
+        let mut grid = Grid::new(1, 1, 1);
+        grid.set_block(BlockPos::new(0, 0, 0), STONE);
+        assert_eq!(triangles(&grid, false), 12);
+        assert_eq!(triangles(&grid, true), 12);
+    }
+
+    #[test]
+    fn adjacent_blocks() {
+        let mut grid = Grid::new(2, 1, 1);
+        grid.set_block(BlockPos::new(0, 0, 0), STONE);
+        grid.set_block(BlockPos::new(1, 0, 0), STONE);
+        assert_eq!(triangles(&grid, false), 20);
+        assert_eq!(triangles(&grid, true), 12);
+    }
+
+    #[test]
+    fn hollow_shell() {
+        let mut grid = Grid::new(3, 3, 3);
+        for y in 0..3 {
+            for z in 0..3 {
+                for x in 0..3 {
+                    if (x, y, z) != (1, 1, 1) {
+                        grid.set_block(BlockPos::new(x, y, z), STONE);
+                    }
+                }
+            }
+        }
+        // Nine faces on each outer side, and one facing the hollow from each
+        // side of it.
+        assert_eq!(triangles(&grid, false), (6 * 9 + 6) * 2);
+        // Each outer side merges into one quad; the inner faces can't merge.
+        assert_eq!(triangles(&grid, true), (6 + 6) * 2);
+    }
+}
@@ -0,0 +1,486 @@
+pub mod chunk;
+pub mod mesh;
+pub mod region;
+pub mod ray;
+pub mod tick;
+pub mod shape;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use glium::backend::Facade;
+use glium::{
+    Surface,
+    Program,
+};
+use glium::uniforms::{
+    Uniforms,
+    UniformValue,
+};
+use gel::Vec3;
+
+use errors::{
+    Result,
+};
+use resource::atlas::Atlas;
+use cube::Vertex;
+use self::mesh::BlockTypes;
+
+pub use self::chunk::{
+    Chunk,
+    ChunkMesh,
+    SortedMesh,
+    CHUNK_WIDTH,
+    CHUNK_DEPTH,
+    CHUNK_HEIGHT,
+};
+
+pub type BlockId = u16;
+
+pub const AIR: BlockId = 0;
+
+// Read access to blocks by world position.
+pub trait Blocks {
+    fn block(&self, pos: BlockPos) -> BlockId;
+
+    // Whether `pos` holds real blocks, rather than reading as air because it
+    // is out of range.
+    fn contains(&self, pos: BlockPos) -> bool;
+}
+
+pub trait BlocksMut: Blocks {
+    fn set_block(&mut self, pos: BlockPos, id: BlockId);
+}
+
+// Brightest light level.
+pub const MAX_LIGHT: u8 = 15;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Face {
+    Left,
+    Right,
+    Bottom,
+    Top,
+    Back,
+    Front,
+}
+
+impl Face {
+    pub fn all() -> [Face; 6] {
+        [Face::Left, Face::Right, Face::Bottom, Face::Top, Face::Back, Face::Front]
+    }
+
+    // 0, 1 or 2 for the x, y or z axis.
+    pub fn axis(&self) -> usize {
+        match *self {
+            Face::Left | Face::Right => 0,
+            Face::Bottom | Face::Top => 1,
+            Face::Back | Face::Front => 2,
+        }
+    }
+
+    pub fn direction(&self) -> [i32; 3] {
+        match *self {
+            Face::Left => [-1, 0, 0],
+            Face::Right => [1, 0, 0],
+            Face::Bottom => [0, -1, 0],
+            Face::Top => [0, 1, 0],
+            Face::Back => [0, 0, -1],
+            Face::Front => [0, 0, 1],
+        }
+    }
+
+    pub fn normal(&self) -> [f32; 3] {
+        let d = self.direction();
+        [d[0] as f32, d[1] as f32, d[2] as f32]
+    }
+
+    pub fn opposite(&self) -> Face {
+        match *self {
+            Face::Left => Face::Right,
+            Face::Right => Face::Left,
+            Face::Bottom => Face::Top,
+            Face::Top => Face::Bottom,
+            Face::Back => Face::Front,
+            Face::Front => Face::Back,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> BlockPos {
+        BlockPos {
+            x: x,
+            y: y,
+            z: z,
+        }
+    }
+
+    pub fn offset(&self, dx: i32, dy: i32, dz: i32) -> BlockPos {
+        BlockPos::new(self.x + dx, self.y + dy, self.z + dz)
+    }
+
+    pub fn neighbour(&self, face: Face) -> BlockPos {
+        let d = face.direction();
+        self.offset(d[0], d[1], d[2])
+    }
+
+    pub fn chunk(&self) -> ChunkPos {
+        ChunkPos::new(div_floor(self.x, CHUNK_WIDTH as i32),
+                      div_floor(self.z, CHUNK_DEPTH as i32))
+    }
+
+    // Position relative to the origin of the containing chunk.
+    pub fn local(&self) -> (i32, i32, i32) {
+        (mod_floor(self.x, CHUNK_WIDTH as i32),
+         self.y,
+         mod_floor(self.z, CHUNK_DEPTH as i32))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> ChunkPos {
+        ChunkPos {
+            x: x,
+            z: z,
+        }
+    }
+
+    pub fn origin(&self) -> BlockPos {
+        BlockPos::new(self.x * CHUNK_WIDTH as i32, 0, self.z * CHUNK_DEPTH as i32)
+    }
+}
+
+// A dense box of blocks, for working on block data away from the world.
+#[derive(Debug, Clone)]
+pub struct Grid {
+    size: [i32; 3],
+    blocks: Vec<BlockId>,
+}
+
+impl Grid {
+    pub fn new(width: i32, height: i32, depth: i32) -> Grid {
+        Grid {
+            size: [width, height, depth],
+            blocks: vec![AIR; (width * height * depth) as usize],
+        }
+    }
+
+    pub fn size(&self) -> [i32; 3] {
+        self.size
+    }
+
+    fn index(&self, pos: BlockPos) -> Option<usize> {
+        let (w, h, d) = (self.size[0], self.size[1], self.size[2]);
+        if pos.x < 0 || pos.y < 0 || pos.z < 0 || pos.x >= w || pos.y >= h || pos.z >= d {
+            None
+        } else {
+            Some(((pos.y * d + pos.z) * w + pos.x) as usize)
+        }
+    }
+}
+
+// Positions outside the grid read as air and ignore writes.
+impl Blocks for Grid {
+    fn block(&self, pos: BlockPos) -> BlockId {
+        match self.index(pos) {
+            Some(i) => self.blocks[i],
+            None => AIR,
+        }
+    }
+
+    fn contains(&self, pos: BlockPos) -> bool {
+        self.index(pos).is_some()
+    }
+}
+
+impl BlocksMut for Grid {
+    fn set_block(&mut self, pos: BlockPos, id: BlockId) {
+        if let Some(i) = self.index(pos) {
+            self.blocks[i] = id;
+        }
+    }
+}
+
+pub struct World {
+    chunks: HashMap<ChunkPos, Chunk>,
+    meshes: HashMap<ChunkPos, ChunkMesh>,
+    cutout: HashMap<ChunkPos, ChunkMesh>,
+    translucent: HashMap<ChunkPos, SortedMesh>,
+    greedy: bool,
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            chunks: HashMap::new(),
+            meshes: HashMap::new(),
+            cutout: HashMap::new(),
+            translucent: HashMap::new(),
+            greedy: false,
+        }
+    }
+
+    // Whether chunk meshes merge coplanar faces. Takes effect as chunks are
+    // next meshed.
+    pub fn set_greedy(&mut self, greedy: bool) {
+        self.greedy = greedy;
+    }
+
+    pub fn insert(&mut self, chunk: Chunk) -> Option<Chunk> {
+        self.meshes.remove(&chunk.position());
+        self.cutout.remove(&chunk.position());
+        self.translucent.remove(&chunk.position());
+        self.chunks.insert(chunk.position(), chunk)
+    }
+
+    pub fn remove(&mut self, pos: ChunkPos) -> Option<Chunk> {
+        self.meshes.remove(&pos);
+        self.cutout.remove(&pos);
+        self.translucent.remove(&pos);
+        self.chunks.remove(&pos)
+    }
+
+    pub fn chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(&pos)
+    }
+
+    pub fn chunk_mut(&mut self, pos: ChunkPos) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&pos)
+    }
+
+    pub fn chunks(&self) -> &HashMap<ChunkPos, Chunk> {
+        &self.chunks
+    }
+
+    fn mark_dirty(&mut self, pos: ChunkPos) {
+        if let Some(chunk) = self.chunks.get_mut(&pos) {
+            chunk.set_dirty(true);
+        }
+    }
+
+    // Rebuilds the vertex buffers of every chunk modified since the last call.
+    pub fn update_meshes<F, T>(&mut self, facade: &F, types: &T, atlas: &Atlas) -> Result<()>
+        where F: Facade,
+              T: BlockTypes,
+    {
+        let dirty: Vec<ChunkPos> = self.chunks.values()
+            .filter(|c| c.is_dirty())
+            .map(|c| c.position())
+            .collect();
+
+        for pos in dirty {
+            let size = [CHUNK_WIDTH as i32, CHUNK_HEIGHT as i32, CHUNK_DEPTH as i32];
+            // Opaque, cutout and translucent faces are drawn in separate passes.
+            let pass = |id: BlockId| {
+                if types.is_translucent(id) {
+                    2
+                } else if types.is_cutout(id) {
+                    1
+                } else {
+                    0
+                }
+            };
+            let mut quads = [Vec::new(), Vec::new(), Vec::new()];
+            for quad in mesh::mesh(self, types, pos.origin(), size, self.greedy) {
+                quads[pass(quad.block())].push(quad);
+            }
+            let mut passes = [mesh::atlas_vertices(&quads[0], types, atlas),
+                              mesh::atlas_vertices(&quads[1], types, atlas),
+                              mesh::atlas_vertices(&quads[2], types, atlas)];
+            for piece in shape::pieces(self, types, pos.origin(), size) {
+                passes[pass(piece.block)].extend(piece.vertices(types, atlas));
+            }
+            try!(build_mesh(&mut self.meshes, facade, pos, &passes[0]));
+            try!(build_mesh(&mut self.cutout, facade, pos, &passes[1]));
+            let sorted = SortedMesh::new(&passes[2]);
+            if sorted.is_empty() {
+                self.translucent.remove(&pos);
+            } else {
+                self.translucent.insert(pos, sorted);
+            }
+            if let Some(chunk) = self.chunks.get_mut(&pos) {
+                chunk.set_dirty(false);
+            }
+        }
+        Ok(())
+    }
+
+    // Draws opaque and cutout blocks. Texels of cutout blocks less opaque than
+    // `cutoff` are discarded. The program is given a `cutoff` uniform on top
+    // of `uniforms`.
+    pub fn draw<S, U>(&self,
+                      surface: &mut S,
+                      program: &Program,
+                      uniforms: &U,
+                      cutoff: f32) -> Result<()>
+        where S: Surface,
+              U: Uniforms,
+    {
+        let opaque = Pass { uniforms: uniforms, cutoff: 0.0 };
+        for mesh in self.meshes.values() {
+            try!(mesh.draw(surface, program, &opaque));
+        }
+        let cutout = Pass { uniforms: uniforms, cutoff: cutoff };
+        for mesh in self.cutout.values() {
+            try!(mesh.draw(surface, program, &cutout));
+        }
+        Ok(())
+    }
+
+    // Draws translucent blocks back to front as seen from `eye`. Call after
+    // everything else has been drawn.
+    pub fn draw_translucent<F, S, U>(&mut self,
+                                     facade: &F,
+                                     surface: &mut S,
+                                     program: &Program,
+                                     uniforms: &U,
+                                     eye: Vec3) -> Result<()>
+        where F: Facade,
+              S: Surface,
+              U: Uniforms,
+    {
+        let distance = |pos: &ChunkPos| {
+            let o = pos.origin();
+            let dx = o.x as f32 + CHUNK_WIDTH as f32 * 0.5 - eye.x;
+            let dz = o.z as f32 + CHUNK_DEPTH as f32 * 0.5 - eye.z;
+            dx * dx + dz * dz
+        };
+        let mut order: Vec<ChunkPos> = self.translucent.keys().cloned().collect();
+        order.sort_by(|a, b| distance(b).partial_cmp(&distance(a)).unwrap_or(Ordering::Equal));
+
+        let uniforms = Pass { uniforms: uniforms, cutoff: 0.0 };
+        for pos in order {
+            if let Some(mesh) = self.translucent.get_mut(&pos) {
+                try!(mesh.sort(facade, eye));
+                try!(mesh.draw(surface, program, &uniforms));
+            }
+        }
+        Ok(())
+    }
+}
+
+// The caller's uniforms plus the alpha test threshold of one pass.
+struct Pass<'a, U: 'a> {
+    uniforms: &'a U,
+    cutoff: f32,
+}
+
+impl<'a, U> Uniforms for Pass<'a, U>
+    where U: Uniforms
+{
+    fn visit_values<'b, F>(&'b self, mut output: F)
+        where F: FnMut(&str, UniformValue<'b>)
+    {
+        self.uniforms.visit_values(&mut output);
+        output("cutoff", UniformValue::Float(self.cutoff));
+    }
+}
+
+fn build_mesh<F>(meshes: &mut HashMap<ChunkPos, ChunkMesh>,
+                 facade: &F,
+                 pos: ChunkPos,
+                 vertices: &[Vertex]) -> Result<()>
+    where F: Facade
+{
+    if vertices.is_empty() {
+        meshes.remove(&pos);
+    } else {
+        meshes.insert(pos, try!(ChunkMesh::new(facade, vertices)));
+    }
+    Ok(())
+}
+
+// Blocks in chunks that are not loaded read as air.
+impl Blocks for World {
+    fn block(&self, pos: BlockPos) -> BlockId {
+        let (x, y, z) = pos.local();
+        match self.chunks.get(&pos.chunk()) {
+            Some(chunk) => chunk.get(x, y, z),
+            None => AIR,
+        }
+    }
+
+    fn contains(&self, pos: BlockPos) -> bool {
+        pos.y >= 0 && pos.y < CHUNK_HEIGHT as i32 && self.chunks.contains_key(&pos.chunk())
+    }
+}
+
+// Writes to chunks that are not loaded are dropped, as an empty chunk made
+// for them would be saved over the terrain generated there later. Neighbouring
+// chunks are remeshed when an edge block changes, since their culled faces may
+// change.
+impl BlocksMut for World {
+    fn set_block(&mut self, pos: BlockPos, id: BlockId) {
+        let (x, y, z) = pos.local();
+        let chunk_pos = pos.chunk();
+        match self.chunks.get_mut(&chunk_pos) {
+            Some(chunk) => chunk.set(x, y, z, id),
+            None => return,
+        }
+
+        if x == 0 {
+            self.mark_dirty(ChunkPos::new(chunk_pos.x - 1, chunk_pos.z));
+        } else if x == CHUNK_WIDTH as i32 - 1 {
+            self.mark_dirty(ChunkPos::new(chunk_pos.x + 1, chunk_pos.z));
+        }
+        if z == 0 {
+            self.mark_dirty(ChunkPos::new(chunk_pos.x, chunk_pos.z - 1));
+        } else if z == CHUNK_DEPTH as i32 - 1 {
+            self.mark_dirty(ChunkPos::new(chunk_pos.x, chunk_pos.z + 1));
+        }
+    }
+}
+
+fn div_floor(a: i32, b: i32) -> i32 {
+    let d = a / b;
+    if (a % b != 0) && ((a < 0) != (b < 0)) { d - 1 } else { d }
+}
+
+fn mod_floor(a: i32, b: i32) -> i32 {
+    let r = a % b;
+    if r != 0 && ((r < 0) != (b < 0)) { r + b } else { r }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Blocks,
+        BlocksMut,
+        BlockPos,
+        Chunk,
+        ChunkPos,
+        World,
+        AIR,
+    };
+
+    #[test]
+    fn writes_to_unloaded_chunks_are_dropped() {
+        let mut world = World::new();
+        world.insert(Chunk::new(ChunkPos::new(0, 0)));
+
+        let inside = BlockPos::new(3, 10, 3);
+        world.set_block(inside, 1);
+        assert_eq!(world.block(inside), 1);
+
+        let outside = BlockPos::new(-1, 10, 3);
+        assert!(!world.contains(outside));
+        world.set_block(outside, 1);
+        assert_eq!(world.block(outside), AIR);
+        assert!(world.chunk(outside.chunk()).is_none());
+        assert_eq!(world.chunks().len(), 1);
+    }
+}
@@ -0,0 +1,90 @@
+use gel::Vec3;
+
+use world::{
+    Blocks,
+    BlockId,
+    BlockPos,
+    Face,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Hit {
+    // The block that was hit.
+    pub block: BlockPos,
+    // The face the ray entered through. Its neighbour is where a block would be placed.
+    pub face: Face,
+    // Distance along the ray to the face.
+    pub distance: f32,
+}
+
+impl Hit {
+    pub fn normal(&self) -> [f32; 3] {
+        self.face.normal()
+    }
+}
+
+// Walks the grid cells along a ray in the order it crosses them (Amanatides and
+// Woo) and returns the first cell for which `solid` holds, up to `max` units
+// away. The cell containing `origin` is never reported, since the ray enters it
+// through no face. `direction` need not be normalised.
+pub fn cast<B, F>(blocks: &B, origin: Vec3, direction: Vec3, max: f32, solid: F) -> Option<Hit>
+    where B: Blocks,
+          F: Fn(BlockId) -> bool
+{
+    let o = [origin.x, origin.y, origin.z];
+    let len = (direction.x * direction.x + direction.y * direction.y +
+               direction.z * direction.z).sqrt();
+    if len == 0.0 {
+        return None;
+    }
+    let d = [direction.x / len, direction.y / len, direction.z / len];
+
+    let mut cell = [o[0].floor() as i32, o[1].floor() as i32, o[2].floor() as i32];
+    let mut step = [0; 3];
+    // Distance along the ray to the next cell boundary on each axis, and between
+    // successive boundaries.
+    let mut next = [::std::f32::INFINITY; 3];
+    let mut delta = [::std::f32::INFINITY; 3];
+    for axis in 0..3 {
+        if d[axis] > 0.0 {
+            step[axis] = 1;
+            delta[axis] = 1.0 / d[axis];
+            next[axis] = (cell[axis] as f32 + 1.0 - o[axis]) * delta[axis];
+        } else if d[axis] < 0.0 {
+            step[axis] = -1;
+            delta[axis] = -1.0 / d[axis];
+            next[axis] = (o[axis] - cell[axis] as f32) * delta[axis];
+        }
+    }
+
+    loop {
+        let axis = if next[0] < next[1] {
+            if next[0] < next[2] { 0 } else { 2 }
+        } else {
+            if next[1] < next[2] { 1 } else { 2 }
+        };
+        let distance = next[axis];
+        if distance > max {
+            return None;
+        }
+        cell[axis] += step[axis];
+        next[axis] += delta[axis];
+
+        let pos = BlockPos::new(cell[0], cell[1], cell[2]);
+        if solid(blocks.block(pos)) {
+            let face = match (axis, step[axis] > 0) {
+                (0, true) => Face::Left,
+                (0, false) => Face::Right,
+                (1, true) => Face::Bottom,
+                (1, false) => Face::Top,
+                (_, true) => Face::Back,
+                (_, false) => Face::Front,
+            };
+            return Some(Hit {
+                block: pos,
+                face: face,
+                distance: distance,
+            });
+        }
+    }
+}
@@ -0,0 +1,452 @@
+use std::path::{
+    PathBuf,
+    Path,
+};
+use std::io::prelude::*;
+use std::fs::{
+    self,
+    File,
+};
+use std::collections::hash_map::{
+    HashMap,
+};
+
+use world::{
+    BlockId,
+    Chunk,
+    ChunkPos,
+    World,
+    div_floor,
+};
+use world::chunk::CHUNK_VOLUME;
+use errors::{
+    Result,
+    Error,
+};
+
+// Region files hold the chunks of a REGION_SIZE x REGION_SIZE square.
+//
+// Layout, all integers little endian:
+//
+//     magic    4 bytes  "CUBR"
+//     version  u16
+//     reserved u16
+//     table    REGION_SIZE^2 entries of (offset u32, length u32), indexed by
+//              local z * REGION_SIZE + local x. A zero length means absent.
+//     sections each one compression byte followed by the encoded blocks.
+//
+// Every section decodes to exactly CHUNK_VOLUME block ids.
+pub const REGION_SIZE: i32 = 32;
+pub const VERSION: u16 = 1;
+
+const MAGIC: &'static [u8] = b"CUBR";
+const HEADER_LEN: usize = 8 + 8 * (REGION_SIZE * REGION_SIZE) as usize;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    // Block ids as u16.
+    None = 0,
+    // Runs of (count u16, id u16).
+    RunLength = 1,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RegionPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl RegionPos {
+    pub fn of(chunk: ChunkPos) -> RegionPos {
+        RegionPos {
+            x: div_floor(chunk.x, REGION_SIZE),
+            z: div_floor(chunk.z, REGION_SIZE),
+        }
+    }
+
+    fn slot(&self, chunk: ChunkPos) -> usize {
+        let lx = chunk.x - self.x * REGION_SIZE;
+        let lz = chunk.z - self.z * REGION_SIZE;
+        (lz * REGION_SIZE + lx) as usize
+    }
+
+    fn file_name(&self) -> String {
+        format!("r.{}.{}.region", self.x, self.z)
+    }
+}
+
+// The encoded sections of one region file.
+pub struct Region {
+    position: RegionPos,
+    sections: HashMap<usize, Vec<u8>>,
+}
+
+impl Region {
+    pub fn new(position: RegionPos) -> Region {
+        Region {
+            position: position,
+            sections: HashMap::new(),
+        }
+    }
+
+    pub fn open<P>(path: P, position: RegionPos) -> Result<Region>
+        where P: AsRef<Path>
+    {
+        let mut file = try!(File::open(path));
+        let mut buf = Vec::new();
+        try!(file.read_to_end(&mut buf));
+        Region::decode(&buf, position)
+    }
+
+    pub fn decode(buf: &[u8], position: RegionPos) -> Result<Region> {
+        if buf.len() < HEADER_LEN || &buf[..4] != MAGIC {
+            return Err(Error::with_detail("region error", "not a region file"));
+        }
+        let version = read_u16(buf, 4);
+        if version != VERSION {
+            return Err(Error::with_detail("region error",
+                                          format!("unsupported version {}", version)));
+        }
+
+        let mut region = Region::new(position);
+        for slot in 0..(REGION_SIZE * REGION_SIZE) as usize {
+            let offset = read_u32(buf, 8 + slot * 8) as usize;
+            let length = read_u32(buf, 12 + slot * 8) as usize;
+            if length == 0 {
+                continue;
+            }
+            if offset < HEADER_LEN || offset + length > buf.len() {
+                return Err(Error::with_detail("region error",
+                                              format!("section {} out of bounds", slot)));
+            }
+            region.sections.insert(slot, buf[offset..offset + length].to_vec());
+        }
+        Ok(region)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_LEN);
+        buf.extend(MAGIC.iter().cloned());
+        write_u16(&mut buf, VERSION);
+        write_u16(&mut buf, 0);
+
+        let mut slots: Vec<&usize> = self.sections.keys().collect();
+        slots.sort();
+        let mut table = vec![(0, 0); (REGION_SIZE * REGION_SIZE) as usize];
+        let mut offset = HEADER_LEN;
+        for &slot in slots.iter() {
+            let len = self.sections[slot].len();
+            table[*slot] = (offset as u32, len as u32);
+            offset += len;
+        }
+        for &(offset, len) in table.iter() {
+            write_u32(&mut buf, offset);
+            write_u32(&mut buf, len);
+        }
+        for &slot in slots.iter() {
+            buf.extend(self.sections[slot].iter().cloned());
+        }
+        buf
+    }
+
+    // Writes to a temporary file first so a crash never leaves a torn region.
+    pub fn save<P>(&self, path: P) -> Result<()>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(&self.encode()));
+        }
+        try!(fs::rename(&tmp, path));
+        Ok(())
+    }
+
+    pub fn contains(&self, pos: ChunkPos) -> bool {
+        self.sections.contains_key(&self.position.slot(pos))
+    }
+
+    pub fn put(&mut self, chunk: &Chunk) {
+        let slot = self.position.slot(chunk.position());
+        self.sections.insert(slot, encode_section(chunk.blocks()));
+    }
+
+    pub fn get(&self, pos: ChunkPos) -> Result<Option<Chunk>> {
+        match self.sections.get(&self.position.slot(pos)) {
+            Some(section) => {
+                let blocks = try!(decode_section(section));
+                Ok(Some(Chunk::from_blocks(pos, blocks)))
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+// A directory of region files. Regions are read once and kept, so loading
+// the chunks of a region doesn't go back to the file for each one. The store
+// expects to be the only writer of its directory.
+pub struct RegionStore {
+    dir: PathBuf,
+    regions: HashMap<RegionPos, Region>,
+}
+
+impl RegionStore {
+    pub fn new<P>(dir: P) -> Result<RegionStore>
+        where P: AsRef<Path>
+    {
+        try!(fs::create_dir_all(dir.as_ref()));
+        Ok(RegionStore {
+            dir: dir.as_ref().to_path_buf(),
+            regions: HashMap::new(),
+        })
+    }
+
+    fn path(&self, pos: RegionPos) -> PathBuf {
+        self.dir.join(pos.file_name())
+    }
+
+    // The region at `pos`, read from its file the first time it is asked for.
+    fn region(&mut self, pos: RegionPos) -> Result<&mut Region> {
+        if !self.regions.contains_key(&pos) {
+            let path = self.path(pos);
+            let region = if path.exists() {
+                try!(Region::open(path, pos))
+            } else {
+                Region::new(pos)
+            };
+            self.regions.insert(pos, region);
+        }
+        Ok(self.regions.get_mut(&pos).unwrap())
+    }
+
+    pub fn load_chunk(&mut self, pos: ChunkPos) -> Result<Option<Chunk>> {
+        let region = try!(self.region(RegionPos::of(pos)));
+        region.get(pos)
+    }
+
+    pub fn save_chunks<'a, I>(&mut self, chunks: I) -> Result<()>
+        where I: Iterator<Item=&'a Chunk>
+    {
+        let mut touched = Vec::new();
+        for chunk in chunks {
+            let pos = RegionPos::of(chunk.position());
+            try!(self.region(pos)).put(chunk);
+            if !touched.contains(&pos) {
+                touched.push(pos);
+            }
+        }
+        for pos in touched {
+            try!(self.regions[&pos].save(self.path(pos)));
+        }
+        Ok(())
+    }
+
+    pub fn save_world(&mut self, world: &World) -> Result<()> {
+        self.save_chunks(world.chunks().values())
+    }
+}
+
+pub fn encode_section(blocks: &[BlockId]) -> Vec<u8> {
+    let mut rle = vec![Compression::RunLength as u8];
+    let mut i = 0;
+    while i < blocks.len() {
+        let id = blocks[i];
+        let mut run = 1;
+        while i + run < blocks.len() && blocks[i + run] == id && run < 0xffff {
+            run += 1;
+        }
+        write_u16(&mut rle, run as u16);
+        write_u16(&mut rle, id);
+        i += run;
+    }
+
+    if rle.len() <= 1 + blocks.len() * 2 {
+        return rle;
+    }
+    let mut raw = vec![Compression::None as u8];
+    for &id in blocks.iter() {
+        write_u16(&mut raw, id);
+    }
+    raw
+}
+
+pub fn decode_section(section: &[u8]) -> Result<Vec<BlockId>> {
+    let bad = |detail: &str| Error::with_detail("region error", detail.to_string());
+    if section.is_empty() {
+        return Err(bad("empty section"));
+    }
+    let data = &section[1..];
+    let mut blocks = Vec::with_capacity(CHUNK_VOLUME);
+
+    match section[0] {
+        c if c == Compression::None as u8 => {
+            if data.len() != CHUNK_VOLUME * 2 {
+                return Err(bad("section has the wrong size"));
+            }
+            for i in 0..CHUNK_VOLUME {
+                blocks.push(read_u16(data, i * 2));
+            }
+        },
+        c if c == Compression::RunLength as u8 => {
+            if data.len() % 4 != 0 {
+                return Err(bad("truncated run"));
+            }
+            for run in data.chunks(4) {
+                let (count, id) = (read_u16(run, 0) as usize, read_u16(run, 2));
+                if blocks.len() + count > CHUNK_VOLUME {
+                    return Err(bad("section has the wrong size"));
+                }
+                for _ in 0..count {
+                    blocks.push(id);
+                }
+            }
+            if blocks.len() != CHUNK_VOLUME {
+                return Err(bad("section has the wrong size"));
+            }
+        },
+        _ => return Err(bad("unknown compression")),
+    }
+    Ok(blocks)
+}
+
+fn read_u16(buf: &[u8], at: usize) -> u16 {
+    buf[at] as u16 | (buf[at + 1] as u16) << 8
+}
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    read_u16(buf, at) as u32 | (read_u16(buf, at + 2) as u32) << 16
+}
+
+fn write_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.push(v as u8);
+    buf.push((v >> 8) as u8);
+}
+
+fn write_u32(buf: &mut Vec<u8>, v: u32) {
+    write_u16(buf, v as u16);
+    write_u16(buf, (v >> 16) as u16);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{
+        self,
+        File,
+    };
+    use std::io::prelude::*;
+    use std::path::PathBuf;
+
+    use world::{
+        BlockId,
+        Chunk,
+        ChunkPos,
+    };
+    use world::chunk::CHUNK_VOLUME;
+    use super::{
+        decode_section,
+        encode_section,
+        Compression,
+        RegionPos,
+        RegionStore,
+        REGION_SIZE,
+    };
+
+    // A fresh directory for one test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("cube-region-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // Ground with a few scattered blocks above it.
+    fn chunk(pos: ChunkPos, seed: BlockId) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set(x, 0, z, 1);
+                chunk.set(x, 1 + (x * z) % 5, z, seed + (x % 3) as BlockId);
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn sections_round_trip() {
+        let uniform = vec![7; CHUNK_VOLUME];
+        let section = encode_section(&uniform);
+        assert_eq!(section[0], Compression::RunLength as u8);
+        assert!(decode_section(&section).unwrap() == uniform);
+
+        // Runs of one block compress worse than the raw ids.
+        let noisy: Vec<BlockId> = (0..CHUNK_VOLUME).map(|i| i as BlockId).collect();
+        let section = encode_section(&noisy);
+        assert_eq!(section[0], Compression::None as u8);
+        assert!(decode_section(&section).unwrap() == noisy);
+    }
+
+    #[test]
+    fn regions_of_negative_chunks() {
+        assert_eq!(RegionPos::of(ChunkPos::new(0, 0)), RegionPos { x: 0, z: 0 });
+        assert_eq!(RegionPos::of(ChunkPos::new(-1, REGION_SIZE)), RegionPos { x: -1, z: 1 });
+        assert_eq!(RegionPos::of(ChunkPos::new(-REGION_SIZE - 1, -REGION_SIZE)),
+                   RegionPos { x: -2, z: -1 });
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let dir = temp_dir("round-trip");
+        let positions = [ChunkPos::new(0, 0),
+                         ChunkPos::new(1, 0),
+                         ChunkPos::new(-1, -1),
+                         ChunkPos::new(REGION_SIZE, -REGION_SIZE - 3)];
+        let chunks: Vec<Chunk> = positions.iter()
+            .enumerate()
+            .map(|(i, &pos)| chunk(pos, 2 + i as BlockId))
+            .collect();
+        {
+            let mut store = RegionStore::new(&dir).unwrap();
+            store.save_chunks(chunks.iter()).unwrap();
+        }
+
+        // A new store has nothing cached, so everything comes off the disk.
+        let mut store = RegionStore::new(&dir).unwrap();
+        for chunk in chunks.iter() {
+            let loaded = store.load_chunk(chunk.position()).unwrap().unwrap();
+            assert_eq!(loaded.position(), chunk.position());
+            assert!(loaded.blocks() == chunk.blocks());
+        }
+        assert!(store.load_chunk(ChunkPos::new(2, 0)).unwrap().is_none());
+        assert!(store.load_chunk(ChunkPos::new(5 * REGION_SIZE, 0)).unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saving_keeps_other_chunks() {
+        let dir = temp_dir("keep");
+        let (a, b) = (chunk(ChunkPos::new(0, 0), 2), chunk(ChunkPos::new(3, 4), 5));
+        {
+            let mut store = RegionStore::new(&dir).unwrap();
+            store.save_chunks(Some(&a).into_iter()).unwrap();
+        }
+        {
+            let mut store = RegionStore::new(&dir).unwrap();
+            store.save_chunks(Some(&b).into_iter()).unwrap();
+        }
+        let mut store = RegionStore::new(&dir).unwrap();
+        assert!(store.load_chunk(a.position()).unwrap().unwrap().blocks() == a.blocks());
+        assert!(store.load_chunk(b.position()).unwrap().unwrap().blocks() == b.blocks());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let dir = temp_dir("bad");
+        fs::create_dir_all(&dir).unwrap();
+        File::create(dir.join("r.0.0.region")).unwrap().write_all(b"CUBR").unwrap();
+        let mut store = RegionStore::new(&dir).unwrap();
+        assert!(store.load_chunk(ChunkPos::new(0, 0)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
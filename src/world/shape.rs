@@ -0,0 +1,523 @@
+use cube::Vertex;
+use resource::atlas::Atlas;
+use world::{
+    Blocks,
+    BlockId,
+    BlockPos,
+    Face,
+    AIR,
+};
+use world::mesh::{
+    self,
+    BlockTypes,
+    Shape,
+};
+
+const HORIZONTAL: [Face; 4] = [Face::Left, Face::Right, Face::Back, Face::Front];
+
+// Boxes this close together count as touching, so rounding can't let a moving
+// box slip into a block it was stopped against.
+const EPSILON: f32 = 1e-4;
+
+// A box inside a block, in sixteenths of a block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Part {
+    pub min: [i32; 3],
+    pub max: [i32; 3],
+}
+
+// A box in world space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    // A box `width` across and `height` tall, standing on `feet`.
+    pub fn standing(feet: [f32; 3], width: f32, height: f32) -> Aabb {
+        let r = width / 2.0;
+        Aabb {
+            min: [feet[0] - r, feet[1], feet[2] - r],
+            max: [feet[0] + r, feet[1] + height, feet[2] + r],
+        }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] < other.max[i] && other.min[i] < self.max[i])
+    }
+
+    // Distance along `direction`, a unit vector, from `origin` to where a ray
+    // enters this box, 0 if it starts inside. `None` if it misses.
+    pub fn ray(&self, origin: [f32; 3], direction: [f32; 3]) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = ::std::f32::INFINITY;
+        for i in 0..3 {
+            if direction[i] == 0.0 {
+                if origin[i] < self.min[i] || origin[i] > self.max[i] {
+                    return None;
+                }
+            } else {
+                let a = (self.min[i] - origin[i]) / direction[i];
+                let b = (self.max[i] - origin[i]) / direction[i];
+                near = near.max(a.min(b));
+                far = far.min(a.max(b));
+            }
+        }
+        if near <= far { Some(near) } else { None }
+    }
+
+    pub fn translate(&self, d: [f32; 3]) -> Aabb {
+        let mut aabb = *self;
+        for i in 0..3 {
+            aabb.min[i] += d[i];
+            aabb.max[i] += d[i];
+        }
+        aabb
+    }
+
+    // How much of a move by `d` along `axis` this box can make before running
+    // into `other`. Boxes that already overlap don't hold each other up.
+    pub fn clip(&self, other: &Aabb, axis: usize, d: f32) -> f32 {
+        let across = (0..3)
+            .filter(|&i| i != axis)
+            .all(|i| self.min[i] < other.max[i] && other.min[i] < self.max[i]);
+        if !across {
+            d
+        } else if d > 0.0 && other.min[axis] >= self.max[axis] - EPSILON {
+            d.min((other.min[axis] - self.max[axis]).max(0.0))
+        } else if d < 0.0 && other.max[axis] <= self.min[axis] + EPSILON {
+            d.max((other.max[axis] - self.min[axis]).min(0.0))
+        } else {
+            d
+        }
+    }
+}
+
+// Geometry of blocks that join up with their horizontal neighbours, like fences
+// and glass panes: a post in the middle and, towards each joined side, one arm
+// per bar. All sizes are in sixteenths of a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connected {
+    // Half the width of the post.
+    post: i32,
+    // Half the thickness of the arms.
+    arm: i32,
+    // Bottom and top of each arm.
+    bars: Vec<(i32, i32)>,
+    // Height of the collision box, which may be taller than the block.
+    collision: i32,
+}
+
+impl Connected {
+    pub fn new(post: i32, arm: i32, bars: Vec<(i32, i32)>, collision: i32) -> Connected {
+        Connected {
+            post: post,
+            arm: arm,
+            bars: bars,
+            collision: collision,
+        }
+    }
+
+    pub fn collision(&self) -> i32 {
+        self.collision
+    }
+
+    // The post and the arms towards `sides`.
+    pub fn parts(&self, sides: &[Face]) -> Vec<(Part, Option<Face>)> {
+        let (p, a) = (self.post, self.arm);
+        let mut parts = vec![(Part { min: [8 - p, 0, 8 - p], max: [8 + p, 16, 8 + p] }, None)];
+        for &side in sides.iter() {
+            for &(y0, y1) in self.bars.iter() {
+                let part = match side {
+                    Face::Left => Part { min: [0, y0, 8 - a], max: [8 - p, y1, 8 + a] },
+                    Face::Right => Part { min: [8 + p, y0, 8 - a], max: [16, y1, 8 + a] },
+                    Face::Back => Part { min: [8 - a, y0, 0], max: [8 + a, y1, 8 - p] },
+                    Face::Front => Part { min: [8 - a, y0, 8 + p], max: [8 + a, y1, 16] },
+                    _ => continue,
+                };
+                parts.push((part, Some(side)));
+            }
+        }
+        parts
+    }
+}
+
+// Whether block `id` at a connected block joins up with `neighbour`.
+pub fn connects<T>(types: &T, id: BlockId, neighbour: BlockId) -> bool
+    where T: BlockTypes
+{
+    if neighbour == AIR {
+        false
+    } else if types.connected(neighbour).is_some() {
+        types.group(neighbour) == types.group(id)
+    } else {
+        types.is_opaque(neighbour) && types.shape(neighbour) == Shape::Cube
+    }
+}
+
+// The horizontal sides the connected block at `pos` joins up with.
+pub fn sides<B, T>(blocks: &B, types: &T, pos: BlockPos) -> Vec<Face>
+    where B: Blocks,
+          T: BlockTypes,
+{
+    let id = blocks.block(pos);
+    HORIZONTAL.iter()
+        .cloned()
+        .filter(|&face| connects(types, id, blocks.block(pos.neighbour(face))))
+        .collect()
+}
+
+// One box of a connected block placed in the world.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Piece {
+    pub pos: BlockPos,
+    pub part: Part,
+    pub block: BlockId,
+    // The side an arm reaches out to, for the post `None`.
+    pub side: Option<Face>,
+}
+
+impl Piece {
+    // Textures are cut from the block's tiles where the box lies in the block,
+    // so neighbouring pieces line up.
+    pub fn vertices<T>(&self, types: &T, atlas: &Atlas) -> Vec<Vertex>
+        where T: BlockTypes
+    {
+        let mut lo = [0.0; 3];
+        let mut hi = [0.0; 3];
+        for i in 0..3 {
+            lo[i] = self.part.min[i] as f32 / 16.0;
+            hi[i] = self.part.max[i] as f32 / 16.0;
+        }
+        let origin = [self.pos.x as f32, self.pos.y as f32, self.pos.z as f32];
+
+        let mut vertices = Vec::with_capacity(36);
+        for &face in Face::all().iter() {
+            // The arm's end against the post, or against whatever it joins.
+            if let Some(side) = self.side {
+                if face == side || face == side.opposite() {
+                    continue;
+                }
+            }
+            let uv = atlas.uv(types.tile(self.block, face));
+            let shine = types.shine(self.block).unwrap_or(0.0);
+            let alpha = types.alpha(self.block);
+            let c = mesh::box_corners(face, lo, hi);
+            let n = face.normal();
+            let v = |i: usize| {
+                let (u, v) = face_uv(face, c[i]);
+                Vertex::new(origin[0] + c[i][0], origin[1] + c[i][1], origin[2] + c[i][2],
+                            n[0], n[1], n[2],
+                            u, v).with_tile(uv.rect()).with_shine(shine).with_alpha(alpha)
+            };
+            vertices.extend([v(0), v(1), v(2), v(2), v(3), v(0)].iter().cloned());
+        }
+        vertices
+    }
+}
+
+// Texture coordinates of a point on `face` of the unit block, matching the
+// orientation `Quad` gives full faces.
+fn face_uv(face: Face, p: [f32; 3]) -> (f32, f32) {
+    match face {
+        Face::Right => (1.0 - p[2], p[1]),
+        Face::Left => (p[2], p[1]),
+        Face::Front => (p[0], p[1]),
+        Face::Back => (1.0 - p[0], p[1]),
+        Face::Top => (p[0], 1.0 - p[2]),
+        Face::Bottom => (p[0], p[2]),
+    }
+}
+
+// Every piece of the connected blocks in the box starting at `min` with `size`.
+pub fn pieces<B, T>(blocks: &B, types: &T, min: BlockPos, size: [i32; 3]) -> Vec<Piece>
+    where B: Blocks,
+          T: BlockTypes,
+{
+    let mut pieces = Vec::new();
+    for y in 0..size[1] {
+        for z in 0..size[2] {
+            for x in 0..size[0] {
+                let pos = min.offset(x, y, z);
+                let id = blocks.block(pos);
+                let connected = match types.connected(id) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                for (part, side) in connected.parts(&sides(blocks, types, pos)) {
+                    pieces.push(Piece {
+                        pos: pos,
+                        part: part,
+                        block: id,
+                        side: side,
+                    });
+                }
+            }
+        }
+    }
+    pieces
+}
+
+// The boxes the block at `pos` blocks movement with.
+pub fn collision_boxes<B, T>(blocks: &B, types: &T, pos: BlockPos) -> Vec<Aabb>
+    where B: Blocks,
+          T: BlockTypes,
+{
+    let id = blocks.block(pos);
+    let origin = [pos.x as f32, pos.y as f32, pos.z as f32];
+    let to_world = |part: &Part| {
+        let mut aabb = Aabb { min: origin, max: origin };
+        for i in 0..3 {
+            aabb.min[i] += part.min[i] as f32 / 16.0;
+            aabb.max[i] += part.max[i] as f32 / 16.0;
+        }
+        aabb
+    };
+
+    if id == AIR {
+        return Vec::new();
+    }
+    if let Some(connected) = types.connected(id) {
+        return connected.parts(&sides(blocks, types, pos))
+            .iter()
+            .map(|&(part, _)| {
+                let mut part = part;
+                part.min[1] = 0;
+                part.max[1] = connected.collision();
+                to_world(&part)
+            })
+            .collect();
+    }
+    match types.shape(id) {
+        Shape::Cube => vec![to_world(&Part { min: [0, 0, 0], max: [16, 16, 16] })],
+        _ => Vec::new(),
+    }
+}
+
+// The collision boxes of the `solid` blocks near enough to `aabb` to touch it.
+// Cells below the box are looked at too, since a connected block's collision
+// can reach up out of its cell.
+pub fn colliders<B, T, F>(blocks: &B, types: &T, aabb: &Aabb, solid: &F) -> Vec<Aabb>
+    where B: Blocks,
+          T: BlockTypes,
+          F: Fn(BlockId) -> bool,
+{
+    let lo: Vec<i32> = (0..3).map(|i| aabb.min[i].floor() as i32).collect();
+    let hi: Vec<i32> = (0..3).map(|i| aabb.max[i].ceil() as i32).collect();
+    let mut boxes = Vec::new();
+    for y in lo[1] - 1..hi[1] {
+        for z in lo[2]..hi[2] {
+            for x in lo[0]..hi[0] {
+                let pos = BlockPos::new(x, y, z);
+                if solid(blocks.block(pos)) {
+                    boxes.extend(collision_boxes(blocks, types, pos));
+                }
+            }
+        }
+    }
+    boxes
+}
+
+// Whether `aabb` overlaps any solid block.
+pub fn collides<B, T, F>(blocks: &B, types: &T, aabb: &Aabb, solid: &F) -> bool
+    where B: Blocks,
+          T: BlockTypes,
+          F: Fn(BlockId) -> bool,
+{
+    colliders(blocks, types, aabb, solid).iter().any(|other| aabb.intersects(other))
+}
+
+// How much of `motion` `aabb` can make without running into solid blocks.
+// The box moves along one axis at a time, vertically first, so it slides
+// along whatever stops it.
+pub fn slide<B, T, F>(blocks: &B,
+                      types: &T,
+                      aabb: &Aabb,
+                      motion: [f32; 3],
+                      solid: &F) -> [f32; 3]
+    where B: Blocks,
+          T: BlockTypes,
+          F: Fn(BlockId) -> bool,
+{
+    let mut aabb = *aabb;
+    let mut moved = [0.0; 3];
+    for &axis in [1, 0, 2].iter() {
+        if motion[axis] == 0.0 {
+            continue;
+        }
+        let mut step = [0.0; 3];
+        step[axis] = motion[axis];
+        let mut swept = aabb;
+        for i in 0..3 {
+            swept.min[i] = aabb.min[i].min(aabb.min[i] + step[i]);
+            swept.max[i] = aabb.max[i].max(aabb.max[i] + step[i]);
+        }
+        let d = colliders(blocks, types, &swept, solid)
+            .iter()
+            .fold(motion[axis], |d, other| aabb.clip(other, axis, d));
+        step[axis] = d;
+        aabb = aabb.translate(step);
+        moved[axis] = d;
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use world::{
+        BlockId,
+        BlockPos,
+        BlocksMut,
+        Face,
+        Grid,
+        AIR,
+    };
+    use world::mesh::{
+        BlockTypes,
+        Shape,
+        TileId,
+    };
+    use super::{
+        collides,
+        collision_boxes,
+        slide,
+        Aabb,
+        Connected,
+    };
+
+    const STONE: BlockId = 1;
+    const FENCE: BlockId = 2;
+
+    // Stone is a cube, and fences a post with two bars whose collision is a
+    // block and a half tall.
+    struct Types(Connected);
+
+    impl Types {
+        fn new() -> Types {
+            Types(Connected::new(2, 1, vec![(6, 9), (12, 15)], 24))
+        }
+    }
+
+    impl BlockTypes for Types {
+        fn is_opaque(&self, id: BlockId) -> bool {
+            id == STONE
+        }
+
+        fn tile(&self, _: BlockId, _: Face) -> TileId {
+            0
+        }
+
+        fn shape(&self, id: BlockId) -> Shape {
+            if id == FENCE { Shape::Connected } else { Shape::Cube }
+        }
+
+        fn connected(&self, id: BlockId) -> Option<&Connected> {
+            if id == FENCE { Some(&self.0) } else { None }
+        }
+    }
+
+    fn solid(id: BlockId) -> bool {
+        id != AIR
+    }
+
+    fn unit(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb { min: [x, y, z], max: [x + 1.0, y + 1.0, z + 1.0] }
+    }
+
+    #[test]
+    fn touching_boxes_do_not_intersect() {
+        assert!(unit(0.0, 0.0, 0.0).intersects(&unit(0.5, 0.5, 0.5)));
+        assert!(!unit(0.0, 0.0, 0.0).intersects(&unit(1.0, 0.0, 0.0)));
+        assert!(!unit(0.0, 0.0, 0.0).intersects(&unit(0.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn rays() {
+        let a = unit(2.0, 0.0, 0.0);
+        assert_eq!(a.ray([0.0, 0.5, 0.5], [1.0, 0.0, 0.0]), Some(2.0));
+        assert_eq!(a.ray([2.5, 0.5, 0.5], [1.0, 0.0, 0.0]), Some(0.0));
+        assert_eq!(a.ray([0.0, 0.5, 0.5], [-1.0, 0.0, 0.0]), None);
+        assert_eq!(a.ray([0.0, 1.5, 0.5], [1.0, 0.0, 0.0]), None);
+        let d = 0.5f32.sqrt();
+        assert!((a.ray([1.0, -1.0, 0.5], [d, d, 0.0]).unwrap() - 2.0f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clipping() {
+        let a = unit(0.0, 0.0, 0.0);
+        let b = unit(2.0, 0.0, 0.0);
+        assert_eq!(a.clip(&b, 0, 3.0), 1.0);
+        assert_eq!(a.clip(&b, 0, 0.5), 0.5);
+        assert_eq!(a.clip(&b, 0, -3.0), -3.0);
+        assert_eq!(b.clip(&a, 0, -3.0), -1.0);
+        // Boxes that don't line up across the move pass each other by.
+        assert_eq!(a.clip(&unit(2.0, 1.0, 0.0), 0, 3.0), 3.0);
+        // Nor do boxes already overlapping.
+        assert_eq!(a.clip(&unit(0.5, 0.0, 0.0), 0, 3.0), 3.0);
+    }
+
+    #[test]
+    fn fences_collide_higher_than_a_block() {
+        let mut grid = Grid::new(3, 3, 3);
+        grid.set_block(BlockPos::new(1, 0, 1), FENCE);
+        let types = Types::new();
+        let boxes = collision_boxes(&grid, &types, BlockPos::new(1, 0, 1));
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].max[1], 1.5);
+
+        // Standing on the block above the post is still inside its collision.
+        let above = Aabb::standing([1.5, 1.0, 1.5], 0.6, 1.8);
+        assert!(collides(&grid, &types, &above, &solid));
+        assert!(!collides(&grid, &types, &above.translate([0.0, 0.5, 0.0]), &solid));
+
+        // Something walking into the post at the height of the block above it
+        // is stopped.
+        let walker = Aabb::standing([0.5, 1.25, 1.5], 0.6, 1.0);
+        let moved = slide(&grid, &types, &walker, [2.0, 0.0, 0.0], &solid);
+        assert!((moved[0] - (1.0 + 6.0 / 16.0 - 0.8)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn fences_join_up() {
+        let mut grid = Grid::new(3, 1, 1);
+        grid.set_block(BlockPos::new(0, 0, 0), FENCE);
+        grid.set_block(BlockPos::new(1, 0, 0), FENCE);
+        let types = Types::new();
+        // A post and an arm per bar, all as tall as the collision.
+        let boxes = collision_boxes(&grid, &types, BlockPos::new(0, 0, 0));
+        assert_eq!(boxes.len(), 3);
+        assert!(boxes.iter().all(|b| b.min[1] == 0.0 && b.max[1] == 1.5));
+        assert!(collision_boxes(&grid, &types, BlockPos::new(2, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn falling_lands_on_blocks() {
+        let mut grid = Grid::new(1, 2, 1);
+        grid.set_block(BlockPos::new(0, 0, 0), STONE);
+        let types = Types::new();
+        let aabb = Aabb::standing([0.5, 3.0, 0.5], 0.6, 1.8);
+        let moved = slide(&grid, &types, &aabb, [0.0, -5.0, 0.0], &solid);
+        assert!((moved[1] + 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sliding_along_a_wall() {
+        let mut grid = Grid::new(3, 2, 3);
+        for z in 0..3 {
+            grid.set_block(BlockPos::new(2, 0, z), STONE);
+            grid.set_block(BlockPos::new(2, 1, z), STONE);
+        }
+        let types = Types::new();
+        let aabb = Aabb::standing([1.5, 0.0, 0.5], 0.6, 1.8);
+        let moved = slide(&grid, &types, &aabb, [1.0, 0.0, 1.0], &solid);
+        assert!((moved[0] - 0.2).abs() < 1e-4);
+        assert_eq!(moved[2], 1.0);
+
+        // Once against the wall it stays there.
+        let against = aabb.translate(moved);
+        let again = slide(&grid, &types, &against, [1.0, 0.0, 0.0], &solid);
+        assert!(again[0].abs() < 1e-4);
+        assert!(!collides(&grid, &types, &against, &solid));
+    }
+}
@@ -0,0 +1,136 @@
+use rand::{
+    Rng,
+    SeedableRng,
+    XorShiftRng,
+};
+
+use world::{
+    BlockPos,
+    ChunkPos,
+    World,
+    CHUNK_WIDTH,
+    CHUNK_DEPTH,
+    CHUNK_HEIGHT,
+};
+
+// Height of the slices of a chunk that each get their own random ticks, so a
+// block is picked as often however tall the chunk is.
+pub const SECTION_HEIGHT: usize = 16;
+
+// Picks random blocks in every loaded chunk to update, so slow processes such as
+// crop growth happen at a steady average rate without visiting every block.
+// Chunks are visited in position order and all choices come from one seeded rng,
+// so the same seed and world give the same updates.
+pub struct RandomTicks {
+    rng: XorShiftRng,
+    per_section: u32,
+}
+
+impl RandomTicks {
+    pub fn new(seed: [u32; 4], per_section: u32) -> RandomTicks {
+        RandomTicks {
+            rng: XorShiftRng::from_seed(seed),
+            per_section: per_section,
+        }
+    }
+
+    pub fn per_section(&self) -> u32 {
+        self.per_section
+    }
+
+    pub fn tick<F>(&mut self, world: &mut World, mut update: F)
+        where F: FnMut(&mut World, BlockPos, &mut XorShiftRng)
+    {
+        let mut chunks: Vec<ChunkPos> = world.chunks().keys().cloned().collect();
+        chunks.sort_by(|a, b| (a.x, a.z).cmp(&(b.x, b.z)));
+
+        let h = SECTION_HEIGHT as i32;
+        for pos in chunks {
+            let origin = pos.origin();
+            for section in 0..(CHUNK_HEIGHT / SECTION_HEIGHT) as i32 {
+                for _ in 0..self.per_section {
+                    let block = origin.offset(self.rng.gen_range(0, CHUNK_WIDTH as i32),
+                                              section * h + self.rng.gen_range(0, h),
+                                              self.rng.gen_range(0, CHUNK_DEPTH as i32));
+                    update(world, block, &mut self.rng);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use farm::Farming;
+    use world::{
+        BlockId,
+        BlockPos,
+        Blocks,
+        Chunk,
+        ChunkPos,
+        World,
+    };
+    use super::RandomTicks;
+
+    const FARMLAND: BlockId = 3;
+    const STAGES: [BlockId; 4] = [10, 11, 12, 13];
+
+    fn farming(growth: f32) -> Farming {
+        Farming::with_blocks(1, 2, FARMLAND, STAGES, growth)
+    }
+
+    // One chunk with a crop planted at `y`.
+    fn field(y: i32) -> (World, BlockPos) {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        chunk.set(5, y - 1, 7, FARMLAND);
+        chunk.set(5, y, 7, STAGES[0]);
+        let mut world = World::new();
+        world.insert(chunk);
+        (world, BlockPos::new(5, y, 7))
+    }
+
+    // The stage of the crop after each of `n` ticks.
+    fn grow(seed: [u32; 4], growth: f32, y: i32, n: usize) -> Vec<usize> {
+        let farming = farming(growth);
+        let (mut world, crop) = field(y);
+        let mut ticks = RandomTicks::new(seed, 3);
+        let mut stages = Vec::with_capacity(n);
+        for _ in 0..n {
+            ticks.tick(&mut world, |world, pos, rng| farming.random_tick(world, pos, rng));
+            stages.push(farming.stage(world.block(crop)).unwrap());
+        }
+        stages
+    }
+
+    #[test]
+    fn crops_grow_one_stage_at_a_time() {
+        let stages = grow([1, 2, 3, 4], 1.0, 1, 40000);
+        for pair in stages.windows(2) {
+            assert!(pair[1] == pair[0] || pair[1] == pair[0] + 1);
+        }
+        // Each block of a section is picked 3 times in 4096 ticks on average,
+        // so three stages are due after about 4096 ticks.
+        assert_eq!(*stages.last().unwrap(), 3);
+        assert!(stages[100] < 3);
+    }
+
+    #[test]
+    fn sections_tick_alike() {
+        // A crop high in the chunk ripens about as quickly as one at the bottom.
+        let low = grow([5, 6, 7, 8], 1.0, 1, 40000);
+        let high = grow([5, 6, 7, 8], 1.0, 200, 40000);
+        assert_eq!(*low.last().unwrap(), 3);
+        assert_eq!(*high.last().unwrap(), 3);
+    }
+
+    #[test]
+    fn same_seed_same_growth() {
+        assert!(grow([9, 8, 7, 6], 0.5, 1, 8000) == grow([9, 8, 7, 6], 0.5, 1, 8000));
+        assert!(grow([9, 8, 7, 6], 0.5, 1, 8000) != grow([1, 1, 1, 1], 0.5, 1, 8000));
+    }
+
+    #[test]
+    fn no_growth_without_chance() {
+        assert!(grow([1, 2, 3, 4], 0.0, 1, 5000).iter().all(|&s| s == 0));
+    }
+}